@@ -0,0 +1,46 @@
+/*
+This example calls the functions from py/functions_venv.py from async code (tokio)
+
+Expected behavior:
+- an AsyncPython executor thread is started with a queue of 2 jobs
+- random_number() is called from 5 tasks at once; the calls run one after the other on the executor thread,
+  and the tasks that don't fit in the queue wait for room before their call is queued (backpressure)
+- a Python busy loop is started with a 200 ms tokio::time::timeout; when the timeout drops the future, the loop
+  is interrupted with examples.CallCancelled and the executor thread is free again
+- random_number() is called once more to show the executor still works after the cancellation
+ */
+
+use std::time::{Duration, Instant};
+
+use examples::async_python::AsyncPython;
+use examples::{Error, PyFunction};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    if let Err(e) = examples::activate_venv() {
+        println!("Could not activate the virtualenv because {e}");
+    }
+    let python = AsyncPython::start(2)?;
+    let random_number: PyFunction<(i64, i64), i64> = PyFunction::new("functions_venv", "random_number");
+
+    let tasks: Vec<_> = (1..=5)
+        .map(|i| {
+            let (python, random_number) = (python.clone(), random_number.clone());
+            tokio::spawn(async move { python.call(&random_number, (1, i * 10)).await })
+        })
+        .collect();
+    for task in tasks {
+        println!("random_number() returned {}", task.await.expect("the task panicked")?);
+    }
+
+    let started = Instant::now();
+    let busy_loop = python.run(|py| py.run("while True: pass", None, None).map_err(Error::from));
+    match tokio::time::timeout(Duration::from_millis(200), busy_loop).await {
+        Ok(result) => println!("\nthe busy loop returned {result:?}"),
+        Err(_) => println!("\nthe busy loop was cancelled after {:?}", started.elapsed()),
+    }
+
+    println!("random_number() returned {}", python.call(&random_number, (1, 10)).await?);
+    println!("finished after {:?}", started.elapsed());
+    Ok(())
+}
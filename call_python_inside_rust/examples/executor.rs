@@ -0,0 +1,51 @@
+/*
+This example calls function 7c from several Rust threads at once through a PyExecutor
+
+Expected behavior:
+- a PyExecutor is started with a queue of 4 jobs; its thread owns the Python interpreter
+- 8 threads each submit a call to python_function_venv_c() with a different color
+    - the calls run one at a time, in the order they were queued, on the executor thread
+    - once 4 calls are waiting, the next threads block in submit() until there is room (backpressure)
+- every thread prints the emoji it got back and how long its job waited in the queue and ran
+- main() prints the executor statistics: jobs completed, mean and max wait and run times, queue depth
+ */
+
+use std::thread;
+
+use examples::{Error, PyExecutor};
+
+const COLORS: [&str; 8] = ["red", "orange", "yellow", "green", "blue", "purple", "brown", "black"];
+
+fn main() -> Result<(), Error> {
+    if let Err(e) = examples::activate_venv() {
+        println!("Could not activate the virtualenv because {e}");
+    }
+    let executor = PyExecutor::start(4)?;
+
+    let threads: Vec<_> = COLORS
+        .iter()
+        .map(|color| {
+            let executor = executor.clone();
+            thread::spawn(move || -> Result<String, Error> {
+                let job = executor.submit(move |_py| examples::python_function_venv_c(color))?;
+                let timed = job.wait_timed();
+                let emoji = timed.value?.map(|emoji| emoji.emoji).unwrap_or_default();
+                Ok(format!("{color:>6}: {emoji} waited {:?}, ran {:?}", timed.timing.waited, timed.timing.ran))
+            })
+        })
+        .collect();
+    println!("queue depth right after submitting: {}", executor.queue_depth());
+
+    let results: Vec<_> = threads.into_iter().map(|thread| thread.join().expect("a calling thread panicked")).collect();
+    println!("\n---------------------------------------------------------------------------");
+    for result in results {
+        match result {
+            Ok(line) => println!("{line}"),
+            Err(e) => println!("the call failed because {}", e.report()),
+        }
+    }
+    let stats = executor.stats();
+    println!("\n{stats:?}");
+    println!("mean wait {:?}, mean run {:?}", stats.mean_wait(), stats.mean_run());
+    Ok(())
+}
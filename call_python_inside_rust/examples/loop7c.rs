@@ -5,11 +5,11 @@ Expected behavior:
 - Prompt the user to type a color
 - call the function from example 7c in lib, while supplying the users text input 
 - inside the example 7c function, the pyo3 crate is used to create a Python session inside Rust
-    - The return value of this function is in the form Result<Option<char>, Error>
-    - the Python Module is created from the contents of py/functions_venv.py file
+    - The return value of this function is in the form Result<Option<Emoji>, Error>
+    - the Python Module is created from the contents of py/functions_venv.py file (only on the first pass; later passes reuse the cached module)
     - the Python function "def color_emoji()" is loaded from the PyModule
     - color_emoji() is executed using the users text input
-        - an error is raised if the text input contains a number
+        - an error is raised if the text input contains anything but letters (ex. a number)
         - the supplied color name is converted into the appropriate CLDR shortname to display a colored circle
             - ex: "Red" into ":red_circle:"
         - attempt to convert the CLDR shortname into an emoji using the python emoji module
@@ -17,35 +17,81 @@ Expected behavior:
             - If the CLDR shortname does not match any emoji, the operation completes and color_emoji() returns None  
     - the rust example function receives a Result containing either OK(PyAny) (if the operation completed) or else PyErr
     - this result is converted into Rust-compatible types and then returned to main()
-        - If OK: The PyAny is a python Optional which can manually converted to Rust Option Enum and returned inside Rust Result Enum
-        - If PyErr: convert into Rust Err and return inside Rust Result Enum
-    - main() receives the Result<Option<char>,Error> returned by the example fnuction and displays the results
+        - If OK: Python's None becomes Rust None and an emoji string is looked up in emoji.EMOJI_DATA and returned as Some(Emoji) inside Rust Result Enum
+        - If PyErr: convert into the matching examples::Error variant (ex. Error::Value for a color name with numbers) and return inside Rust Result Enum
+    - main() receives the Result<Option<Emoji>,Error> returned by the example fnuction and displays the results
+    - if py/functions_venv.py was edited since the last pass, it is recompiled before the function runs (hot reload)
     - the user is prompted to either try again or quit
     - based on user input, either the loop restarts or ends
  */
 
-use examples;
+use examples::ModuleLoader;
+use pyo3::prelude::*;
+
 fn main() {
+    // Python's logging ends up here too; RUST_LOG=debug shows more
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    // use env/ (or $VIRTUAL_ENV) so emoji can be imported without `source env/bin/activate`
+    match examples::activate_venv() {
+        Ok(environment) => println!("{environment}"),
+        Err(e) => println!("Could not activate the virtualenv because {e}"),
+    }
+
+    // hot reload: edit py/functions_venv.py while the loop is running and the next pass uses the new code
+    // if the edit doesn't compile, the last good version keeps running and the syntax error is shown
+    let hot_reload = Python::with_gil(|py| ModuleLoader::with_global(py, |loader| {
+        loader.set_hot_reload(true);
+        Ok(())
+    }));
+    if let Err(e) = hot_reload {
+        println!("Could not turn on hot reload because {e}");
+    }
+
     loop {
         println!("\n\n---------------------------------------------------------------------------\n---------------------------------------------------------------------------\n");
+
+        // recompile py/functions_venv.py if it was edited since the last pass
+        match Python::with_gil(|py| ModuleLoader::with_global(py, |loader| Ok(loader.reload_changed(py)))) {
+            Ok(reload_events) => reload_events.iter().for_each(|event| println!("{event}\n")),
+            Err(e) => println!("Could not check for edited files because {e}"),
+        }
         
         // user input selects a color to be displayed
-        println!("Enter a color to be Displayed: ");
-        let input = examples::get_user_input();
+        println!("Enter a color (or any emoji name) to be Displayed: ");
+        let input = match examples::get_user_input() {
+            Ok(input) => input,
+            Err(e) => {
+                println!("Could not read input because {e}");
+                break;
+            }
+        };
 
     
         // run the example
-        println!("\nExample 7c: functions requiring packages installed on venv -- returns Result<Option<char>, Error>");
-        let _r7c = match examples::python_function_venv_c(&input) {
-            Ok(n) =>     println!("\nPy Function 7c success!! \nThe result was Ok({n:?}) \n"),
-            Err(e) =>     println!("\nPy Function 7c failed because {e}...\n"),
+        println!("\nExample 7c: functions requiring packages installed on venv -- returns Result<Option<Emoji>, Error>");
+        match examples::python_function_venv_c(&input) {
+            Ok(Some(emoji)) =>     println!("\nPy Function 7c success!! \nThe result was {emoji} ({} code point(s))\n", emoji.code_points()),
+            Ok(None) =>     println!("\nPy Function 7c success!! \nThe result was None (no emoji with that name)\n"),
+            Err(e) =>     println!("\nPy Function 7c failed because {}\n", e.report()),
         };
     
         println!("\nEnd\n--------------------------------------------------\n");
-    
+        
+        // py/functions_venv.py is only compiled on the first pass (or after it is edited)
+        match examples::ModuleLoader::global_stats() {
+            Ok(stats) => println!("Module cache: {stats:?}"),
+            Err(e) => println!("Could not read the module cache stats because {e}"),
+        }
+        // what the shared loader holds, under the names it has in sys.modules
+        match Python::with_gil(|py| ModuleLoader::with_global(py, |loader| loader.loaded_modules(py))) {
+            Ok(modules) => modules.iter().for_each(|module| println!("    {} <- {}", module.module_name, module.source)),
+            Err(e) => println!("Could not list the loaded modules because {e}"),
+        }
+
         // try again or quit
         println!("\nExample complete...do you want to start again?");
-        if examples::get_user_confirmation() {
+        if examples::get_user_confirmation().unwrap_or(false) {
             println!("Restarting");
             continue;
         } else {
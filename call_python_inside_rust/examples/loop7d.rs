@@ -4,39 +4,73 @@ This example runs function 7d in a loop so you can quickly try out different inp
 Expected behavior:
 - call the function from example 7d in lib
 - inside the example 7d function, the pyo3 crate is used to create a Python session inside Rust
-    - The return value of this function is in the form Result<Option<i32>, Error>
-    - the Python Module is created from the contents of py/functions_venv.py file
+    - The return value of this function is in the form Result<Option<i64>, Error>
+    - the Python Module is created from the contents of py/functions_venv.py file (only on the first pass; later passes reuse the cached module)
     - the Python function "def random_number()" is loaded from the PyModule
     - random_number() is executed using the range 1, 10
         - a random number from 1 to 10 is generated and returned  
     - the rust example function receives a Result containing either OK(PyAny) (if the operation completed) or else PyErr
-        - If OK: The PyAny is converted into Rust i32 and returned inside an Option
+        - If OK: The PyAny is converted into Rust i64 and returned inside an Option
             - The option is Some if the number is Odd and None if the number is even
-        - If PyErr: convert into corresponding Rust Err and return inside Rust Result Enum
+        - If PyErr: convert into the matching examples::Error variant and return inside Rust Result Enum
     - Some simple arithmatic is performed on the value inside the Option (if it is not none) and it is returned to main() inside a Result 
     - main() receives the Result<Option<int>,Error> returned by the example fnuction and displays the results
+    - if py/functions_venv.py was edited since the last pass, it is recompiled before the function runs (hot reload)
     - the user is prompted to either try again or quit
     - based on user input, either the loop restarts or ends
  */
 
- use examples;
+ use examples::ModuleLoader;
+ use pyo3::prelude::*;
+
  fn main() {
+     // Python's logging ends up here too; RUST_LOG=debug shows more
+     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+     // use env/ (or $VIRTUAL_ENV) so emoji can be imported without `source env/bin/activate`
+     match examples::activate_venv() {
+         Ok(environment) => println!("{environment}"),
+         Err(e) => println!("Could not activate the virtualenv because {e}"),
+     }
+
+     // hot reload: edit py/functions_venv.py while the loop is running and the next pass uses the new code
+     // if the edit doesn't compile, the last good version keeps running and the syntax error is shown
+     let hot_reload = Python::with_gil(|py| ModuleLoader::with_global(py, |loader| {
+         loader.set_hot_reload(true);
+         Ok(())
+     }));
+     if let Err(e) = hot_reload {
+         println!("Could not turn on hot reload because {e}");
+     }
+
      loop {
          println!("\n\n---------------------------------------------------------------------------\n---------------------------------------------------------------------------\n");
+
+         // recompile py/functions_venv.py if it was edited since the last pass
+         match Python::with_gil(|py| ModuleLoader::with_global(py, |loader| Ok(loader.reload_changed(py)))) {
+             Ok(reload_events) => reload_events.iter().for_each(|event| println!("{event}\n")),
+             Err(e) => println!("Could not check for edited files because {e}"),
+         }
          
  
          // run the example
-         println!("\nExample 7d:  returns Result<Option<i32>, Error>");
-         let _r7c = match examples::python_function_venv_d(10, 1) {
+         println!("\nExample 7d:  returns Result<Option<i64>, Error>");
+         match examples::python_function_venv_d(10, 1) {
              Ok(n) =>     println!("\nPy Function 7c success!! \nThe result was Ok({n:?}) \n"),
-             Err(e) =>     println!("\nPy Function 7c failed because {e}...\n"),
+             Err(e) =>     println!("\nPy Function 7c failed because {}\n", e.report()),
          };
      
          println!("\nEnd\n--------------------------------------------------\n");
+         
+         // py/functions_venv.py is only compiled on the first pass (or after it is edited)
+         match examples::ModuleLoader::global_stats() {
+             Ok(stats) => println!("Module cache: {stats:?}"),
+             Err(e) => println!("Could not read the module cache stats because {e}"),
+         }
      
          // try again or quit
          println!("\nExample complete...do you want to start again?");
-         if examples::get_user_confirmation() {
+         if examples::get_user_confirmation().unwrap_or(false) {
              println!("Restarting");
              continue;
          } else {
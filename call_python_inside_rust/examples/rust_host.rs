@@ -0,0 +1,76 @@
+/*
+This example runs Python code that calls back into Rust through the rust_host module
+
+Expected behavior:
+- a config value and a progress handler are set on the Rust side
+- a word_count() function written in Rust is added to rust_host next to the standard ones
+- a module is loaded from the code below; it does `import rust_host` at the top like any other import
+- simulate() reads its config, seeds the Rust RNG, draws random numbers, logs through the Rust logger
+  (shown by env_logger with target rust_host) and reports progress, which the Rust handler prints
+- simulate() runs twice with the same seed and gets the same numbers both times
+- rust_host functions check their arguments: random_int("1", 6) raises a TypeError naming the parameter,
+  and random_int(6, 1) raises the ValueError the Rust function returned
+- inspect.signature() shows the Python signature built from the Rust types
+ */
+
+use std::sync::Arc;
+
+use examples::{host, Error, HostModule, PyFunction};
+use pyo3::prelude::*;
+use pyo3::types::PyModule;
+
+const SIMULATION: &str = r#"
+import inspect
+import rust_host
+
+def simulate(seed: int) -> list[int]:
+    rolls = int(rust_host.config("simulation.rolls") or "3")
+    rust_host.seed(seed)
+    results = []
+    for roll in range(rolls):
+        results.append(rust_host.random_int(1, 6))
+        rust_host.progress(roll + 1, rolls, "rolling dice")
+    rust_host.log("info", f"rolled {results}, {rust_host.word_count('three short words')} words counted in Rust")
+    return results
+
+def bad_calls() -> list[str]:
+    errors = []
+    for args in [("1", 6), (6, 1)]:
+        try:
+            rust_host.random_int(*args)
+        except (TypeError, ValueError) as e:
+            errors.append(f"{type(e).__name__}: {e}")
+    return errors
+
+def signatures() -> list[str]:
+    return [f"{name}{inspect.signature(getattr(rust_host, name))}" for name in ["log", "config", "random_int", "progress", "word_count"]]
+"#;
+
+fn main() -> Result<(), Error> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    host::set_config("simulation.rolls", "5");
+    host::set_progress_handler(Some(Arc::new(|progress: &examples::Progress| println!("    [{progress}]"))));
+
+    Python::with_gil(|py| -> Result<(), Error> {
+        // the standard functions are installed first, then word_count is added to them
+        host::install(py)?;
+        HostModule::new()
+            .function("word_count", "Count the words in text", &["text"], |text: String| Ok(text.split_whitespace().count() as i64))
+            .install(py)?;
+
+        let module: Py<PyModule> = examples::module_from_code(py, SIMULATION, "simulation.py", "simulation")?.into();
+        let simulate: PyFunction<(u64,), Vec<i64>> = PyFunction::from_module(module.clone_ref(py), "simulate");
+        let first = simulate.call_with_gil(py, (42,))?;
+        let second = simulate.call_with_gil(py, (42,))?;
+        println!("\nsimulate(42) returned {first:?}, then {second:?}\n");
+
+        let bad_calls: PyFunction<(), Vec<String>> = PyFunction::from_module(module.clone_ref(py), "bad_calls");
+        bad_calls.call_with_gil(py, ())?.iter().for_each(|error| println!("{error}"));
+
+        let signatures: PyFunction<(), Vec<String>> = PyFunction::from_module(module.clone_ref(py), "signatures");
+        println!();
+        signatures.call_with_gil(py, ())?.iter().for_each(|signature| println!("rust_host.{signature}"));
+        Ok(())
+    })
+}
@@ -0,0 +1,46 @@
+/*
+This example runs the functions from py/functions_venv.py in child worker processes instead of the embedded interpreter
+
+Expected behavior:
+- a WorkerPool with two workers is started, each one a separate `python` (the venv's, when env/ exists)
+- random_number() is called from several Rust threads at once; each call runs on whichever worker is free
+- color_emoji() is called with a bad color name: the ValueError comes back as Error::Value with the Python traceback,
+  the same as for an in-process call
+- os._exit() is called to kill a worker on purpose: the call returns Error::WorkerCrashed and the pool starts a new worker
+- random_number() is called again to show the pool still works after the crash
+ */
+
+use std::thread;
+
+use examples::{Error, WorkerOptions, WorkerPool};
+
+fn main() -> Result<(), Error> {
+    let pool = WorkerPool::new(WorkerOptions::default().workers(2))?;
+    println!("Started workers {:?} with {}\n", pool.pids(), WorkerOptions::default().python.display());
+
+    // same signature as the generated functions_venv::random_number
+    let random_number = pool.function::<(i64, i64), i64>("functions_venv", "random_number");
+    let calls: Vec<_> = (1..=4)
+        .map(|i| {
+            let random_number = random_number.clone();
+            thread::spawn(move || random_number.call((1, i * 10)))
+        })
+        .collect();
+    for call in calls {
+        println!("random_number() returned {:?}", call.join().expect("the calling thread panicked")?);
+    }
+
+    let color_emoji = pool.function::<(&str,), Option<String>>("functions_venv", "color_emoji");
+    match color_emoji.call(("red2",)) {
+        Ok(emoji) => println!("\ncolor_emoji() returned {emoji:?}"),
+        Err(e) => println!("\ncolor_emoji() failed because {}", e.report()),
+    }
+
+    match pool.call::<_, ()>("os", "_exit", (1,)) {
+        Err(e @ Error::WorkerCrashed { .. }) => println!("{e}"),
+        other => println!("os._exit() should have crashed the worker, got {other:?}"),
+    }
+    println!("Workers after {} restart(s): {:?}", pool.restarts(), pool.pids());
+    println!("random_number() returned {}", random_number.call((1, 10))?);
+    Ok(())
+}
@@ -1,5 +1,6 @@
 # standard library imports; no install or venv activation required
 import random    
+from typing import Optional
 # external library; install using "pip3 install <name>" or by running setup.sh which installs everything listed in requirements.txt
 import emoji     
 
@@ -9,12 +10,13 @@ def random_number(a: int = 0, b: int = 100)-> int:
     print("Here's a random number from {} to {}:  {}".format(a,b,n) )
     return n
 
-def emoji_test():
+def emoji_test() -> None:
     print(emoji.emojize('Rust is :thumbs_up:'))
 
 # This function is used only by example 7c
-# The when this function is called in Rust, the final output will be in the form Result<Option<char>, Error>
-def color_emoji(color:str):
+# The when this function is called in Rust, the final output will be in the form Result<Option<Emoji>, Error>
+# returning None is enough: None arrives in Rust as None (the generated binding returns Option<String>, example 7c binds it as Option<Emoji>)
+def color_emoji(color: str) -> Optional[str]:
     if not color.isalpha():
         raise ValueError("No numbers allowed in color name")
     # format string into color code
@@ -35,11 +37,21 @@ def color_emoji(color:str):
         else:
             print("No emoji with this name exists") 
             # return pythons version of Option None
-            return '0'
+            return None
 
     except:
         raise Exception("Error occured, could not create emoji")
 
+# This function is used only by example 7e
+# Look up any emoji by CLDR short name or alias, ex. "thumbs_up_medium_skin_tone", "jp" or ":family_man_woman_girl:"
+# the result can be several code points (skin tones, flags, keycaps, ZWJ sequences), so Rust receives it as a String
+def find_emoji(name: str) -> Optional[str]:
+    code = name if name.startswith(":") else ":" + name + ":"
+    found = emoji.emojize(code, language='alias')
+    if emoji.is_emoji(found):
+        return found
+    return None
+
 if __name__ == "__main__":
     emoji_test()
     color_emoji("red")
@@ -1,3 +1,3 @@
 #!/usr/bin/bash
-source env/bin/activate
-cargo run 
\ No newline at end of file
+# the binary finds env/ itself, activating it here is no longer needed
+cargo run
@@ -0,0 +1,118 @@
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+
+use crate::error::Error;
+use crate::executor::PyExecutor;
+use crate::function::PyFunction;
+use crate::signature::{PyAnnotation, PyArgs};
+
+pub use crate::executor::DEFAULT_QUEUE_CAPACITY;
+
+// Async front end for the Python call path, for use from tokio (or any other executor)
+// jobs run on a PyExecutor thread, so awaiting a call never blocks a runtime worker on the GIL
+//     let python = AsyncPython::start(DEFAULT_QUEUE_CAPACITY)?;
+//     let random_number: PyFunction<(i64, i64), i64> = PyFunction::new("functions_venv", "random_number");
+//     let n = python.call(&random_number, (1, 10)).await?;
+// the queue is bounded: when it is full, run() and call() wait for room before the job is queued (backpressure)
+// dropping the future cancels the job, ex. tokio::time::timeout or tokio::select!: a queued job is
+// skipped, and a running one gets examples.CallCancelled raised inside it
+// clones share the same thread and queue
+#[derive(Clone, Debug)]
+pub struct AsyncPython {
+    executor: PyExecutor,
+}
+
+impl AsyncPython {
+    // Start the executor thread; at most `queue_capacity` jobs wait in the queue
+    pub fn start(queue_capacity: usize) -> Result<AsyncPython, Error> {
+        Ok(AsyncPython { executor: PyExecutor::start(queue_capacity)? })
+    }
+
+    // the executor behind this, ex. to check its queue_depth()
+    pub fn executor(&self) -> &PyExecutor {
+        &self.executor
+    }
+
+    // Run `f` with the GIL on the executor thread
+    // Error::ExecutorStopped when the thread is gone (a job panicked)
+    pub async fn run<T: Send + 'static>(&self, f: impl FnOnce(Python<'_>) -> Result<T, Error> + Send + 'static) -> Result<T, Error> {
+        self.executor.submit_async(f).await?.await
+    }
+
+    // Async version of PyFunction::call
+    pub async fn call<Args, Ret>(&self, function: &PyFunction<Args, Ret>, args: Args) -> Result<Ret, Error>
+    where
+        Args: IntoPy<Py<PyTuple>> + PyArgs + Send + 'static,
+        Ret: for<'p> FromPyObject<'p> + PyAnnotation + Send + 'static,
+    {
+        let function = function.clone();
+        self.run(move |py| function.call_with_gil(py, args)).await
+    }
+
+    // room left in the queue
+    pub fn capacity(&self) -> usize {
+        self.executor.queue_capacity() - self.executor.queue_depth()
+    }
+}
+
+impl From<PyExecutor> for AsyncPython {
+    fn from(executor: PyExecutor) -> AsyncPython {
+        AsyncPython { executor }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    // room in the queue is freed when the executor picks the next job up
+    #[tokio::test]
+    async fn a_full_queue_makes_callers_wait() {
+        let python = AsyncPython::start(1).unwrap();
+        let (started, started_rx) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let running = python
+            .executor()
+            .submit_async(move |py| {
+                started.send(()).unwrap();
+                py.allow_threads(move || release_rx.recv().unwrap());
+                Ok(1)
+            })
+            .await
+            .unwrap();
+        tokio::task::spawn_blocking(move || started_rx.recv().unwrap()).await.unwrap();
+        let queued = python.executor().submit_async(|_py| Ok(2)).await.unwrap();
+        assert_eq!(python.capacity(), 0);
+
+        // the queue is full, so this waits (and the job is never queued when it gives up)
+        let waiting = tokio::time::timeout(Duration::from_millis(100), python.executor().submit_async(|_py| Ok(3))).await;
+        assert!(waiting.is_err());
+
+        release.send(()).unwrap();
+        let third = python.executor().submit_async(|_py| Ok(3)).await.unwrap();
+        assert_eq!((running.await.unwrap(), queued.await.unwrap(), third.await.unwrap()), (1, 2, 3));
+        assert_eq!(python.executor().stats().completed, 3);
+    }
+
+    #[tokio::test]
+    async fn dropped_futures_cancel_their_call() {
+        let python = AsyncPython::start(4).unwrap();
+        let busy_loop = python.run(|py| Ok(py.run("while True:\n    pass\n", None, None)?));
+        assert!(tokio::time::timeout(Duration::from_millis(200), busy_loop).await.is_err());
+
+        assert_eq!(python.run(|_py| Ok("free again")).await.unwrap(), "free again");
+        let stats = python.executor().stats();
+        assert_eq!((stats.cancelled, stats.completed), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn a_panicking_job_stops_the_executor() {
+        let python = AsyncPython::start(4).unwrap();
+        let panicked = python.run(|_py| -> Result<(), Error> { panic!("the job panicked") }).await;
+        assert!(matches!(panicked, Err(Error::ExecutorStopped)));
+        assert!(matches!(python.run(|_py| Ok(())).await, Err(Error::ExecutorStopped)));
+    }
+}
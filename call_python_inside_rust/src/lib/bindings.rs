@@ -0,0 +1,142 @@
+// Declare Rust functions that call Python functions of the same name
+//     examples::py_bindings! {
+//         module "functions_venv";
+//         pub fn random_number(a: i32, b: i32) -> i32;
+//         pub fn emoji_test();
+//         pub fn color_emoji(color: &str) -> Option<String>;
+//     }
+// generates
+//     pub fn random_number(a: i32, b: i32) -> Result<i32, examples::Error>
+//     pub fn emoji_test() -> Result<(), examples::Error>
+// each function goes through a PyFunction, so the module is imported through the shared ModuleLoader,
+// arguments and return values are converted the same way and every binding reports the same errors
+// (Error::MissingFunction, Error::ReturnType, or the variant of the exception Python raised)
+// string arguments can be borrowed (&str, Option<&str>) or owned; return values are always owned
+// the first call of each function checks the argument names and types against the Python signature,
+// and returns Error::SignatureMismatch without calling into Python if they disagree
+// the check runs again on the next call after a module is reloaded or unloaded
+// every block also gets
+//     pub fn validate_all() -> Result<(), examples::Error>
+// which imports the module and checks all the functions at once, so a mismatch shows up at startup
+// rather than at the first call (use one py_bindings! block per Rust module)
+#[macro_export]
+macro_rules! py_bindings {
+    (
+        module $module:literal;
+        $(
+            $(#[$attr:meta])*
+            $vis:vis fn $name:ident($($arg:ident : $arg_ty:ty),* $(,)?) $(-> $ret:ty)?;
+        )*
+    ) => {
+        $(
+            $(#[$attr])*
+            $vis fn $name($($arg: $arg_ty),*) -> Result<$crate::py_bindings!(@ret $($ret)?), $crate::Error> {
+                $crate::py_bindings!(@call $module, $name, ($($arg: $arg_ty),*) $(-> $ret)?)
+            }
+        )*
+
+        // check every function above against its Python signature, stopping at the first mismatch
+        #[allow(dead_code)]
+        pub fn validate_all() -> Result<(), $crate::Error> {
+            $crate::bindings::__private::Python::with_gil(|py| {
+                $(
+                    $crate::py_bindings!(@function $module, $name, ($($arg: $arg_ty),*) $(-> $ret)?).validate(py)?;
+                )*
+                Ok(())
+            })
+        }
+    };
+
+    // functions without `-> T` return () and ignore whatever Python returned
+    (@ret) => { () };
+    (@ret $ret:ty) => { $ret };
+
+    (@call $module:literal, $name:ident, ($($arg:ident : $arg_ty:ty),*)) => {
+        $crate::py_bindings!(@call $module, $name, ($($arg: $arg_ty),*) -> $crate::bindings::__private::PyObject).map(|_| ())
+    };
+    (@call $module:literal, $name:ident, ($($arg:ident : $arg_ty:ty),*) -> $ret:ty) => {{
+        static CHECKED: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0);
+        let function = $crate::py_bindings!(@function $module, $name, ($($arg: $arg_ty),*) -> $ret);
+        $crate::bindings::__private::Python::with_gil(|py| {
+            function.validate_once(py, &CHECKED)?;
+            function.call_with_gil(py, ($($arg,)*))
+        })
+    }};
+
+    // the PyFunction behind a binding
+    (@function $module:literal, $name:ident, ($($arg:ident : $arg_ty:ty),*)) => {
+        $crate::py_bindings!(@function $module, $name, ($($arg: $arg_ty),*) -> $crate::bindings::__private::PyObject)
+    };
+    (@function $module:literal, $name:ident, ($($arg:ident : $arg_ty:ty),*) -> $ret:ty) => {
+        $crate::PyFunction::<($($arg_ty,)*), $ret>::new($module, stringify!($name)).with_arg_names(&[$(stringify!($arg)),*])
+    };
+}
+
+// used by the macro so the calling crate doesn't need its own pyo3 paths to line up
+#[doc(hidden)]
+pub mod __private {
+    pub use pyo3::{PyObject, Python};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use pyo3::prelude::*;
+
+    use crate::error::Error;
+
+    // written to a directory on sys.path, so the bindings import them like any other module
+    const GOOD: &str = "def add(a: int, b: int) -> int:\n    return a + b\n\ndef greet(name: str) -> str:\n    return 'hello ' + name\n\ndef ping():\n    pass\n";
+    const BAD: &str = "CALLS = []\n\ndef add(a: int, b: int) -> int:\n    CALLS.append('add')\n    return a + b\n\ndef greet(name: int) -> str:\n    CALLS.append('greet')\n    return str(name)\n";
+
+    mod good {
+        crate::py_bindings! {
+            module "bindings_test_good";
+            pub fn add(a: i64, b: i64) -> i64;
+            pub fn greet(name: &str) -> String;
+            pub fn ping();
+        }
+    }
+
+    mod bad {
+        crate::py_bindings! {
+            module "bindings_test_bad";
+            pub fn add(a: i64, b: i64) -> i64;
+            pub fn greet(name: &str) -> String;
+        }
+    }
+
+    #[test]
+    fn validate_all_checks_every_function_before_any_is_called() {
+        let dir = env::temp_dir().join(format!("examples-bindings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("bindings_test_good.py"), GOOD).unwrap();
+        fs::write(dir.join("bindings_test_bad.py"), BAD).unwrap();
+        Python::with_gil(|py| {
+            let sys_path = py.import("sys").unwrap().getattr("path").unwrap();
+            sys_path.call_method1("append", (dir.to_string_lossy().as_ref(),)).unwrap();
+        });
+        good::validate_all().unwrap();
+        assert_eq!(good::add(2, 3).unwrap(), 5);
+        assert_eq!(good::greet("rust").unwrap(), "hello rust");
+        good::ping().unwrap();
+
+        match bad::validate_all() {
+            Err(Error::SignatureMismatch { function, problems }) => {
+                assert_eq!(function, "bindings_test_bad.greet");
+                assert_eq!(problems, ["argument `name` is annotated int in Python but Rust passes str"]);
+            }
+            other => panic!("expected Error::SignatureMismatch, got {other:?}"),
+        }
+        // the call is checked too, and never reaches Python
+        assert!(matches!(bad::greet("rust"), Err(Error::SignatureMismatch { .. })));
+        let calls = || -> Vec<String> { Python::with_gil(|py| py.import("bindings_test_bad").unwrap().getattr("CALLS").unwrap().extract().unwrap()) };
+        assert!(calls().is_empty());
+        // the functions that do match can still be called
+        assert_eq!(bad::add(2, 3).unwrap(), 5);
+        assert_eq!(calls(), ["add"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
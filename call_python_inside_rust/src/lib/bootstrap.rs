@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use pyo3::prelude::*;
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::requirements::{normalize, parse_requirements};
+use crate::venv::{VenvOrigin, VirtualEnv, VENV_DIR_NAME};
+
+// name of the requirements file inside the crate directory
+pub const REQUIREMENTS_FILE: &str = "requirements.txt";
+
+// What `<binary> bootstrap` should do
+//     bootstrap [--venv DIR] [--requirements FILE] [--python PATH] [--wheelhouse DIR]
+#[derive(Debug, Clone)]
+pub struct BootstrapOptions {
+    pub venv_dir: PathBuf,
+    pub requirements: PathBuf,
+    // interpreter used to create the venv
+    // None picks python3.X matching the embedded interpreter, so the venv can be activated by it
+    pub python: Option<PathBuf>,
+    // install from the wheels in this directory only (pip --no-index --find-links)
+    pub wheelhouse: Option<PathBuf>,
+}
+
+// a package as listed by `pip list`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradedPackage {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+// What bootstrap() did, worked out by comparing `pip list` before and after installing
+#[derive(Debug, Clone)]
+pub struct BootstrapReport {
+    pub venv_dir: PathBuf,
+    // false when an existing venv was reused
+    pub created: bool,
+    pub python: String,
+    pub requirements: PathBuf,
+    pub wheelhouse: Option<PathBuf>,
+    // packages that weren't in the venv before
+    pub installed: Vec<InstalledPackage>,
+    // packages whose version changed
+    pub upgraded: Vec<UpgradedPackage>,
+    // requirements that were already installed and left alone
+    pub already_satisfied: Vec<InstalledPackage>,
+}
+
+impl BootstrapOptions {
+    // env/ and requirements.txt inside the crate directory; pass env!("CARGO_MANIFEST_DIR") from that crate
+    pub fn for_crate(manifest_dir: impl AsRef<Path>) -> BootstrapOptions {
+        let manifest_dir = manifest_dir.as_ref();
+        BootstrapOptions {
+            venv_dir: manifest_dir.join(VENV_DIR_NAME),
+            requirements: manifest_dir.join(REQUIREMENTS_FILE),
+            python: None,
+            wheelhouse: None,
+        }
+    }
+
+    // Override the defaults with command line flags (the arguments after "bootstrap")
+    pub fn parse_args(mut self, args: impl IntoIterator<Item = String>) -> Result<BootstrapOptions, Error> {
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().map(PathBuf::from).ok_or_else(|| Error::Usage(format!("{flag} needs a value")));
+            match flag.as_str() {
+                "--venv" => self.venv_dir = value()?,
+                "--requirements" | "-r" => self.requirements = value()?,
+                "--python" => self.python = Some(value()?),
+                "--wheelhouse" => self.wheelhouse = Some(value()?),
+                _ => {
+                    return Err(Error::Usage(format!(
+                        "unknown argument {flag}\nusage: bootstrap [--venv DIR] [--requirements FILE] [--python PATH] [--wheelhouse DIR]"
+                    )))
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
+// Create the venv (unless a usable one is already there) and install the requirements into it
+// replaces setup.sh; unlike it, an existing venv still gets any requirements that are missing
+pub fn bootstrap(options: &BootstrapOptions) -> Result<BootstrapReport, Error> {
+    let venv_dir = &options.venv_dir;
+    if !options.requirements.is_file() {
+        return Err(Error::invalid_file(&options.requirements, "requirements file not found"));
+    }
+    if let Some(wheelhouse) = options.wheelhouse.as_ref().filter(|dir| !dir.is_dir()) {
+        return Err(Error::invalid_file(wheelhouse, "wheelhouse is not a directory"));
+    }
+
+    let created = if venv_dir.join("pyvenv.cfg").is_file() {
+        // make sure the embedded interpreter can use the venv before installing anything into it
+        Python::with_gil(|py| VirtualEnv::open(py, venv_dir, VenvOrigin::Configured))?;
+        println!("Using the existing virtualenv at {}", venv_dir.display());
+        false
+    } else {
+        if venv_dir.exists() {
+            // an empty directory is left behind by an interrupted setup; anything else might be someone's data
+            let is_empty_dir = venv_dir.is_dir() && fs::read_dir(venv_dir)?.next().is_none();
+            if !is_empty_dir {
+                return Err(Error::invalid_venv(venv_dir, "exists but is not a virtualenv (no pyvenv.cfg), remove it or pick another --venv"));
+            }
+            fs::remove_dir(venv_dir)?;
+        }
+        let python = match &options.python {
+            Some(python) => python.clone(),
+            None => Python::with_gil(|py| -> PyResult<PathBuf> {
+                let version_info = py.import("sys")?.getattr("version_info")?;
+                let major: u8 = version_info.getattr("major")?.extract()?;
+                let minor: u8 = version_info.getattr("minor")?.extract()?;
+                Ok(PathBuf::from(format!("python{major}.{minor}")))
+            })?,
+        };
+        println!("Creating a virtualenv at {} with {}...", venv_dir.display(), python.display());
+        run(Command::new(&python).arg("-m").arg("venv").arg(venv_dir))?;
+        true
+    };
+
+    let venv_python = venv_dir.join(if cfg!(windows) { "Scripts/python.exe" } else { "bin/python" });
+    let python = run(Command::new(&venv_python).arg("--version"))?.trim().to_owned();
+    let before = pip_list(&venv_python)?;
+
+    println!("Installing the packages in {}...", options.requirements.display());
+    let mut install = Command::new(&venv_python);
+    install.args(["-m", "pip", "install", "--disable-pip-version-check", "-r"]).arg(&options.requirements);
+    if let Some(wheelhouse) = &options.wheelhouse {
+        install.arg("--no-index").arg("--find-links").arg(wheelhouse);
+    }
+    run(&mut install)?;
+
+    let after = pip_list(&venv_python)?;
+    let before: HashMap<String, String> = before.into_iter().map(|p| (normalize(&p.name), p.version)).collect();
+    let mut installed = Vec::new();
+    let mut upgraded = Vec::new();
+    for package in &after {
+        match before.get(&normalize(&package.name)) {
+            None => installed.push(package.clone()),
+            Some(from) if from != &package.version => {
+                upgraded.push(UpgradedPackage { name: package.name.clone(), from: from.clone(), to: package.version.clone() })
+            }
+            Some(_) => {}
+        }
+    }
+    let required: Vec<String> = parse_requirements(&options.requirements)?.iter().map(|r| r.normalized_name()).collect();
+    let already_satisfied = after
+        .into_iter()
+        .filter(|p| required.contains(&normalize(&p.name)) && before.get(&normalize(&p.name)) == Some(&p.version))
+        .collect();
+
+    Ok(BootstrapReport {
+        venv_dir: venv_dir.clone(),
+        created,
+        python,
+        requirements: options.requirements.clone(),
+        wheelhouse: options.wheelhouse.clone(),
+        installed,
+        upgraded,
+        already_satisfied,
+    })
+}
+
+impl fmt::Display for BootstrapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = if self.created { "Created" } else { "Reused" };
+        writeln!(f, "{action} virtualenv {} ({})", self.venv_dir.display(), self.python)?;
+        match &self.wheelhouse {
+            Some(wheelhouse) => writeln!(f, "Requirements: {} (offline, from {})", self.requirements.display(), wheelhouse.display())?,
+            None => writeln!(f, "Requirements: {}", self.requirements.display())?,
+        }
+        writeln!(f, "Installed: {}", self.installed.len())?;
+        for p in &self.installed {
+            writeln!(f, "    {}=={}", p.name, p.version)?;
+        }
+        writeln!(f, "Upgraded: {}", self.upgraded.len())?;
+        for p in &self.upgraded {
+            writeln!(f, "    {} {} -> {}", p.name, p.from, p.to)?;
+        }
+        write!(f, "Already satisfied: {}", self.already_satisfied.len())?;
+        for p in &self.already_satisfied {
+            write!(f, "\n    {}=={}", p.name, p.version)?;
+        }
+        Ok(())
+    }
+}
+
+// Run a command to completion and return its stdout
+// a missing program or a non-zero exit status becomes Error::Command with whatever it printed to stderr
+fn run(command: &mut Command) -> Result<String, Error> {
+    let description = format!("{command:?}").replace('"', "");
+    let output = command.output().map_err(|e| Error::Command { command: description.clone(), status: None, stderr: e.to_string() })?;
+    if !output.status.success() {
+        return Err(Error::Command {
+            command: description,
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn pip_list(venv_python: &Path) -> Result<Vec<InstalledPackage>, Error> {
+    let json = run(Command::new(venv_python).args(["-m", "pip", "list", "--format=json", "--disable-pip-version-check"]))?;
+    serde_json::from_str(&json).map_err(|e| Error::Conversion { target: "pip list output", message: e.to_string() })
+}
@@ -0,0 +1,112 @@
+# Python side of capture() (see capture.rs)
+# sys.stdout and sys.stderr are replaced once by a StreamDispatcher wrapping the real stream; each capture
+# pushes a CaptureWriter for its own thread, so captures on different threads can start and end in any
+# order without putting back each other's streams
+# the writers are keyed by threading.get_ident() rather than kept in a threading.local, since a Rust thread
+# may get a new Python thread state every time it takes the GIL
+import io
+import sys
+import threading
+
+
+class CaptureWriter:
+    # keeps everything written and hands each complete line to on_line(stream_name, line) as soon as
+    # it is written; with tee the dispatcher also passes the text on to the stream below
+    def __init__(self, name, tee, on_line):
+        self.name = name
+        self.tee = tee
+        self.on_line = on_line
+        self.chunks = []
+        self.pending = ""
+
+    def write(self, text):
+        self.chunks.append(text)
+        if self.on_line is not None:
+            *lines, self.pending = (self.pending + text).split("\n")
+            for line in lines:
+                self.on_line(self.name, line)
+
+    # called once the captured call is over: reports a last line without "\n" and returns the text
+    def finish(self):
+        if self.on_line is not None and self.pending:
+            self.on_line(self.name, self.pending)
+            self.pending = ""
+        return "".join(self.chunks)
+
+
+class StreamDispatcher(io.TextIOBase):
+    def __init__(self, name, original):
+        self.name = name
+        self.original = original
+        self.by_thread = {}
+
+    # the CaptureWriters of the current thread, innermost last
+    def writers(self):
+        return self.by_thread.get(threading.get_ident(), [])
+
+    def push(self, writer):
+        self.by_thread.setdefault(threading.get_ident(), []).append(writer)
+
+    # removes `writer` wherever it is, so a capture that ends early can't take another one with it
+    def pop(self, writer):
+        thread = threading.get_ident()
+        writers = self.by_thread.get(thread, [])
+        for index in range(len(writers) - 1, -1, -1):
+            if writers[index] is writer:
+                del writers[index]
+                break
+        if not writers:
+            self.by_thread.pop(thread, None)
+
+    def write(self, text):
+        writers = self.writers()
+        index = len(writers) - 1
+        # an inner capture sees the outer one as the terminal
+        while index >= 0:
+            writers[index].write(text)
+            if not writers[index].tee:
+                return len(text)
+            index -= 1
+        if self.original is not None:
+            self.original.write(text)
+        return len(text)
+
+    def flush(self):
+        if self.original is not None:
+            self.original.flush()
+
+    def writable(self):
+        return True
+
+    @property
+    def encoding(self):
+        return getattr(self.original, "encoding", None) or "utf-8"
+
+    @property
+    def errors(self):
+        return getattr(self.original, "errors", None)
+
+    def isatty(self):
+        return self.original is not None and self.original.isatty()
+
+    def fileno(self):
+        if self.original is None:
+            raise io.UnsupportedOperation("fileno")
+        return self.original.fileno()
+
+    # buffer, errors, reconfigure(), ... of the real stream
+    def __getattr__(self, name):
+        return getattr(self.original, name)
+
+
+# the dispatchers for stdout and stderr, replacing the streams the first time
+# if other code replaced sys.stdout since, the new stream gets a dispatcher of its own
+def dispatchers():
+    result = []
+    for name in ("stdout", "stderr"):
+        stream = getattr(sys, name)
+        if not isinstance(stream, StreamDispatcher):
+            stream = StreamDispatcher(name, stream)
+            setattr(sys, name, stream)
+        result.append(stream)
+    return tuple(result)
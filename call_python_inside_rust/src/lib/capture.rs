@@ -0,0 +1,268 @@
+use std::fmt;
+use std::sync::Arc;
+
+use pyo3::once_cell::GILOnceCell;
+use pyo3::prelude::*;
+use pyo3::types::{PyCFunction, PyDict, PyTuple};
+
+use crate::error::Error;
+use crate::loader::helper_module;
+
+const CAPTURE_WRITER: &str = include_str!("capture.py");
+
+// What happens to Python's sys.stdout / sys.stderr while a call runs
+//     let captured = capture(&Capture::Silent, || functions_venv::random_number(1, 10));
+//     println!("Python printed:\n{}", captured.stdout);
+//     let n = captured.value?;
+#[derive(Clone, Default)]
+pub enum Capture {
+    // nothing is captured, Python prints straight to the terminal (and the captured text is empty)
+    #[default]
+    Off,
+    // captured, and also printed as it is written (pass-through)
+    Tee,
+    // captured only
+    Silent,
+    // captured, and every complete line is passed to the callback as soon as Python writes it
+    // a last line without "\n" is passed when the call returns
+    Lines(LineCallback),
+}
+
+// called with the stream and the line, without the "\n"
+pub type LineCallback = Arc<dyn Fn(OutputStream, &str) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+// The result of a captured call together with what it printed
+// value is the call's own result, so the output is still there when the call failed
+#[derive(Debug, Clone)]
+pub struct Captured<T> {
+    pub value: T,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Capture {
+    // Lines mode from a closure, ex. Capture::lines(|stream, line| println!("[{stream}] {line}"))
+    pub fn lines(callback: impl Fn(OutputStream, &str) + Send + Sync + 'static) -> Capture {
+        Capture::Lines(Arc::new(callback))
+    }
+}
+
+// Run `f` with Python's stdout and stderr redirected according to `mode`
+// only what Python writes on the calling thread is captured: sys.stdout / sys.stderr are replaced once
+// by dispatchers that keep a stack of captures per thread, so captures on other threads (overlapping or
+// not) and their output are left alone; captures nest, an inner capture sees the outer one as the terminal
+pub fn capture<T>(mode: &Capture, f: impl FnOnce() -> Result<T, Error>) -> Captured<Result<T, Error>> {
+    if matches!(mode, Capture::Off) {
+        return Captured { value: f(), stdout: String::new(), stderr: String::new() };
+    }
+    let writers = match Python::with_gil(|py| Redirect::install(py, mode)) {
+        Ok(writers) => writers,
+        Err(e) => return Captured { value: Err(e), stdout: String::new(), stderr: String::new() },
+    };
+    // if `f` panics, dropping `writers` ends the capture
+    let value = f();
+    match Python::with_gil(|py| writers.restore(py)) {
+        Ok((stdout, stderr)) => Captured { value, stdout, stderr },
+        Err(e) => Captured { value: value.and(Err(e)), stdout: String::new(), stderr: String::new() },
+    }
+}
+
+impl<T> Captured<T> {
+    // stdout followed by stderr, for display
+    pub fn output(&self) -> String {
+        format!("{}{}", self.stdout, self.stderr)
+    }
+}
+
+// the CaptureWriters pushed for one capture() and the dispatchers they were pushed on
+// they are popped by restore(), or when it is dropped without being restored
+struct Redirect {
+    stdout: PyObject,
+    stderr: PyObject,
+    dispatchers: (PyObject, PyObject),
+    restored: bool,
+}
+
+impl Redirect {
+    fn install(py: Python<'_>, mode: &Capture) -> Result<Redirect, Error> {
+        static MODULE: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+        let module = MODULE.get_or_try_init(py, || -> PyResult<Py<PyModule>> {
+            Ok(helper_module(py, CAPTURE_WRITER, "capture.py", "capture")?.into())
+        })?.as_ref(py);
+        let tee = matches!(mode, Capture::Tee);
+        let on_line: PyObject = match mode {
+            Capture::Lines(callback) => {
+                let callback = Arc::clone(callback);
+                let function = PyCFunction::new_closure(py, None, None, move |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<()> {
+                    let (stream, line): (&str, &str) = args.extract()?;
+                    let stream = if stream == "stderr" { OutputStream::Stderr } else { OutputStream::Stdout };
+                    callback(stream, line);
+                    Ok(())
+                })?;
+                function.into()
+            }
+            _ => py.None(),
+        };
+
+        // replaces sys.stdout / sys.stderr the first time, later captures reuse the same dispatchers
+        let dispatchers: (PyObject, PyObject) = module.getattr("dispatchers")?.call0()?.extract()?;
+        let class = module.getattr("CaptureWriter")?;
+        let stdout: PyObject = class.call1(("stdout", tee, on_line.clone_ref(py)))?.into();
+        let stderr: PyObject = class.call1(("stderr", tee, on_line))?.into();
+        // Rust's println! output is buffered separately; flush Python's first so tee output keeps its order
+        dispatchers.0.call_method0(py, "flush")?;
+        dispatchers.0.call_method1(py, "push", (&stdout,))?;
+        dispatchers.1.call_method1(py, "push", (&stderr,))?;
+        Ok(Redirect { stdout, stderr, dispatchers, restored: false })
+    }
+
+    fn restore(mut self, py: Python<'_>) -> Result<(String, String), Error> {
+        self.pop(py)?;
+        let stdout = self.stdout.call_method0(py, "finish")?.extract(py)?;
+        let stderr = self.stderr.call_method0(py, "finish")?.extract(py)?;
+        Ok((stdout, stderr))
+    }
+
+    fn pop(&mut self, py: Python<'_>) -> PyResult<()> {
+        if !self.restored {
+            self.restored = true;
+            self.dispatchers.0.call_method1(py, "pop", (&self.stdout,))?;
+            self.dispatchers.1.call_method1(py, "pop", (&self.stderr,))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Redirect {
+    fn drop(&mut self) {
+        if !self.restored {
+            // nowhere to report a failure from here, and panicking while unwinding would abort
+            let _ = Python::with_gil(|py| self.pop(py));
+        }
+    }
+}
+
+impl fmt::Debug for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capture::Off => write!(f, "Off"),
+            Capture::Tee => write!(f, "Tee"),
+            Capture::Silent => write!(f, "Silent"),
+            Capture::Lines(_) => write!(f, "Lines(..)"),
+        }
+    }
+}
+
+impl fmt::Display for OutputStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputStream::Stdout => write!(f, "stdout"),
+            OutputStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::*;
+
+    fn print(code: &str) -> Result<(), Error> {
+        Python::with_gil(|py| Ok(py.run(code, None, None)?))
+    }
+
+    // no capture is left behind on this thread, and the dispatcher is still the one installed
+    fn nothing_captured_here() -> bool {
+        Python::with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+            ["stdout", "stderr"].iter().all(|name| {
+                let stream = sys.getattr(*name).unwrap();
+                let writers = stream.call_method0("writers").unwrap();
+                stream.get_type().name().unwrap() == "StreamDispatcher" && writers.len().unwrap() == 0
+            })
+        })
+    }
+
+    #[test]
+    fn captures_each_stream() {
+        let captured = capture(&Capture::Silent, || print("import sys\nprint('out')\nprint('err', file=sys.stderr)"));
+        assert!(captured.value.is_ok());
+        assert_eq!((captured.stdout.as_str(), captured.stderr.as_str()), ("out\n", "err\n"));
+
+        // the output is kept when the call fails
+        let captured = capture(&Capture::Silent, || print("print('before')\nraise ValueError('bad')"));
+        assert!(matches!(captured.value, Err(Error::Value(_))));
+        assert_eq!(captured.stdout, "before\n");
+        assert!(nothing_captured_here());
+    }
+
+    #[test]
+    fn inner_captures_see_the_outer_one_as_the_terminal() {
+        let lines = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&lines);
+        let outer = capture(&Capture::lines(move |stream, line| seen.lock().unwrap().push(format!("{stream}: {line}"))), || {
+            let tee = capture(&Capture::Tee, || print("print('tee')"));
+            let silent = capture(&Capture::Silent, || print("print('silent')"));
+            assert_eq!((tee.stdout.as_str(), silent.stdout.as_str()), ("tee\n", "silent\n"));
+            print("print('outer', end='')")
+        });
+        assert_eq!(outer.stdout, "tee\nouter");
+        assert_eq!(*lines.lock().unwrap(), ["stdout: tee", "stdout: outer"]);
+    }
+
+    #[test]
+    fn panicking_calls_end_their_capture() {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            capture(&Capture::Silent, || -> Result<(), Error> {
+                print("print('swallowed')")?;
+                panic!("the call panicked");
+            })
+        }));
+        assert!(result.is_err());
+        assert!(nothing_captured_here());
+    }
+
+    // a starts capturing, b starts, a stops while b is still capturing, then b stops
+    #[test]
+    fn overlapping_captures_on_two_threads_keep_their_own_output() {
+        let started = Arc::new(Barrier::new(2));
+        let both_running = Arc::new(Barrier::new(2));
+        let a_done = Arc::new(Barrier::new(2));
+
+        let a = {
+            let (started, both_running, a_done) = (Arc::clone(&started), Arc::clone(&both_running), Arc::clone(&a_done));
+            thread::spawn(move || {
+                let captured = capture(&Capture::Silent, || {
+                    print("print('a1')")?;
+                    started.wait();
+                    both_running.wait();
+                    print("print('a2')")
+                });
+                a_done.wait();
+                (captured.value.is_ok(), captured.stdout, nothing_captured_here())
+            })
+        };
+        let b = thread::spawn(move || {
+            started.wait();
+            let captured = capture(&Capture::Silent, || {
+                print("print('b1')")?;
+                both_running.wait();
+                a_done.wait();
+                print("print('b2')")
+            });
+            (captured.value.is_ok(), captured.stdout, nothing_captured_here())
+        });
+
+        assert_eq!(a.join().unwrap(), (true, "a1\na2\n".to_string(), true));
+        assert_eq!(b.join().unwrap(), (true, "b1\nb2\n".to_string(), true));
+    }
+}
@@ -0,0 +1,462 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+
+use crate::error::Error;
+use crate::signature::TypeHint;
+
+// where the generated bindings live inside the crate, included by lib.rs as `mod py_functions`
+pub const GENERATED_FILE: &str = "src/lib/py_functions.rs";
+
+// What `<binary> codegen` should do
+//     codegen [--py-dir DIR] [--out FILE] [--check]
+#[derive(Debug, Clone)]
+pub struct CodegenOptions {
+    // every *.py file directly inside this directory becomes one Rust module
+    pub py_dir: PathBuf,
+    pub out: PathBuf,
+    // compare with the existing file instead of writing it, ex. in CI
+    pub check: bool,
+}
+
+// A top-level Python function that gets a Rust wrapper
+#[derive(Debug, Clone)]
+pub struct PyFunctionDef {
+    pub name: String,
+    // positional parameters in order; unannotated ones are TypeHint::Any
+    pub params: Vec<(String, TypeHint)>,
+    pub returns: TypeHint,
+}
+
+// The functions found in one .py file
+#[derive(Debug, Clone)]
+pub struct PyModuleDef {
+    pub name: String,
+    pub path: PathBuf,
+    pub functions: Vec<PyFunctionDef>,
+    // top-level functions that have type hints but can't be called through py_bindings!, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CodegenReport {
+    pub out: PathBuf,
+    pub modules: Vec<PyModuleDef>,
+    // false when the file already matched (or, with --check, when it was up to date)
+    pub changed: bool,
+}
+
+impl CodegenOptions {
+    // py/ and src/lib/py_functions.rs inside the crate directory; pass env!("CARGO_MANIFEST_DIR") from that crate
+    pub fn for_crate(manifest_dir: impl AsRef<Path>) -> CodegenOptions {
+        let manifest_dir = manifest_dir.as_ref();
+        CodegenOptions { py_dir: manifest_dir.join("py"), out: manifest_dir.join(GENERATED_FILE), check: false }
+    }
+
+    // Override the defaults with command line flags (the arguments after "codegen")
+    pub fn parse_args(mut self, args: impl IntoIterator<Item = String>) -> Result<CodegenOptions, Error> {
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().map(PathBuf::from).ok_or_else(|| Error::Usage(format!("{flag} needs a value")));
+            match flag.as_str() {
+                "--py-dir" => self.py_dir = value()?,
+                "--out" => self.out = value()?,
+                "--check" => self.check = true,
+                _ => return Err(Error::Usage(format!("unknown argument {flag}\nusage: codegen [--py-dir DIR] [--out FILE] [--check]"))),
+            }
+        }
+        Ok(self)
+    }
+}
+
+// Read the .py files and write (or with --check, compare) the Rust bindings
+// the files are parsed with Python's ast module, not imported, so packages they need don't have to be installed
+pub fn codegen(options: &CodegenOptions) -> Result<CodegenReport, Error> {
+    if !options.py_dir.is_dir() {
+        return Err(Error::invalid_file(&options.py_dir, "not a directory"));
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(&options.py_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|path| path.extension().is_some_and(|ext| ext == "py"));
+    files.sort();
+
+    let modules = Python::with_gil(|py| files.iter().map(|path| read_module(py, path)).collect::<Result<Vec<_>, Error>>())?;
+    let code = render(&modules);
+    let existing = fs::read_to_string(&options.out).ok();
+    let changed = existing.as_deref() != Some(code.as_str());
+    if options.check {
+        if changed {
+            return Err(Error::OutOfDate { path: options.out.clone(), sources: options.py_dir.clone() });
+        }
+    } else if changed {
+        fs::write(&options.out, &code)?;
+    }
+    Ok(CodegenReport { out: options.out.clone(), modules, changed })
+}
+
+// Find the top-level functions with type hints in a .py file
+// functions without any annotation and private ones (leading underscore) are left out
+pub fn read_module(py: Python<'_>, path: &Path) -> Result<PyModuleDef, Error> {
+    let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_owned();
+    if !is_rust_identifier(&name) {
+        return Err(Error::invalid_file(path, format!("`{name}` can't be used as a Rust module name")));
+    }
+    let source = fs::read_to_string(path)?;
+    let ast = py.import("ast")?;
+    let tree = ast
+        .call_method1("parse", (source.as_str(), path.to_string_lossy().as_ref()))
+        .map_err(|e| Error::from_pyerr(py, e))?;
+
+    let mut functions = Vec::new();
+    let mut skipped = Vec::new();
+    for node in tree.getattr("body")?.iter()? {
+        let node = node?;
+        let kind = node.get_type().name()?;
+        if kind != "FunctionDef" && kind != "AsyncFunctionDef" {
+            continue;
+        }
+        let function_name: String = node.getattr("name")?.extract()?;
+        if function_name.starts_with('_') {
+            continue;
+        }
+        let arguments = node.getattr("args")?;
+        let positional: Vec<&PyAny> = arguments
+            .getattr("posonlyargs")?
+            .iter()?
+            .chain(arguments.getattr("args")?.iter()?)
+            .collect::<PyResult<_>>()?;
+        let returns = node.getattr("returns")?;
+        let annotated = !returns.is_none() || positional.iter().any(|arg| arg.getattr("annotation").is_ok_and(|a| !a.is_none()));
+        if !annotated {
+            continue;
+        }
+
+        let required_keyword_only = arguments
+            .getattr("kw_defaults")?
+            .iter()?
+            .zip(arguments.getattr("kwonlyargs")?.iter()?)
+            .find_map(|(default, arg)| match (default, arg) {
+                (Ok(default), Ok(arg)) if default.is_none() => arg.getattr("arg").and_then(|a| a.extract::<String>()).ok(),
+                _ => None,
+            });
+        let positional_names = positional.iter().map(|arg| arg.getattr("arg")?.extract()).collect::<PyResult<Vec<String>>>()?;
+        let reason = if kind == "AsyncFunctionDef" {
+            Some("async functions can't be called from Rust".to_owned())
+        } else if !is_rust_identifier(&function_name) {
+            Some(format!("`{function_name}` can't be used as a Rust function name"))
+        } else if let Some(arg) = positional_names.iter().find(|name| !is_rust_identifier(name)) {
+            Some(format!("argument `{arg}` can't be used as a Rust name"))
+        } else {
+            required_keyword_only.map(|arg| format!("keyword-only argument `{arg}` can't be passed from Rust"))
+        };
+        if let Some(reason) = reason {
+            skipped.push((function_name, reason));
+            continue;
+        }
+
+        let mut params = Vec::new();
+        for (arg, arg_name) in positional.into_iter().zip(positional_names) {
+            params.push((arg_name, hint_from_ast(py, arg.getattr("annotation")?)?));
+        }
+        // an unannotated return is unknown rather than None
+        let returns = if returns.is_none() { TypeHint::Any } else { hint_from_ast(py, returns)? };
+        functions.push(PyFunctionDef { name: function_name, params, returns });
+    }
+    Ok(PyModuleDef { name, path: path.to_owned(), functions, skipped })
+}
+
+// The contents of py_functions.rs: one module per .py file, each a py_bindings! block
+pub fn render(modules: &[PyModuleDef]) -> String {
+    let mut code = String::from(
+        "// @generated by `cargo run -- codegen` from the type hints in py/*.py\n\
+         // don't edit this file, change the Python functions and run the generator again\n",
+    );
+    for module in modules.iter().filter(|m| !m.functions.is_empty() || !m.skipped.is_empty()) {
+        let file_name = module.path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+        code.push_str(&format!("\n// py/{file_name}\npub mod {} {{\n", module.name));
+        for (function, reason) in &module.skipped {
+            code.push_str(&format!("    // skipped {function}(): {reason}\n"));
+        }
+        if !module.functions.is_empty() {
+            code.push_str(&format!("    crate::py_bindings! {{\n        module \"{}\";\n", module.name));
+            for function in &module.functions {
+                code.push_str(&format!("        {}\n", function.rust_declaration()));
+            }
+            code.push_str("    }\n");
+        }
+        code.push_str("}\n");
+    }
+
+    let bound: Vec<&PyModuleDef> = modules.iter().filter(|m| !m.functions.is_empty()).collect();
+    if !bound.is_empty() {
+        code.push_str("\n// check every binding above against its Python function, ex. at startup\npub fn validate_all() -> Result<(), crate::Error> {\n");
+        for module in bound {
+            code.push_str(&format!("    {}::validate_all()?;\n", module.name));
+        }
+        code.push_str("    Ok(())\n}\n");
+    }
+    code
+}
+
+impl PyFunctionDef {
+    // the py_bindings! line for this function, ex. `pub fn random_number(a: i64, b: i64) -> i64;`
+    pub fn rust_declaration(&self) -> String {
+        let params: Vec<String> = self.params.iter().map(|(name, hint)| format!("{name}: {}", hint.rust_arg_type())).collect();
+        let returns = match &self.returns {
+            TypeHint::None => String::new(),
+            hint => format!(" -> {}", hint.rust_type()),
+        };
+        format!("pub fn {}({}){returns};", self.name, params.join(", "))
+    }
+}
+
+impl fmt::Display for CodegenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = if self.changed { "Wrote" } else { "Up to date:" };
+        write!(f, "{action} {}", self.out.display())?;
+        for module in &self.modules {
+            write!(f, "\n    {} ({} function(s))", module.name, module.functions.len())?;
+            for function in &module.functions {
+                write!(f, "\n        {}", function.rust_declaration())?;
+            }
+            for (function, reason) in &module.skipped {
+                write!(f, "\n        skipped {function}(): {reason}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Translate an annotation from the syntax tree, the same way TypeHint::from_annotation does for
+// the evaluated annotation: list[int], List[int], Optional[str], str | None, "int" (forward reference), ...
+fn hint_from_ast(py: Python<'_>, node: &PyAny) -> PyResult<TypeHint> {
+    if node.is_none() {
+        return Ok(TypeHint::Any);
+    }
+    match node.get_type().name()? {
+        "Constant" => {
+            let value = node.getattr("value")?;
+            if value.is_none() {
+                Ok(TypeHint::None)
+            } else if let Ok(text) = value.extract::<&str>() {
+                let expression = py.import("ast")?.call_method1("parse", (text, "<annotation>", "eval"))?;
+                hint_from_ast(py, expression.getattr("body")?)
+            } else {
+                Ok(TypeHint::Any)
+            }
+        }
+        "Name" | "Attribute" => Ok(match type_name(node)?.as_str() {
+            "int" => TypeHint::Int,
+            "float" => TypeHint::Float,
+            "str" => TypeHint::Str,
+            "bool" => TypeHint::Bool,
+            "bytes" => TypeHint::Bytes,
+            "None" => TypeHint::None,
+            "list" | "List" => TypeHint::List(Box::new(TypeHint::Any)),
+            "dict" | "Dict" => TypeHint::Dict(Box::new(TypeHint::Any), Box::new(TypeHint::Any)),
+            _ => TypeHint::Any,
+        }),
+        "Subscript" => {
+            let slice = node.getattr("slice")?;
+            // several parameters (dict[str, int]) come as a Tuple node
+            let items: Vec<&PyAny> = if slice.get_type().name()? == "Tuple" {
+                slice.getattr("elts")?.downcast::<PyList>()?.iter().collect()
+            } else {
+                vec![slice]
+            };
+            let item = |index: usize| items.get(index).map_or(Ok(TypeHint::Any), |item| hint_from_ast(py, item));
+            Ok(match type_name(node.getattr("value")?)?.as_str() {
+                "list" | "List" => TypeHint::List(Box::new(item(0)?)),
+                "dict" | "Dict" => TypeHint::Dict(Box::new(item(0)?), Box::new(item(1)?)),
+                "tuple" | "Tuple" => {
+                    // tuple[int, ...] is a tuple of any length
+                    let ellipsis = py.import("builtins")?.getattr("Ellipsis")?;
+                    let variable_length = items.iter().any(|item| item.getattr("value").is_ok_and(|value| value.is(ellipsis)));
+                    if variable_length {
+                        TypeHint::Any
+                    } else {
+                        TypeHint::Tuple((0..items.len()).map(item).collect::<PyResult<_>>()?)
+                    }
+                }
+                "Optional" => TypeHint::Optional(Box::new(item(0)?)),
+                "Union" => union(py, &items)?,
+                _ => TypeHint::Any,
+            })
+        }
+        // str | None
+        "BinOp" if node.getattr("op")?.get_type().name()? == "BitOr" => {
+            let mut members = Vec::new();
+            let mut pending = vec![node];
+            while let Some(node) = pending.pop() {
+                if node.get_type().name()? == "BinOp" && node.getattr("op")?.get_type().name()? == "BitOr" {
+                    pending.push(node.getattr("right")?);
+                    pending.push(node.getattr("left")?);
+                } else {
+                    members.push(node);
+                }
+            }
+            union(py, &members)
+        }
+        _ => Ok(TypeHint::Any),
+    }
+}
+
+// Union[T, None] is Optional[T]; unions of several types aren't checked
+fn union(py: Python<'_>, members: &[&PyAny]) -> PyResult<TypeHint> {
+    let hints = members.iter().map(|member| hint_from_ast(py, member)).collect::<PyResult<Vec<_>>>()?;
+    let not_none: Vec<&TypeHint> = hints.iter().filter(|hint| **hint != TypeHint::None).collect();
+    Ok(match not_none.as_slice() {
+        [hint] if not_none.len() < hints.len() => TypeHint::Optional(Box::new((*hint).clone())),
+        _ => TypeHint::Any,
+    })
+}
+
+// `int` for Name(id="int") and `Optional` for Attribute(value=Name(id="typing"), attr="Optional")
+fn type_name(node: &PyAny) -> PyResult<String> {
+    match node.get_type().name()? {
+        "Name" => node.getattr("id")?.extract(),
+        "Attribute" => node.getattr("attr")?.extract(),
+        "Constant" if node.getattr("value")?.is_none() => Ok("None".to_owned()),
+        _ => Ok(String::new()),
+    }
+}
+
+fn is_rust_identifier(name: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for",
+        "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static",
+        "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "yield", "abstract", "become", "box",
+        "do", "final", "macro", "override", "priv", "try", "typeof", "unsized", "virtual",
+    ];
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    // an empty directory for the .py files of one test
+    fn scratch(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("examples-codegen-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("py")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn annotations_map_to_rust_types() {
+        let cases = [
+            ("int", "i64"),
+            ("'int'", "i64"),
+            ("list[float]", "Vec<f64>"),
+            ("typing.List[str]", "Vec<String>"),
+            ("Dict[str, list[int]]", "std::collections::HashMap<String, Vec<i64>>"),
+            ("dict[float, int]", "pyo3::PyObject"),
+            ("tuple[int, str]", "(i64, String)"),
+            ("tuple[int, ...]", "pyo3::PyObject"),
+            ("Optional[str]", "Option<String>"),
+            ("typing.Union[None, int]", "Option<i64>"),
+            ("list[int] | None", "Option<Vec<i64>>"),
+            ("int | str | None", "pyo3::PyObject"),
+            ("Union[int, str]", "pyo3::PyObject"),
+            ("bytes", "pyo3::PyObject"),
+            ("SomeClass", "pyo3::PyObject"),
+        ];
+        Python::with_gil(|py| {
+            let ast = py.import("ast").unwrap();
+            for (annotation, rust_type) in cases {
+                let node = ast.call_method1("parse", (annotation, "<test>", "eval")).unwrap().getattr("body").unwrap();
+                assert_eq!(hint_from_ast(py, node).unwrap().rust_type(), rust_type, "{annotation}");
+            }
+        });
+    }
+
+    #[test]
+    fn writes_one_module_per_file_and_checks_it() {
+        let dir = scratch("render");
+        let code = "\
+from typing import Optional
+
+def random_number(a: int, b: int) -> int: ...
+def emoji_test() -> None: ...
+def untyped(a, b): ...
+def _private(a: int) -> int: ...
+def find(names: list[str], name, start: int = 0) -> Optional[int]: ...
+def greet(name: str, title: Optional[str]) -> str: ...
+async def fetch(url: str) -> str: ...
+def resize(width: int, *, height: int) -> None: ...
+def type(name: str) -> str: ...
+";
+        fs::write(dir.join("py/functions.py"), code).unwrap();
+        fs::write(dir.join("py/no_hints.py"), "def f(x):\n    return x\n").unwrap();
+        fs::write(dir.join("py/notes.txt"), "not python").unwrap();
+        let options = CodegenOptions::for_crate(&dir).parse_args(["--out".to_owned(), dir.join("bindings.rs").display().to_string()]).unwrap();
+
+        let report = codegen(&options).unwrap();
+        assert!(report.changed);
+        assert_eq!(report.modules.iter().map(|module| module.name.as_str()).collect::<Vec<_>>(), vec!["functions", "no_hints"]);
+        assert_eq!(
+            fs::read_to_string(&options.out).unwrap(),
+            "\
+// @generated by `cargo run -- codegen` from the type hints in py/*.py
+// don't edit this file, change the Python functions and run the generator again
+
+// py/functions.py
+pub mod functions {
+    // skipped fetch(): async functions can't be called from Rust
+    // skipped resize(): keyword-only argument `height` can't be passed from Rust
+    // skipped type(): `type` can't be used as a Rust function name
+    crate::py_bindings! {
+        module \"functions\";
+        pub fn random_number(a: i64, b: i64) -> i64;
+        pub fn emoji_test();
+        pub fn find(names: Vec<String>, name: pyo3::PyObject, start: i64) -> Option<i64>;
+        pub fn greet(name: &str, title: Option<&str>) -> String;
+    }
+}
+
+// check every binding above against its Python function, ex. at startup
+pub fn validate_all() -> Result<(), crate::Error> {
+    functions::validate_all()?;
+    Ok(())
+}
+"
+        );
+
+        // unchanged sources: nothing to write, and --check passes
+        assert!(!codegen(&options).unwrap().changed);
+        let check = CodegenOptions { check: true, ..options.clone() };
+        assert!(!codegen(&check).unwrap().changed);
+
+        fs::write(dir.join("py/functions.py"), format!("{code}def added() -> str: ...\n")).unwrap();
+        assert!(matches!(codegen(&check), Err(Error::OutOfDate { .. })));
+        assert!(!fs::read_to_string(&options.out).unwrap().contains("added"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bad_arguments_and_module_names() {
+        let options = CodegenOptions::for_crate("/crate");
+        assert!(matches!(options.clone().parse_args(["--out".to_owned()]), Err(Error::Usage(_))));
+        assert!(matches!(options.clone().parse_args(["--verbose".to_owned()]), Err(Error::Usage(_))));
+        let options = options.parse_args(["--check".to_owned(), "--py-dir".to_owned(), "scripts".to_owned()]).unwrap();
+        assert!(options.check);
+        assert_eq!(options.py_dir, PathBuf::from("scripts"));
+        assert_eq!(options.out, Path::new("/crate").join(GENERATED_FILE));
+
+        let dir = scratch("names");
+        fs::write(dir.join("py/my-module.py"), "def f(x: int) -> int: ...\n").unwrap();
+        let result = Python::with_gil(|py| read_module(py, &dir.join("py/my-module.py")));
+        assert!(matches!(result, Err(Error::InvalidFile { .. })), "{result:?}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
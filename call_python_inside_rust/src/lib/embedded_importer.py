@@ -0,0 +1,38 @@
+# Import hook that serves the .py files embedded in the Rust binary (see sources.rs and loader.rs)
+# modules are registered by dotted name, so packages, submodules and relative imports work the
+# same way they do for files found on sys.path
+import importlib.abc
+import importlib.util
+import linecache
+
+
+class EmbeddedFinder(importlib.abc.MetaPathFinder, importlib.abc.Loader):
+    def __init__(self):
+        # dotted name -> (file name, code, is package)
+        self.sources = {}
+
+    def add(self, fullname, file_name, code, is_package):
+        self.sources[fullname] = (file_name, code, is_package)
+
+    def find_spec(self, fullname, path=None, target=None):
+        entry = self.sources.get(fullname)
+        if entry is None:
+            return None
+        file_name, _, is_package = entry
+        return importlib.util.spec_from_loader(fullname, self, origin=file_name, is_package=is_package)
+
+    def create_module(self, spec):
+        # use the default module object
+        return None
+
+    def exec_module(self, module):
+        file_name, code, _ = self.sources[module.__spec__.name]
+        module.__file__ = file_name
+        # lets the Rust side tell embedded modules apart from files on disk
+        module.__embedded__ = True
+        # so tracebacks can show the source lines
+        linecache.cache[file_name] = (len(code), None, code.splitlines(True), file_name)
+        exec(compile(code, file_name, "exec"), module.__dict__)
+
+    def get_source(self, fullname):
+        return self.sources[fullname][1]
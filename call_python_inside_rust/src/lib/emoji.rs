@@ -0,0 +1,183 @@
+use std::fmt;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::error::Error;
+use crate::signature::{PyAnnotation, TypeHint};
+
+// An emoji returned from Python, with what the emoji package knows about it
+// `emoji` is the whole grapheme cluster, so skin tones ("👍🏽"), flags ("🇯🇵"), keycaps ("#️⃣") and
+// ZWJ sequences ("👨‍👩‍👧") fit as well as single code points; a char can only hold the latter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emoji {
+    pub emoji: String,
+    // CLDR short name, ex. ":thumbs_up_medium_skin_tone:"
+    pub name: String,
+    // other names emojize(language='alias') accepts, ex. [":+1:", ":thumbsup:"]
+    pub aliases: Vec<String>,
+    // Emoji version that introduced it, ex. 0.6 or 13.1
+    pub version: Option<f64>,
+}
+
+impl Emoji {
+    // Metadata for one emoji from emoji.EMOJI_DATA
+    // None when the text isn't exactly one emoji the installed package knows
+    pub fn lookup(py: Python<'_>, text: &str) -> Result<Option<Emoji>, Error> {
+        Ok(lookup(py, text)?)
+    }
+
+    // Every emoji in a piece of text, in order, split the way the emoji package splits them (emoji_list)
+    pub fn find_all(py: Python<'_>, text: &str) -> Result<Vec<Emoji>, Error> {
+        let mut found = Vec::new();
+        for item in py.import("emoji")?.call_method1("emoji_list", (text,))?.iter()? {
+            let emoji: &str = item?.get_item("emoji")?.extract()?;
+            found.extend(lookup(py, emoji)?);
+        }
+        Ok(found)
+    }
+
+    // 1 for "🔴", 2 for "👍🏽", 5 for "👨‍👩‍👧"
+    pub fn code_points(&self) -> usize {
+        self.emoji.chars().count()
+    }
+}
+
+fn lookup(py: Python<'_>, text: &str) -> PyResult<Option<Emoji>> {
+    let data: &PyDict = py.import("emoji")?.getattr("EMOJI_DATA")?.downcast()?;
+    let Some(entry) = data.get_item(text) else {
+        return Ok(None);
+    };
+    let field = |key: &str| entry.get_item(key).ok().filter(|value| !value.is_none());
+    Ok(Some(Emoji {
+        emoji: text.to_owned(),
+        name: field("en").map(|name| name.extract()).transpose()?.unwrap_or_default(),
+        aliases: field("alias").map(|aliases| aliases.extract()).transpose()?.unwrap_or_default(),
+        version: field("E").map(|version| version.extract()).transpose()?,
+    }))
+}
+
+// lets bindings return Emoji (or Option<Emoji>) straight from a Python str
+// a str that isn't a single known emoji fails the conversion, which PyFunction reports as Error::ReturnType
+impl<'p> FromPyObject<'p> for Emoji {
+    fn extract(obj: &'p PyAny) -> PyResult<Emoji> {
+        let text: &str = obj.extract()?;
+        lookup(obj.py(), text)?.ok_or_else(|| PyValueError::new_err(format!("{text:?} is not an emoji known to the emoji package")))
+    }
+}
+
+impl IntoPy<PyObject> for Emoji {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        self.emoji.into_py(py)
+    }
+}
+
+impl PyAnnotation for Emoji {
+    fn type_hint() -> TypeHint {
+        TypeHint::Str
+    }
+}
+
+// "👍🏽 :thumbs_up_medium_skin_tone: (Emoji 1.0)"
+impl fmt::Display for Emoji {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.emoji, self.name)?;
+        if !self.aliases.is_empty() {
+            write!(f, " also {}", self.aliases.join(" "))?;
+        }
+        if let Some(version) = self.version {
+            write!(f, " (Emoji {version:.1})")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::exceptions::PyTypeError;
+
+    use super::*;
+    use crate::capture::{capture, Capture};
+    use crate::venv::{self, VenvLocator};
+
+    // the emoji package comes from the project venv (`cargo run -- bootstrap`), like in the examples
+    fn with_emoji(f: impl FnOnce(Python<'_>)) {
+        Python::with_gil(|py| {
+            venv::activate(py, &VenvLocator::default()).unwrap();
+            py.import("emoji").expect("the emoji package, run `cargo run -- bootstrap` to install it");
+            f(py)
+        })
+    }
+
+    #[test]
+    fn lookup_takes_whole_grapheme_clusters() {
+        with_emoji(|py| {
+            let cases = [
+                ("🔴", Some(":red_circle:"), 1),
+                ("👍🏽", Some(":thumbs_up_medium_skin_tone:"), 2),
+                ("🇯🇵", Some(":Japan:"), 2),
+                ("👨‍👩‍👧", Some(":family_man_woman_girl:"), 5),
+                // not exactly one emoji
+                ("👍👍", None, 0),
+                ("red", None, 0),
+                ("", None, 0),
+            ];
+            for (text, name, code_points) in cases {
+                let emoji = Emoji::lookup(py, text).unwrap();
+                assert_eq!(emoji.as_ref().map(|emoji| emoji.name.as_str()), name, "{text}");
+                if let Some(emoji) = emoji {
+                    assert_eq!(emoji.emoji, text);
+                    assert_eq!(emoji.code_points(), code_points, "{text}");
+                    assert!(emoji.version.is_some(), "{text}");
+                }
+            }
+            let thumbs_up = Emoji::lookup(py, "👍").unwrap().unwrap();
+            assert!(thumbs_up.aliases.iter().any(|alias| alias == ":thumbsup:"), "{:?}", thumbs_up.aliases);
+            assert!(thumbs_up.to_string().starts_with("👍 :thumbs_up: also "), "{thumbs_up}");
+        });
+    }
+
+    #[test]
+    fn find_all_splits_text_into_emoji() {
+        with_emoji(|py| {
+            let found = Emoji::find_all(py, "Rust is 👍🏽, made in 🇯🇵 by 👨‍👩‍👧!").unwrap();
+            let found: Vec<&str> = found.iter().map(|emoji| emoji.emoji.as_str()).collect();
+            assert_eq!(found, ["👍🏽", "🇯🇵", "👨‍👩‍👧"]);
+            assert!(Emoji::find_all(py, "no emoji here").unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn extract_fails_for_anything_but_a_known_emoji() {
+        with_emoji(|py| {
+            let red: PyObject = "🔴".into_py(py);
+            let emoji: Emoji = red.extract(py).unwrap();
+            assert_eq!(emoji.name, ":red_circle:");
+            let option: Option<Emoji> = py.None().extract(py).unwrap();
+            assert_eq!(option, None);
+
+            let red: PyObject = "red".into_py(py);
+            let err = red.extract::<Emoji>(py).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert_eq!(err.value(py).to_string(), "\"red\" is not an emoji known to the emoji package");
+            let one: PyObject = 1.into_py(py);
+            let err = one.extract::<Emoji>(py).unwrap_err();
+            assert!(err.is_instance_of::<PyTypeError>(py));
+        });
+    }
+
+    // example 7c: colors only, anything but letters is rejected by color_emoji()
+    #[test]
+    fn color_emoji_only_finds_colored_circles() {
+        with_emoji(|_| {});
+        // color_emoji() prints the emoji, which the test harness's ASCII stdout can't encode
+        let color = |name: &str| capture(&Capture::Silent, || crate::python_function_venv_c(name)).value;
+        assert_eq!(color("Red").unwrap().map(|emoji| emoji.name), Some(":red_circle:".to_owned()));
+        assert_eq!(color("thumbsup").unwrap(), None);
+        for name in ["light-blue", "red!", "red5", ""] {
+            assert!(matches!(color(name), Err(Error::Value(_))), "{name:?}");
+        }
+        assert_eq!(crate::python_function_venv_e("thumbsup").unwrap().map(|emoji| emoji.emoji), Some("👍".to_owned()));
+    }
+}
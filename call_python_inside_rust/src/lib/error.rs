@@ -0,0 +1,296 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use pyo3::exceptions::PyModuleNotFoundError;
+use pyo3::exceptions::PySyntaxError;
+use pyo3::exceptions::PyTypeError;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::requirements::RequirementsReport;
+use crate::traceback::Traceback;
+
+// Error type returned by every public function in the examples crate
+// Python failures are sorted into variants by exception type so callers can match on them
+// instead of digging through an io::Error with ErrorKind::Other
+#[derive(Debug)]
+pub enum Error {
+    // the Python code could not be compiled (SyntaxError, IndentationError, ...)
+    Syntax(PyException),
+    // an import inside the Python code failed (usually a package missing from the venv)
+    ModuleNotFound(PyException),
+    // Python raised TypeError (ex. wrong argument types)
+    Type(PyException),
+    // Python raised ValueError (ex. color_emoji() given a color name with numbers)
+    Value(PyException),
+    // any other Python exception
+    Python(PyException),
+    // a .py file failed validation before it was handed to the interpreter
+    InvalidFile { path: PathBuf, reason: String },
+    // a virtualenv was found but can't be used by the embedded interpreter
+    InvalidVenv { path: PathBuf, reason: String },
+    // a .py file wasn't found in any directory of the SearchPath
+    NotFound { name: String, tried: Vec<PathBuf> },
+    // a Python value could not be converted into the requested Rust type
+    Conversion { target: &'static str, message: String },
+    // a PyFunction's module has no function with that name
+    MissingFunction { module: String, function: String },
+    // a PyFunction returned a value that doesn't convert to its Rust return type
+    ReturnType { function: String, target: &'static str, message: String },
+    // a PyFunction's Rust argument or return types don't match the Python signature (checked before calling it)
+    SignatureMismatch { function: String, problems: Vec<String> },
+    // a call ran past its deadline and was interrupted (see timeout.rs), or its worker process was killed (worker.rs)
+    Timeout { function: String, after: Duration },
+    // a worker process died during the call (see worker.rs); it has been replaced by a new one
+    WorkerCrashed { function: String, pid: u32, status: String, message: String },
+    // the PyExecutor thread running Python jobs is gone (a job panicked)
+    ExecutorStopped,
+    // a job on the PyExecutor thread submitted to its own executor while the queue was full, or called run(),
+    // so it would have waited for itself
+    ExecutorReentered,
+    // ModuleLoader::with_global() was called again from inside its own closure on the same thread
+    LoaderReentered,
+    // a ModuleLoader was asked to load() and import() the same module, which would give two separate copies
+    ModuleNameClash { name: String },
+    // a generated file doesn't match what the generator would write now (codegen --check)
+    OutOfDate { path: PathBuf, sources: PathBuf },
+    // the installed packages don't match requirements.txt (RequirementsPolicy::Enforce)
+    RequirementsNotMet(Box<RequirementsReport>),
+    // an external program (python3 -m venv, pip, ...) couldn't be started or exited with an error
+    Command { command: String, status: Option<i32>, stderr: String },
+    // bad command line arguments
+    Usage(String),
+    // reading files, stdin, etc. failed
+    Io(io::Error),
+}
+
+// The parts of a Python exception worth keeping once the PyErr itself is gone
+// type_name is the exception class name (ex. "ValueError") and message is str(exception)
+// traceback holds the frames and the __cause__/__context__ chain
+#[derive(Debug, Clone)]
+pub struct PyException {
+    pub type_name: String,
+    pub message: String,
+    pub traceback: Box<Traceback>,
+}
+
+impl PyException {
+    fn from_pyerr(py: Python<'_>, err: &PyErr) -> PyException {
+        let type_name = err
+            .get_type(py)
+            .name()
+            .map(str::to_owned)
+            .unwrap_or_else(|_| "<unknown>".to_owned());
+        let message = err
+            .value(py)
+            .str()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let traceback = Box::new(Traceback::from_pyerr(py, err));
+        PyException { type_name, message, traceback }
+    }
+}
+
+impl fmt::Display for PyException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{}", self.type_name)
+        } else {
+            write!(f, "{}: {}", self.type_name, self.message)
+        }
+    }
+}
+
+impl Error {
+    // sort a PyErr into the matching variant
+    // the GIL is needed to inspect the exception type
+    pub fn from_pyerr(py: Python<'_>, err: PyErr) -> Error {
+        let exception = PyException::from_pyerr(py, &err);
+        if err.is_instance_of::<PySyntaxError>(py) {
+            Error::Syntax(exception)
+        } else if err.is_instance_of::<PyModuleNotFoundError>(py) {
+            Error::ModuleNotFound(exception)
+        } else if err.is_instance_of::<PyTypeError>(py) {
+            Error::Type(exception)
+        } else if err.is_instance_of::<PyValueError>(py) {
+            Error::Value(exception)
+        } else {
+            Error::Python(exception)
+        }
+    }
+
+    pub fn invalid_file(path: impl Into<PathBuf>, reason: impl Into<String>) -> Error {
+        Error::InvalidFile { path: path.into(), reason: reason.into() }
+    }
+
+    pub fn invalid_venv(path: impl Into<PathBuf>, reason: impl Into<String>) -> Error {
+        Error::InvalidVenv { path: path.into(), reason: reason.into() }
+    }
+
+    // the Python exception behind this error, if there is one
+    pub fn py_exception(&self) -> Option<&PyException> {
+        match self {
+            Error::Syntax(e) | Error::ModuleNotFound(e) | Error::Type(e) | Error::Value(e) | Error::Python(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn traceback(&self) -> Option<&Traceback> {
+        self.py_exception().map(|e| e.traceback.as_ref())
+    }
+
+    // Full description of the error: the message plus the Python traceback when there is one
+    // use this instead of `{e}` when the user needs to find the failing line in the .py file
+    pub fn report(&self) -> String {
+        match self.traceback() {
+            Some(traceback) if !traceback.exceptions.is_empty() => format!("{self}\n\n{traceback}"),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(e) => write!(f, "Python code could not be compiled: {e}"),
+            Error::ModuleNotFound(e) => write!(f, "Python module not found (is the venv active?): {e}"),
+            Error::Type(e) | Error::Value(e) | Error::Python(e) => write!(f, "Python raised {e}"),
+            Error::InvalidFile { path, reason } => write!(f, "invalid Python file {}: {reason}", path.display()),
+            Error::InvalidVenv { path, reason } => write!(f, "invalid virtualenv {}: {reason}", path.display()),
+            Error::NotFound { name, tried } => {
+                write!(f, "could not find {name}, tried:")?;
+                for path in tried {
+                    write!(f, "\n    {}", path.display())?;
+                }
+                Ok(())
+            }
+            Error::Conversion { target, message } => write!(f, "could not convert Python value to {target}: {message}"),
+            Error::MissingFunction { module, function } => write!(f, "Python module {module} has no function {function}()"),
+            Error::ReturnType { function, target, message } => {
+                write!(f, "{function}() returned a value that can't be converted to {target}: {message}")
+            }
+            Error::SignatureMismatch { function, problems } => {
+                write!(f, "{function}() doesn't match its Rust binding:")?;
+                for problem in problems {
+                    write!(f, "\n    {problem}")?;
+                }
+                Ok(())
+            }
+            Error::Timeout { function, after } => write!(f, "{function}() didn't finish within {after:?} and was interrupted"),
+            Error::WorkerCrashed { function, pid, status, message } => {
+                write!(f, "the worker running {function}() (pid {pid}) crashed with {status}: {message}")
+            }
+            Error::ExecutorStopped => write!(f, "the Python executor thread stopped before the call finished"),
+            Error::ExecutorReentered => {
+                write!(f, "a job running on the Python executor thread would have waited for its own executor (a full queue, or run() inside a job)")
+            }
+            Error::LoaderReentered => {
+                write!(f, "the shared ModuleLoader is already in use on this thread (does a module's top-level code call back into Rust?)")
+            }
+            Error::ModuleNameClash { name } => {
+                write!(f, "{name} is already held by this ModuleLoader through the other of load() and import(); unload() it first")
+            }
+            Error::OutOfDate { path, sources } => {
+                write!(f, "{} is out of date with {}, run `cargo run -- codegen`", path.display(), sources.display())
+            }
+            Error::RequirementsNotMet(report) => write!(f, "installed packages don't match the requirements\n{report}"),
+            Error::Command { command, status: Some(code), stderr } => write!(f, "`{command}` exited with status {code}: {stderr}"),
+            Error::Command { command, status: None, stderr } => write!(f, "`{command}` failed: {stderr}"),
+            Error::Usage(message) => write!(f, "{message}"),
+            Error::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// lets `?` be used on PyResult inside the examples
+// with_gil is cheap when the GIL is already held, which is always the case where a PyErr shows up
+impl From<PyErr> for Error {
+    fn from(err: PyErr) -> Error {
+        Python::with_gil(|py| Error::from_pyerr(py, err))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+// .extract() wrapper that reports failures as Error::Conversion rather than a generic TypeError
+pub fn extract<'p, T: FromPyObject<'p>>(obj: &'p PyAny) -> Result<T, Error> {
+    obj.extract().map_err(|e: PyErr| Error::Conversion {
+        target: std::any::type_name::<T>(),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the error `code` raises when it runs
+    fn raise(code: &str) -> Error {
+        Python::with_gil(|py| Error::from_pyerr(py, py.run(code, None, None).expect_err(code)))
+    }
+
+    type IsVariant = fn(&Error) -> bool;
+
+    #[test]
+    fn python_exceptions_map_to_variants() {
+        let cases: &[(&str, &str, IsVariant)] = &[
+            ("x = (", "SyntaxError", |e| matches!(e, Error::Syntax(_))),
+            ("if True:\nx = 1", "IndentationError", |e| matches!(e, Error::Syntax(_))),
+            ("import no_such_module_here", "ModuleNotFoundError", |e| matches!(e, Error::ModuleNotFound(_))),
+            ("len(1)", "TypeError", |e| matches!(e, Error::Type(_))),
+            ("int('red')", "ValueError", |e| matches!(e, Error::Value(_))),
+            // subclasses land in their base class's variant
+            ("b'\\xff'.decode('utf-8')", "UnicodeDecodeError", |e| matches!(e, Error::Value(_))),
+            ("{}['missing']", "KeyError", |e| matches!(e, Error::Python(_))),
+            ("import os.path.nothing", "ModuleNotFoundError", |e| matches!(e, Error::ModuleNotFound(_))),
+        ];
+        for (code, type_name, is_variant) in cases {
+            let error = raise(code);
+            assert!(is_variant(&error), "{code:?} gave {error:?}");
+            assert_eq!(error.py_exception().map(|e| e.type_name.as_str()), Some(*type_name), "{code:?}");
+        }
+    }
+
+    #[test]
+    fn display_and_report() {
+        let error = raise("raise ValueError('bad color')");
+        assert_eq!(error.to_string(), "Python raised ValueError: bad color");
+        assert!(error.report().starts_with("Python raised ValueError: bad color\n\n"));
+        assert!(error.traceback().is_some_and(|traceback| !traceback.exceptions.is_empty()));
+
+        // no message, only the type
+        assert_eq!(raise("raise KeyboardInterrupt").to_string(), "Python raised KeyboardInterrupt");
+
+        // errors that didn't come from Python have no traceback to add
+        let error = Error::MissingFunction { module: "functions".to_owned(), function: "add".to_owned() };
+        assert_eq!(error.report(), "Python module functions has no function add()");
+        assert!(error.py_exception().is_none());
+    }
+
+    #[test]
+    fn extract_reports_conversion_errors() {
+        Python::with_gil(|py| {
+            let value: PyObject = "not a number".into_py(py);
+            match extract::<i64>(value.as_ref(py)) {
+                Err(Error::Conversion { target, .. }) => assert_eq!(target, "i64"),
+                other => panic!("expected Error::Conversion, got {other:?}"),
+            }
+            assert_eq!(extract::<String>(value.as_ref(py)).unwrap(), "not a number");
+        });
+    }
+}
@@ -0,0 +1,447 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use pyo3::exceptions::PyBaseException;
+use pyo3::once_cell::GILOnceCell;
+use pyo3::prelude::*;
+use pyo3::types::{PyTuple, PyType};
+use pyo3::PyTypeInfo;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::error::Error;
+use crate::function::PyFunction;
+use crate::signature::{PyAnnotation, PyArgs};
+use crate::timeout::Interrupter;
+
+// how many jobs can wait for the executor thread; past that, submitting waits for room in the queue
+pub const DEFAULT_QUEUE_CAPACITY: usize = 32;
+
+// examples.CallCancelled, raised inside a running job whose JobHandle (or future) was dropped
+// derives from BaseException for the same reason as examples.CallTimeout
+fn call_cancelled(py: Python<'_>) -> PyResult<&PyType> {
+    static CALL_CANCELLED: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+    let class = CALL_CANCELLED.get_or_try_init(py, || {
+        PyErr::new_type(py, "examples.CallCancelled", Some("the Rust caller waiting for this call gave up"), Some(PyBaseException::type_object(py)), None)
+    })?;
+    Ok(class.as_ref(py))
+}
+
+// Owns the interpreter on one "python-executor" thread and runs the jobs sent to it, one at a time, in order
+// many Rust threads calling Python directly take turns on the GIL in whatever order the OS wakes them;
+// through the executor they queue up instead, and the queue is bounded so callers slow down when it is full
+//     let executor = PyExecutor::start(DEFAULT_QUEUE_CAPACITY)?;
+//     let emoji = executor.run(|_py| python_function_venv_c("red"))?;
+//     println!("{:?}", executor.stats());
+// the thread keeps the GIL while a job runs and releases it (allow_threads) while it waits for the next job
+// and while it records timings and hands results back, so other threads can use Python in between;
+// a job doing slow Rust work should do the same with py.allow_threads()
+// dropping a JobHandle cancels its job: a queued job is skipped, and a running one gets
+// examples.CallCancelled raised inside it
+// clones share the same thread and queue; the thread exits once every clone is dropped
+// a job may submit() more jobs to its own executor, but gets Error::ExecutorReentered rather than waiting
+// for itself when the queue is full, or when it calls run()
+#[derive(Clone)]
+pub struct PyExecutor {
+    jobs: mpsc::Sender<Job>,
+    stats: Arc<Mutex<ExecutorStats>>,
+    thread: ThreadId,
+}
+
+// How long a job spent in the queue and running on the executor thread
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JobTiming {
+    pub waited: Duration,
+    pub ran: Duration,
+}
+
+// A job's result together with its timing, from JobHandle::wait_timed()
+#[derive(Debug, Clone)]
+pub struct Timed<T> {
+    pub value: T,
+    pub timing: JobTiming,
+}
+
+// What the executor has done so far
+//  - queue_depth: jobs waiting in the queue, including cancelled ones it hasn't skipped yet
+//  - running: a job is running right now
+//  - completed: jobs that ran to the end, whether they returned Ok or Err
+//  - cancelled: jobs skipped or interrupted because their handle was dropped
+// the wait and run totals and maximums only count completed jobs; `last` is the last job that ran
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutorStats {
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    pub running: bool,
+    pub completed: u64,
+    pub cancelled: u64,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+    pub total_run: Duration,
+    pub max_run: Duration,
+    pub last: Option<JobTiming>,
+}
+
+// A submitted job: wait() for its result from a thread, or .await it from async code
+// dropping it before the result arrived cancels the job
+pub struct JobHandle<T> {
+    result: oneshot::Receiver<(Result<T, Error>, JobTiming)>,
+    submitted: Instant,
+    cancel: CancelOnDrop,
+}
+
+// hands a job's result to its caller, run without the GIL
+type Deliver = Box<dyn FnOnce(JobTiming) + Send>;
+
+struct Job {
+    // runs the job with the GIL held
+    run: Box<dyn FnOnce(Python<'_>) -> Deliver + Send>,
+    cancellation: Arc<Cancellation>,
+    queued_at: Instant,
+}
+
+// shared by a job and its JobHandle
+#[derive(Default)]
+struct Cancellation {
+    cancelled: AtomicBool,
+    // set by the executor thread while the job runs
+    running: Mutex<Option<Interrupter>>,
+}
+
+// cancels the job when the handle is dropped before the result arrived
+struct CancelOnDrop(Option<Arc<Cancellation>>);
+
+impl PyExecutor {
+    // Start the executor thread; at most `queue_capacity` jobs wait in the queue
+    pub fn start(queue_capacity: usize) -> Result<PyExecutor, Error> {
+        let (jobs, mut queue) = mpsc::channel::<Job>(queue_capacity.max(1));
+        let stats = Arc::new(Mutex::new(ExecutorStats::default()));
+        let thread_stats = Arc::clone(&stats);
+        let thread = thread::Builder::new().name("python-executor".to_owned()).spawn(move || {
+            Python::with_gil(|py| {
+                let cancelled = match call_cancelled(py) {
+                    Ok(class) => Some(Py::from(class)),
+                    Err(e) => {
+                        // jobs still run, they just can't be interrupted
+                        log::warn!("Python jobs can't be cancelled once they started: {e}");
+                        None
+                    }
+                };
+                while let Some(job) = py.allow_threads(|| queue.blocking_recv()) {
+                    run_job(py, job, &thread_stats, cancelled.as_ref());
+                }
+            })
+        })?;
+        Ok(PyExecutor { jobs, stats, thread: thread.thread().id() })
+    }
+
+    // Queue `f`, waiting while the queue is full; it runs with the GIL on the executor thread
+    // blocks the calling thread, so use submit_async() from async code
+    // from a job on this executor, a full queue is Error::ExecutorReentered instead: nothing would empty it
+    pub fn submit<T: Send + 'static>(&self, f: impl FnOnce(Python<'_>) -> Result<T, Error> + Send + 'static) -> Result<JobHandle<T>, Error> {
+        let (job, handle) = job(f);
+        if self.on_executor_thread() {
+            self.jobs.try_send(job).map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => Error::ExecutorReentered,
+                mpsc::error::TrySendError::Closed(_) => Error::ExecutorStopped,
+            })?;
+        } else {
+            self.jobs.blocking_send(job).map_err(|_| Error::ExecutorStopped)?;
+        }
+        Ok(handle)
+    }
+
+    // submit() that waits for room in the queue without blocking the thread
+    pub async fn submit_async<T: Send + 'static>(&self, f: impl FnOnce(Python<'_>) -> Result<T, Error> + Send + 'static) -> Result<JobHandle<T>, Error> {
+        let (job, handle) = job(f);
+        // dropped while waiting here, the job was never queued
+        self.jobs.send(job).await.map_err(|_| Error::ExecutorStopped)?;
+        Ok(handle)
+    }
+
+    // Run `f` on the executor thread and wait for its result
+    // Error::ExecutorReentered from a job on this executor, which would wait for itself (call `f(py)` there instead)
+    pub fn run<T: Send + 'static>(&self, f: impl FnOnce(Python<'_>) -> Result<T, Error> + Send + 'static) -> Result<T, Error> {
+        if self.on_executor_thread() {
+            return Err(Error::ExecutorReentered);
+        }
+        self.submit(f)?.wait()
+    }
+
+    // PyFunction::call on the executor thread
+    pub fn call<Args, Ret>(&self, function: &PyFunction<Args, Ret>, args: Args) -> Result<Ret, Error>
+    where
+        Args: IntoPy<Py<PyTuple>> + PyArgs + Send + 'static,
+        Ret: for<'p> FromPyObject<'p> + PyAnnotation + Send + 'static,
+    {
+        let function = function.clone();
+        self.run(move |py| function.call_with_gil(py, args))
+    }
+
+    pub fn stats(&self) -> ExecutorStats {
+        ExecutorStats { queue_depth: self.queue_depth(), queue_capacity: self.queue_capacity(), ..*lock(&self.stats) }
+    }
+
+    // how many jobs the queue holds
+    pub fn queue_capacity(&self) -> usize {
+        self.jobs.max_capacity()
+    }
+
+    // jobs waiting in the queue
+    pub fn queue_depth(&self) -> usize {
+        self.jobs.max_capacity() - self.jobs.capacity()
+    }
+
+    // true inside a job running on this executor
+    fn on_executor_thread(&self) -> bool {
+        thread::current().id() == self.thread
+    }
+}
+
+// the job for `f` and the handle its result goes to
+fn job<T: Send + 'static>(f: impl FnOnce(Python<'_>) -> Result<T, Error> + Send + 'static) -> (Job, JobHandle<T>) {
+    let (result, result_rx) = oneshot::channel();
+    let cancellation = Arc::new(Cancellation::default());
+    let job = Job {
+        run: Box::new(move |py| {
+            let value = f(py);
+            // the receiver is gone when the handle was dropped, then nobody wants the result
+            Box::new(move |timing| drop(result.send((value, timing))))
+        }),
+        cancellation: Arc::clone(&cancellation),
+        queued_at: Instant::now(),
+    };
+    (job, JobHandle { result: result_rx, submitted: Instant::now(), cancel: CancelOnDrop(Some(cancellation)) })
+}
+
+fn run_job(py: Python<'_>, job: Job, stats: &Mutex<ExecutorStats>, cancelled: Option<&Py<PyType>>) {
+    let started = Instant::now();
+    let waited = started - job.queued_at;
+    // the handle was dropped while the job was queued
+    if job.cancellation.cancelled.load(Ordering::SeqCst) {
+        lock(stats).cancelled += 1;
+        return;
+    }
+    let interrupter = cancelled.and_then(|class| match Interrupter::for_current_thread(py, class.clone_ref(py)) {
+        Ok(interrupter) => Some(interrupter),
+        Err(e) => {
+            log::warn!("this Python job can't be cancelled: {e}");
+            None
+        }
+    });
+    // stored before checking `cancelled` again, while CancelOnDrop sets `cancelled` before reading it,
+    // so one of the two sides always sees the other
+    *lock(&job.cancellation.running) = interrupter.clone();
+    if job.cancellation.cancelled.load(Ordering::SeqCst) {
+        lock(stats).cancelled += 1;
+        return;
+    }
+
+    lock(stats).running = true;
+    let deliver = (job.run)(py);
+    let interrupted = interrupter.is_some_and(|interrupter| interrupter.finish());
+    let timing = JobTiming { waited, ran: started.elapsed() };
+    py.allow_threads(|| {
+        let mut stats = lock(stats);
+        stats.running = false;
+        stats.last = Some(timing);
+        if interrupted {
+            stats.cancelled += 1;
+        } else {
+            stats.completed += 1;
+            stats.total_wait += timing.waited;
+            stats.max_wait = stats.max_wait.max(timing.waited);
+            stats.total_run += timing.ran;
+            stats.max_run = stats.max_run.max(timing.ran);
+        }
+        drop(stats);
+        deliver(timing);
+    });
+}
+
+// a job that panicked must not take the stats down with it
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T> JobHandle<T> {
+    // Block until the job finished; Error::ExecutorStopped when the thread is gone (a job panicked)
+    pub fn wait(self) -> Result<T, Error> {
+        self.wait_timed().value
+    }
+
+    // wait() plus how long the job waited and ran
+    // when the executor stopped, `waited` is the time since submitting and `ran` is zero
+    pub fn wait_timed(mut self) -> Timed<Result<T, Error>> {
+        let result = self.result.blocking_recv();
+        self.cancel.0 = None;
+        match result {
+            Ok((value, timing)) => Timed { value, timing },
+            Err(_) => Timed { value: Err(Error::ExecutorStopped), timing: JobTiming { waited: self.submitted.elapsed(), ran: Duration::ZERO } },
+        }
+    }
+
+    // Give up on the job, the same as dropping the handle
+    pub fn cancel(self) {}
+}
+
+impl<T> Future for JobHandle<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = match Pin::new(&mut self.result).poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.cancel.0 = None;
+        Poll::Ready(result.map_or(Err(Error::ExecutorStopped), |(value, _)| value))
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(cancellation) = self.0.take() else { return };
+        cancellation.cancelled.store(true, Ordering::SeqCst);
+        let running = lock(&cancellation.running).clone();
+        if let Some(interrupter) = running {
+            // interrupt() waits for the GIL, which the job holds; don't block the caller (maybe an async runtime) on it
+            thread::spawn(move || interrupter.interrupt());
+        }
+    }
+}
+
+impl ExecutorStats {
+    pub fn mean_wait(&self) -> Duration {
+        mean(self.total_wait, self.completed)
+    }
+
+    pub fn mean_run(&self) -> Duration {
+        mean(self.total_run, self.completed)
+    }
+}
+
+fn mean(total: Duration, count: u64) -> Duration {
+    match u32::try_from(count) {
+        Ok(0) => Duration::ZERO,
+        Ok(count) => total / count,
+        Err(_) => Duration::from_secs_f64(total.as_secs_f64() / count as f64),
+    }
+}
+
+impl std::fmt::Debug for PyExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PyExecutor").field("stats", &self.stats()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc as std_mpsc;
+
+    use super::*;
+
+    // a job that holds the executor until `release` is sent, without keeping the GIL
+    fn block(executor: &PyExecutor) -> (JobHandle<()>, std_mpsc::Sender<()>) {
+        let (started, started_rx) = std_mpsc::channel();
+        let (release, release_rx) = std_mpsc::channel::<()>();
+        let handle = executor
+            .submit(move |py| {
+                started.send(()).unwrap();
+                py.allow_threads(move || release_rx.recv().unwrap());
+                Ok(())
+            })
+            .unwrap();
+        started_rx.recv().unwrap();
+        (handle, release)
+    }
+
+    #[test]
+    fn dropped_handles_skip_queued_jobs() {
+        let executor = PyExecutor::start(4).unwrap();
+        let (blocker, release) = block(&executor);
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&ran);
+        let skipped = executor
+            .submit(move |_py| {
+                flag.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+            .unwrap();
+        drop(skipped);
+        release.send(()).unwrap();
+        blocker.wait().unwrap();
+        executor.run(|_py| Ok(())).unwrap();
+
+        assert!(!ran.load(Ordering::SeqCst));
+        assert_eq!(executor.stats().cancelled, 1);
+    }
+
+    #[test]
+    fn dropped_handles_interrupt_running_jobs() {
+        let executor = PyExecutor::start(4).unwrap();
+        let (started, started_rx) = std_mpsc::channel();
+        let (raised, raised_rx) = std_mpsc::channel();
+        let handle: JobHandle<()> = executor
+            .submit(move |py| {
+                started.send(()).unwrap();
+                let error = py.run("while True:\n    pass\n", None, None).unwrap_err();
+                raised.send(error.get_type(py).name()?.to_owned()).unwrap();
+                Err(error.into())
+            })
+            .unwrap();
+        started_rx.recv().unwrap();
+        handle.cancel();
+
+        assert_eq!(raised_rx.recv_timeout(Duration::from_secs(5)).unwrap(), "CallCancelled");
+        // the executor carries on with the next job
+        assert_eq!(executor.run(|_py| Ok(7)).unwrap(), 7);
+        let stats = executor.stats();
+        assert_eq!((stats.cancelled, stats.completed), (1, 1));
+    }
+
+    #[test]
+    fn stats_count_queued_and_completed_jobs() {
+        let executor = PyExecutor::start(4).unwrap();
+        let (blocker, release) = block(&executor);
+        let ok = executor.submit(|_py| Ok(1)).unwrap();
+        let failed = executor.submit(|py| -> Result<(), Error> { Ok(py.run("raise ValueError('bad')", None, None)?) }).unwrap();
+        let stats = executor.stats();
+        assert_eq!((stats.queue_depth, stats.queue_capacity, stats.running, stats.completed), (2, 4, true, 0));
+
+        thread::sleep(Duration::from_millis(50));
+        release.send(()).unwrap();
+        let blocked = blocker.wait_timed();
+        assert_eq!(ok.wait().unwrap(), 1);
+        // a job returning Err still completed
+        assert!(matches!(failed.wait(), Err(Error::Value(_))));
+
+        let stats = executor.stats();
+        assert_eq!((stats.queue_depth, stats.running, stats.completed, stats.cancelled), (0, false, 3, 0));
+        assert!(blocked.timing.ran >= Duration::from_millis(50));
+        assert_eq!(stats.max_run, blocked.timing.ran);
+        assert!(stats.total_run >= stats.max_run && stats.last.is_some());
+    }
+
+    // the job would wait for itself: for room in the queue only it can free, or for its own turn
+    #[test]
+    fn jobs_cant_wait_for_their_own_executor() {
+        let executor = PyExecutor::start(1).unwrap();
+        let inner = executor.clone();
+        let (queued, full, nested) = executor
+            .run(move |_py| {
+                let queued = inner.submit(|_py| Ok("inner"))?;
+                let full = inner.submit(|_py| Ok("too many")).map(|_| ());
+                let nested = inner.run(|_py| Ok(()));
+                Ok((queued, full, nested))
+            })
+            .unwrap();
+        assert!(matches!(full, Err(Error::ExecutorReentered)));
+        assert!(matches!(nested, Err(Error::ExecutorReentered)));
+        assert_eq!(queued.wait().unwrap(), "inner");
+    }
+}
@@ -0,0 +1,225 @@
+use std::any::type_name;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use pyo3::exceptions::PyAttributeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyModule, PyTuple};
+
+use crate::capture::{capture, Capture, Captured};
+use crate::error::Error;
+use crate::loader::{display_name, module_generation, ModuleLoader};
+use crate::signature::{PyAnnotation, PyArgs, Signature};
+use crate::timeout::{default_timeout, run_with_timeout};
+
+// A Python function bound once by module and name, called with a Rust tuple
+//     let random_number: PyFunction<(i32, i32), i32> = PyFunction::new("functions_venv", "random_number");
+//     let n = random_number.call((1, 10))?;
+// arguments are converted with IntoPy and the return value with FromPyObject, so Ret can be
+// i32, String, Vec<T>, Option<T>, ... (use PyObject when the return value doesn't matter)
+// failures name the function: Error::MissingFunction when it doesn't exist and Error::ReturnType
+// when the value it returned doesn't convert to Ret; exceptions raised inside it keep their usual variant
+// bind() and validate() compare Args and Ret with the function's type hints before anything is called
+pub struct PyFunction<Args, Ret> {
+    module: ModuleRef,
+    name: String,
+    // names of the Rust arguments, checked against the Python parameter names when set
+    arg_names: Option<Vec<String>>,
+    // None uses timeout::default_timeout()
+    timeout: Option<Duration>,
+    signature: PhantomData<fn(Args) -> Ret>,
+}
+
+#[derive(Clone)]
+enum ModuleRef {
+    // imported by dotted name through the shared ModuleLoader on every call (a cache hit after the
+    // first one), so edits picked up by hot reload are used right away
+    Import(String),
+    // a module created some other way, ex. PyModule::from_code
+    Module(Py<PyModule>),
+}
+
+impl<Args, Ret> PyFunction<Args, Ret>
+where
+    Args: IntoPy<Py<PyTuple>> + PyArgs,
+    Ret: for<'p> FromPyObject<'p> + PyAnnotation,
+{
+    // bind `name` from the module imported as `module` (ex. "functions_venv")
+    // nothing is imported until the first call
+    pub fn new(module: &str, name: &str) -> PyFunction<Args, Ret> {
+        PyFunction { module: ModuleRef::Import(module.to_owned()), name: name.to_owned(), arg_names: None, timeout: None, signature: PhantomData }
+    }
+
+    // bind `name` from a module that is already loaded
+    pub fn from_module(module: Py<PyModule>, name: &str) -> PyFunction<Args, Ret> {
+        PyFunction { module: ModuleRef::Module(module), name: name.to_owned(), arg_names: None, timeout: None, signature: PhantomData }
+    }
+
+    // new() followed by validate(): imports the module now and fails with Error::SignatureMismatch
+    // if the Python signature doesn't fit Args and Ret
+    pub fn bind(py: Python<'_>, module: &str, name: &str) -> Result<PyFunction<Args, Ret>, Error> {
+        let function = PyFunction::new(module, name);
+        function.validate(py)?;
+        Ok(function)
+    }
+
+    // Also check the Python parameter names, ex. `color` in `fn color_emoji(color: &str)`
+    pub fn with_arg_names(mut self, names: &[&str]) -> PyFunction<Args, Ret> {
+        self.arg_names = Some(names.iter().map(|name| (*name).to_owned()).collect());
+        self
+    }
+
+    // Interrupt calls that take longer than this with Error::Timeout, overriding the global default
+    pub fn with_timeout(mut self, timeout: Duration) -> PyFunction<Args, Ret> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Call the function, acquiring the GIL
+    pub fn call(&self, args: Args) -> Result<Ret, Error> {
+        Python::with_gil(|py| self.call_with_gil(py, args))
+    }
+
+    // Call the function with its stdout/stderr handled according to `mode`, see capture()
+    pub fn call_captured(&self, mode: &Capture, args: Args) -> Captured<Result<Ret, Error>> {
+        capture(mode, || self.call(args))
+    }
+
+    // Call the function when the GIL is already held
+    pub fn call_with_gil(&self, py: Python<'_>, args: Args) -> Result<Ret, Error> {
+        let call = || self.function(py)?.call1(args).map_err(|e| Error::from_pyerr(py, e));
+        let result = match self.timeout.or_else(default_timeout) {
+            Some(timeout) => run_with_timeout(timeout, &self.qualified_name(py), call)?,
+            None => call()?,
+        };
+        result.extract().map_err(|e: PyErr| Error::ReturnType {
+            function: self.qualified_name(py),
+            target: type_name::<Ret>(),
+            // Option<T> extracts None as None at any depth (ex. Option<Vec<Option<i64>>>), so a top-level None
+            // failing means Ret isn't an Option
+            message: if result.is_none() { "it returned None, use an Option to accept that".to_owned() } else { e.to_string() },
+        })
+    }
+
+    // Look the function up without calling it, ex. to fail early when it was renamed in the .py file
+    pub fn function<'py>(&self, py: Python<'py>) -> Result<&'py PyAny, Error> {
+        let module = self.module(py)?;
+        match module.getattr(self.name.as_str()) {
+            Ok(function) if function.is_callable() => Ok(function),
+            Ok(_) => Err(self.missing(py)),
+            Err(err) if err.is_instance_of::<PyAttributeError>(py) => Err(self.missing(py)),
+            Err(err) => Err(Error::from_pyerr(py, err)),
+        }
+    }
+
+    // Compare Args, Ret (and the argument names, if given) with inspect.signature() of the function
+    // only annotated parameters have their types checked; the number of arguments is always checked
+    pub fn validate(&self, py: Python<'_>) -> Result<Signature, Error> {
+        let signature = Signature::of(py, self.function(py)?)?;
+        let problems = signature.check_call(self.arg_names.as_deref(), &Args::type_hints(), &Ret::type_hint());
+        if !problems.is_empty() {
+            return Err(Error::SignatureMismatch { function: self.qualified_name(py), problems });
+        }
+        Ok(signature)
+    }
+
+    // validate() unless `checked` says it already passed, for bindings that are created on every call
+    // (py_bindings! keeps one per generated function, starting at 0)
+    // `checked` holds the module_generation() the check passed in, plus one, so a hot reload or unload
+    // makes the next call check the new version of the function again
+    pub fn validate_once(&self, py: Python<'_>, checked: &AtomicU64) -> Result<(), Error> {
+        // read before validating: a reload in between makes the stored value stale, not wrongly current
+        let generation = module_generation() + 1;
+        if checked.load(Ordering::Acquire) != generation {
+            self.validate(py)?;
+            checked.store(generation, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    fn module<'py>(&self, py: Python<'py>) -> Result<&'py PyModule, Error> {
+        match &self.module {
+            ModuleRef::Import(name) => {
+                let module = ModuleLoader::with_global(py, |loader| loader.import(py, name))?;
+                Ok(module.into_ref(py))
+            }
+            ModuleRef::Module(module) => Ok(module.clone_ref(py).into_ref(py)),
+        }
+    }
+
+    fn missing(&self, py: Python<'_>) -> Error {
+        Error::MissingFunction { module: self.module_name(py), function: self.name.clone() }
+    }
+
+    // "functions_venv.random_number"
+    fn qualified_name(&self, py: Python<'_>) -> String {
+        format!("{}.{}", self.module_name(py), self.name)
+    }
+
+    fn module_name(&self, py: Python<'_>) -> String {
+        match &self.module {
+            ModuleRef::Import(name) => name.clone(),
+            ModuleRef::Module(module) => display_name(module.as_ref(py).name().unwrap_or("<module>")).to_owned(),
+        }
+    }
+}
+
+// not derived, which would require Args and Ret to be Clone
+impl<Args, Ret> Clone for PyFunction<Args, Ret> {
+    fn clone(&self) -> Self {
+        PyFunction {
+            module: self.module.clone(),
+            name: self.name.clone(),
+            arg_names: self.arg_names.clone(),
+            timeout: self.timeout,
+            signature: PhantomData,
+        }
+    }
+}
+
+impl<Args, Ret> fmt::Debug for PyFunction<Args, Ret> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let module = match &self.module {
+            ModuleRef::Import(name) => name.as_str(),
+            ModuleRef::Module(_) => "<module>",
+        };
+        write!(f, "PyFunction({module}.{}: {} -> {})", self.name, type_name::<Args>(), type_name::<Ret>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::{bump_generation, module_from_code, unload_module};
+
+    #[test]
+    fn validate_once_checks_again_after_a_reload() {
+        Python::with_gil(|py| {
+            let module = module_from_code(py, "def add(a: int, b: int) -> int:\n    return a + b\n", "validate_once.py", "validate_once").unwrap();
+            let function = PyFunction::<(i64, i64), i64>::from_module(module.into(), "add");
+            let checked = AtomicU64::new(0);
+            function.validate_once(py, &checked).unwrap();
+
+            // the signature changes under the binding, as with a hot reload
+            py.run("def add(a: str) -> str:\n    return a\n", Some(module.dict()), None).unwrap();
+            function.validate_once(py, &checked).unwrap();
+
+            // what reloading or unloading any module does
+            bump_generation();
+            match function.validate_once(py, &checked) {
+                Err(Error::SignatureMismatch { function, problems }) => {
+                    assert_eq!(function, "validate_once.add");
+                    assert_eq!(problems.len(), 3, "{problems:?}");
+                }
+                other => panic!("expected Error::SignatureMismatch, got {other:?}"),
+            }
+            unload_module(py, module).unwrap();
+        });
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use log::Level;
+use pyo3::exceptions::{PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::once_cell::GILOnceCell;
+use pyo3::prelude::*;
+use pyo3::types::{PyCFunction, PyDict, PyModule, PyTuple};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error::Error;
+use crate::loader::helper_module;
+use crate::signature::{PyAnnotation, PyArgs, TypeHint};
+
+const HOST_MODULE: &str = include_str!("host_module.py");
+
+// what the Python code imports: `import rust_host`
+pub const HOST_MODULE_NAME: &str = "rust_host";
+
+// Rust functions the embedded Python code can call back into, as the module `rust_host`
+// the standard functions are registered before the first user module is loaded (module_from_code and
+// ModuleLoader::import both call install()), so a .py file can `import rust_host` at the top:
+//     rust_host.log(level: str, message: str) -> None            to the Rust `log` facade, target "rust_host"
+//     rust_host.config(key: str) -> Optional[str]                 values set with set_config()
+//     rust_host.random_int(low: int, high: int) -> int            from a Rust RNG, both ends included
+//     rust_host.random() -> float                                 in [0, 1)
+//     rust_host.seed(seed: int) -> None                           makes the two above repeatable
+//     rust_host.progress(done: int, total: int, message: str) -> None    to set_progress_handler()
+// more functions are declared the same way, with the Python parameter names next to a typed closure:
+//     HostModule::new()
+//         .function("word_count", "Count the words in text", &["text"], |text: String| Ok(text.split_whitespace().count() as i64))
+//         .install(py)?;
+// the Python types come from the Rust ones (PyAnnotation), and arguments are checked against them in Python
+// rust_host only exists in this process: WorkerPool workers can't import it
+#[derive(Default)]
+pub struct HostModule {
+    functions: Vec<HostFunction>,
+}
+
+struct HostFunction {
+    name: String,
+    doc: String,
+    params: Vec<(String, TypeHint)>,
+    returns: TypeHint,
+    call: NativeCall,
+    // found while declaring it, reported by install()
+    problems: Vec<String>,
+}
+
+// A Rust closure taking the arguments of a host function one by one, ex. |a: i64, b: String| -> Result<Ret, Error>
+// implemented for closures of up to 4 arguments
+pub trait HostFn<Args, Ret>: Send + Sync + 'static {
+    // the arguments come from the Python wrapper, which already checked their number and types
+    fn call(&self, args: &PyTuple) -> PyResult<Result<Ret, Error>>;
+}
+
+macro_rules! host_fn {
+    ($($arg:ident : $index:tt),*) => {
+        impl<F, Ret, $($arg),*> HostFn<($($arg,)*), Ret> for F
+        where
+            F: Fn($($arg),*) -> Result<Ret, Error> + Send + Sync + 'static,
+            $($arg: for<'p> FromPyObject<'p>,)*
+        {
+            #[allow(unused_variables)]
+            fn call(&self, args: &PyTuple) -> PyResult<Result<Ret, Error>> {
+                Ok(self($(args.get_item($index)?.extract()?),*))
+            }
+        }
+    };
+}
+
+host_fn!();
+host_fn!(A: 0);
+host_fn!(A: 0, B: 1);
+host_fn!(A: 0, B: 1, C: 2);
+host_fn!(A: 0, B: 1, C: 2, D: 3);
+
+// A progress report from rust_host.progress()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub done: u64,
+    pub total: u64,
+    pub message: String,
+}
+
+// extracts the arguments, runs the Rust closure and converts its result
+type NativeCall = Arc<dyn Fn(Python<'_>, &PyTuple) -> PyResult<PyObject> + Send + Sync>;
+
+pub type ProgressHandler = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+static CONFIG: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+static PROGRESS_HANDLER: RwLock<Option<ProgressHandler>> = RwLock::new(None);
+// created from the OS on first use unless seed() was called
+static RNG: Mutex<Option<StdRng>> = Mutex::new(None);
+
+impl HostModule {
+    pub fn new() -> HostModule {
+        HostModule::default()
+    }
+
+    // log, config, random_int, random, seed and progress
+    pub fn standard() -> HostModule {
+        HostModule::new()
+            .function("log", "Log message at level (error, warning, info, debug or trace) through Rust's log facade", &["level", "message"], log_message)
+            .function("config", "The configuration value for key, None when it isn't set", &["key"], |key: String| Ok(config(&key)))
+            .function("random_int", "A random integer from low to high, both included, from the Rust RNG", &["low", "high"], random_int)
+            .function("random", "A random float in [0, 1) from the Rust RNG", &[], || Ok(with_rng(|rng| rng.random::<f64>())))
+            // i64 rather than u64, so seed(-1) works like any other Python int that fits in 64 bits
+            .function("seed", "Seed the Rust RNG so random_int() and random() repeat", &["seed"], |seed: i64| {
+                *RNG.lock().unwrap_or_else(PoisonError::into_inner) = Some(StdRng::seed_from_u64(seed as u64));
+                Ok(())
+            })
+            .function("progress", "Report that done of total steps are finished", &["done", "total", "message"], report_progress)
+    }
+
+    // Declare a function: its Python name, docstring, parameter names and the Rust closure behind it
+    // the closure's argument and return types must implement PyAnnotation (i64, String, Option<T>, Vec<T>, ...)
+    pub fn function<Args, Ret>(mut self, name: &str, doc: &str, arg_names: &[&str], f: impl HostFn<Args, Ret>) -> HostModule
+    where
+        Args: PyArgs + 'static,
+        Ret: IntoPy<PyObject> + PyAnnotation + 'static,
+    {
+        let hints = Args::type_hints();
+        let mut problems = Vec::new();
+        if hints.len() != arg_names.len() {
+            problems.push(format!("{} parameter name(s) given for {} Rust argument(s)", arg_names.len(), hints.len()));
+        }
+        if !is_identifier(name) || arg_names.iter().any(|arg| !is_identifier(arg)) {
+            problems.push("the function and parameter names must be Python identifiers".to_owned());
+        }
+        let call = move |py: Python<'_>, args: &PyTuple| -> PyResult<PyObject> {
+            // extracting only fails for numbers out of range, ex. a negative u64
+            f.call(args)?.map(|value| value.into_py(py)).map_err(into_pyerr)
+        };
+        self.functions.push(HostFunction {
+            name: name.to_owned(),
+            doc: doc.to_owned(),
+            params: arg_names.iter().map(|arg| (*arg).to_owned()).zip(hints).collect(),
+            returns: Ret::type_hint(),
+            call: Arc::new(call),
+            problems,
+        });
+        self
+    }
+
+    // Add the functions to rust_host, creating the module (and its sys.modules entry) if needed
+    // a function that was already there is replaced
+    pub fn install(self, py: Python<'_>) -> Result<&PyModule, Error> {
+        if let Some(function) = self.functions.iter().find(|function| !function.problems.is_empty()) {
+            return Err(Error::SignatureMismatch { function: format!("{HOST_MODULE_NAME}.{}", function.name), problems: function.problems.clone() });
+        }
+        static MAKE_FUNCTION: GILOnceCell<PyObject> = GILOnceCell::new();
+        let make_function = MAKE_FUNCTION.get_or_try_init(py, || -> PyResult<PyObject> {
+            let helper = helper_module(py, HOST_MODULE, "host_module.py", "host_module")?;
+            Ok(helper.getattr("make_function")?.into())
+        })?;
+
+        let sys_modules: &PyDict = py.import("sys")?.getattr("modules")?.downcast().map_err(PyErr::from)?;
+        let module: &PyModule = match sys_modules.get_item(HOST_MODULE_NAME) {
+            Some(module) => module.downcast().map_err(PyErr::from)?,
+            None => {
+                let module = PyModule::new(py, HOST_MODULE_NAME)?;
+                module.setattr("__doc__", "Functions provided by the Rust program embedding this interpreter")?;
+                sys_modules.set_item(HOST_MODULE_NAME, module)?;
+                module
+            }
+        };
+        for function in self.functions {
+            let call = function.call;
+            let native = PyCFunction::new_closure(py, None, None, move |args: &PyTuple, _kwargs: Option<&PyDict>| call(args.py(), args))?;
+            let params: Vec<(String, String)> = function.params.iter().map(|(name, hint)| (name.clone(), hint.to_string())).collect();
+            let wrapper = make_function.call1(py, (HOST_MODULE_NAME, &function.name, function.doc, params, function.returns.to_string(), native))?;
+            module.setattr(function.name.as_str(), wrapper)?;
+        }
+        Ok(module)
+    }
+}
+
+// Register the standard rust_host functions, once per process; calling it again does nothing
+pub fn install(py: Python<'_>) -> Result<(), Error> {
+    static INSTALLED: GILOnceCell<()> = GILOnceCell::new();
+    INSTALLED.get_or_try_init(py, || HostModule::standard().install(py).map(|_| ()))?;
+    Ok(())
+}
+
+// Set the value rust_host.config(key) returns
+pub fn set_config(key: &str, value: &str) {
+    let mut config = CONFIG.lock().unwrap_or_else(PoisonError::into_inner);
+    config.get_or_insert_with(HashMap::new).insert(key.to_owned(), value.to_owned());
+}
+
+pub fn config(key: &str) -> Option<String> {
+    CONFIG.lock().unwrap_or_else(PoisonError::into_inner).as_ref()?.get(key).cloned()
+}
+
+// Send rust_host.progress() calls to `handler`, None to go back to logging them at info level
+pub fn set_progress_handler(handler: Option<ProgressHandler>) {
+    *PROGRESS_HANDLER.write().unwrap_or_else(PoisonError::into_inner) = handler;
+}
+
+fn log_message(level: String, message: String) -> Result<(), Error> {
+    let level = match level.to_ascii_lowercase().as_str() {
+        "critical" | "error" => Level::Error,
+        "warning" | "warn" => Level::Warn,
+        "info" => Level::Info,
+        "debug" => Level::Debug,
+        "trace" => Level::Trace,
+        _ => return Err(Error::Usage(format!("unknown log level {level:?}, use error, warning, info, debug or trace"))),
+    };
+    log::log!(target: HOST_MODULE_NAME, level, "{message}");
+    Ok(())
+}
+
+fn random_int(low: i64, high: i64) -> Result<i64, Error> {
+    if low > high {
+        return Err(Error::Usage(format!("random_int() needs low <= high, got {low} and {high}")));
+    }
+    Ok(with_rng(|rng| rng.random_range(low..=high)))
+}
+
+fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    let mut rng = RNG.lock().unwrap_or_else(PoisonError::into_inner);
+    f(rng.get_or_insert_with(StdRng::from_os_rng))
+}
+
+fn report_progress(done: u64, total: u64, message: String) -> Result<(), Error> {
+    let progress = Progress { done, total, message };
+    // cloned so a handler that calls set_progress_handler() doesn't deadlock
+    let handler = PROGRESS_HANDLER.read().unwrap_or_else(PoisonError::into_inner).clone();
+    match handler {
+        Some(handler) => handler(&progress),
+        None => log::info!(target: HOST_MODULE_NAME, "{progress}"),
+    }
+    Ok(())
+}
+
+// the exception Python sees when a host function returns an Err
+fn into_pyerr(error: Error) -> PyErr {
+    match error {
+        Error::Type(_) => PyTypeError::new_err(error.to_string()),
+        Error::Value(_) | Error::Conversion { .. } | Error::Usage(_) => PyValueError::new_err(error.to_string()),
+        _ => PyRuntimeError::new_err(error.to_string()),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+impl Progress {
+    // None when total is 0
+    pub fn percent(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.done as f64 * 100.0 / self.total as f64)
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.percent() {
+            Some(percent) => write!(f, "{}: {}/{} ({percent:.0}%)", self.message, self.done, self.total),
+            None => write!(f, "{}: {}", self.message, self.done),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // runs `code` with rust_host imported and returns its globals
+    fn run<'py>(py: Python<'py>, code: &str) -> PyResult<&'py PyDict> {
+        install(py).unwrap();
+        let globals = PyDict::new(py);
+        py.run(&format!("import rust_host\n{code}"), Some(globals), None)?;
+        Ok(globals)
+    }
+
+    #[test]
+    fn arguments_are_checked_in_python() {
+        Python::with_gil(|py| {
+            let cases = [
+                ("rust_host.random_int('1', 2)", "random_int() argument 'low' must be int, not str"),
+                ("rust_host.random_int(1, True)", "random_int() argument 'high' must be int, not bool"),
+                ("rust_host.config(None)", "config() argument 'key' must be str, not NoneType"),
+                ("rust_host.progress(1, 2, b'bytes')", "progress() argument 'message' must be str, not bytes"),
+            ];
+            for (code, message) in cases {
+                let error = run(py, code).unwrap_err();
+                assert!(error.is_instance_of::<PyTypeError>(py), "{code}: {error}");
+                assert_eq!(error.value(py).to_string(), message, "{code}");
+            }
+            // too many or too few arguments fail in inspect.Signature.bind()
+            assert!(run(py, "rust_host.random_int(1)").unwrap_err().is_instance_of::<PyTypeError>(py));
+            // errors from the Rust side keep their type
+            assert!(run(py, "rust_host.random_int(2, 1)").unwrap_err().is_instance_of::<PyValueError>(py));
+            let globals = run(py, "value = rust_host.random_int(low=3, high=3)").unwrap();
+            assert_eq!(globals.get_item("value").unwrap().extract::<i64>().unwrap(), 3);
+        });
+    }
+
+    #[test]
+    fn seed_makes_the_rng_repeat() {
+        Python::with_gil(|py| {
+            let code = "def draw():\n    return [rust_host.random_int(0, 1000) for _ in range(5)], rust_host.random()\n\n\
+                        result = []\nfor seed in (-1, -1, 2**63 - 1, 42, 42):\n    rust_host.seed(seed)\n    result.append(draw())\n";
+            let draws: Vec<(Vec<i64>, f64)> = run(py, code).unwrap().get_item("result").unwrap().extract().unwrap();
+            assert_eq!(draws[0], draws[1]);
+            assert_eq!(draws[3], draws[4]);
+            assert_ne!(draws[0], draws[3]);
+            assert!(draws.iter().all(|(ints, float)| ints.iter().all(|n| (0..=1000).contains(n)) && (0.0..1.0).contains(float)));
+            // still a 64-bit seed
+            assert!(run(py, "rust_host.seed(2**64)").unwrap_err().is_instance_of::<pyo3::exceptions::PyOverflowError>(py));
+        });
+    }
+
+    #[test]
+    fn config_values_come_from_rust() {
+        set_config("host_test_key", "value");
+        Python::with_gil(|py| {
+            let globals = run(py, "value = rust_host.config('host_test_key')\nmissing = rust_host.config('host_test_missing')").unwrap();
+            assert_eq!(globals.get_item("value").unwrap().extract::<&str>().unwrap(), "value");
+            assert!(globals.get_item("missing").unwrap().is_none());
+        });
+        set_config("host_test_key", "changed");
+        assert_eq!(config("host_test_key").as_deref(), Some("changed"));
+    }
+
+    #[test]
+    fn progress_goes_to_the_handler() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&reports);
+        set_progress_handler(Some(Arc::new(move |progress: &Progress| seen.lock().unwrap().push(progress.clone()))));
+        let result = Python::with_gil(|py| run(py, "rust_host.progress(1, 4, 'started')\nrust_host.progress(done=4, total=4, message='done')").map(|_| ()));
+        set_progress_handler(None);
+        result.unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(*reports, [Progress { done: 1, total: 4, message: "started".to_owned() }, Progress { done: 4, total: 4, message: "done".to_owned() }]);
+        assert_eq!(reports[0].percent(), Some(25.0));
+        assert_eq!(reports[1].to_string(), "done: 4/4 (100%)");
+    }
+
+    #[test]
+    fn install_rejects_badly_declared_functions() {
+        Python::with_gil(|py| {
+            let cases: [(HostModule, &str); 2] = [
+                (
+                    HostModule::new().function("host_test_add", "", &["a"], |a: i64, b: i64| Ok(a + b)),
+                    "1 parameter name(s) given for 2 Rust argument(s)",
+                ),
+                (
+                    HostModule::new().function("host_test_len", "", &["the text"], |text: String| Ok(text.len() as i64)),
+                    "the function and parameter names must be Python identifiers",
+                ),
+            ];
+            for (module, problem) in cases {
+                match module.install(py) {
+                    Err(Error::SignatureMismatch { problems, .. }) => assert_eq!(problems, [problem]),
+                    other => panic!("expected Error::SignatureMismatch, got {:?}", other.map(|_| ())),
+                }
+            }
+            install(py).unwrap();
+            let rust_host = py.import(HOST_MODULE_NAME).unwrap();
+            assert!(!rust_host.hasattr("host_test_add").unwrap() && !rust_host.hasattr("host_test_len").unwrap());
+        });
+    }
+}
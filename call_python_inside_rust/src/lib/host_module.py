@@ -0,0 +1,56 @@
+# Python side of the rust_host module (see host.rs)
+# every Rust function is wrapped in a Python function with a real signature and annotations, so
+# inspect.signature(), typing.get_type_hints() and help() describe it, and arguments of the wrong type
+# are rejected with a TypeError naming the parameter before any Rust code runs
+import inspect
+import typing
+
+_NAMESPACE = {"Optional": typing.Optional, "Any": typing.Any}
+
+
+def _matches(value, hint):
+    if hint is typing.Any:
+        return True
+    if hint is None or hint is type(None):
+        return value is None
+    origin = typing.get_origin(hint)
+    args = typing.get_args(hint)
+    if origin is typing.Union:
+        return any(_matches(value, arg) for arg in args)
+    if origin is list:
+        return isinstance(value, list) and all(_matches(item, args[0]) for item in value)
+    if origin is dict:
+        return isinstance(value, dict) and all(_matches(k, args[0]) and _matches(v, args[1]) for k, v in value.items())
+    if origin is tuple:
+        return isinstance(value, tuple) and len(value) == len(args) and all(map(_matches, value, args))
+    # bool is a subclass of int, but True isn't a number to Rust
+    if hint is int:
+        return isinstance(value, int) and not isinstance(value, bool)
+    if hint is float:
+        return isinstance(value, (int, float)) and not isinstance(value, bool)
+    return isinstance(value, hint)
+
+
+def make_function(module, name, doc, params, returns, call):
+    # params is a list of (name, annotation) with annotations as written by TypeHint's Display ("Optional[str]")
+    hints = {param: eval(annotation, _NAMESPACE) for param, annotation in params}
+    return_hint = eval(returns, _NAMESPACE)
+    signature = inspect.Signature(
+        [inspect.Parameter(param, inspect.Parameter.POSITIONAL_OR_KEYWORD, annotation=hint) for param, hint in hints.items()],
+        return_annotation=return_hint,
+    )
+    annotations = dict(params)
+
+    def function(*args, **kwargs):
+        bound = signature.bind(*args, **kwargs)
+        for param, value in bound.arguments.items():
+            if not _matches(value, hints[param]):
+                raise TypeError(f"{name}() argument '{param}' must be {annotations[param]}, not {type(value).__name__}")
+        return call(*bound.args)
+
+    function.__name__ = function.__qualname__ = name
+    function.__module__ = module
+    function.__doc__ = doc
+    function.__signature__ = signature
+    function.__annotations__ = {**hints, "return": return_hint}
+    return function
@@ -3,31 +3,66 @@
     use std::ffi::OsStr;
     use std::io::prelude::*;
     use std::io;
-    use std::io::Error;
     use std::io::ErrorKind;
     use std::collections::HashMap;
     use std::path::Path;
     use std::fs::File;
     
-    use std::process::Command; // used to do "$pip list" from inside Rust
-    
-    use pyo3::exceptions::PyModuleNotFoundError;
-    use pyo3::exceptions::PySyntaxError;
-    use pyo3::exceptions::PyTypeError;
-    use pyo3::exceptions::PyValueError;
     use pyo3::prelude::*;
-    use pyo3::types::PyModule;
     use pyo3::types::PyTuple;
     use pyo3::types::IntoPyDict;
+
+    mod error;
+    pub use error::{extract, Error, PyException};
+    pub mod traceback;
+    pub use traceback::Traceback;
+    mod loader;
+    pub use loader::{module_from_code, module_generation, namespaced_modules, unload_module, CacheStats, LoadedModule, ModuleLoader, ReloadEvent, MODULE_NAMESPACE};
+    pub mod search_path;
+    pub use search_path::SearchPath;
+    pub mod sources;
+    pub use sources::{EmbeddedSource, PySource, SourceInfo, SourceOrigin, SourcePreference, SourceSet};
+    pub mod venv;
+    pub use venv::{ActiveEnvironment, VenvLocator, VenvOrigin, VirtualEnv};
+    pub mod bootstrap;
+    pub use bootstrap::{BootstrapOptions, BootstrapReport};
+    pub mod packages;
+    mod function;
+    pub use function::PyFunction;
+    pub mod signature;
+    pub use signature::{PyAnnotation, PyArgs, Signature, TypeHint};
+    pub mod bindings;
+    pub mod capture;
+    pub mod logging;
+    pub mod timeout;
+    pub mod executor;
+    pub use executor::{ExecutorStats, JobHandle, JobTiming, PyExecutor, Timed};
+    pub mod async_python;
+    pub use async_python::AsyncPython;
+    pub mod worker;
+    pub use worker::{WorkerFunction, WorkerOptions, WorkerPool};
+    pub mod host;
+    pub use host::{HostFn, HostModule, Progress, ProgressHandler, HOST_MODULE_NAME};
+    pub use capture::{capture, Capture, Captured, OutputStream};
+    pub mod codegen;
+    pub use codegen::{CodegenOptions, CodegenReport};
+    pub mod emoji;
+    pub use emoji::Emoji;
+    // Rust functions for the annotated functions in py/*.py, written by `cargo run -- codegen`
+    pub mod py_functions;
+    pub use packages::{OutputFormat, PackageInfo};
+    pub mod requirements;
+    pub use requirements::{Requirement, RequirementsPolicy, RequirementsReport};
     
     // Example functions are defined below
     
     // Example 1
     // Simplest method-- write a python snippet as a rust string and execute it using .eval()
-    // The function returns a PyResult which is a public type representing the result of a python call
+    // The function returns a Result using the crate's Error type
+    // python calls themselves return a PyResult which is a public type representing the result of a python call
     //    pub type PyResult<T> = Result<T, PyErr>
-    // where PyErr  represents a python exception 
-    pub fn simple_inline_python_code() -> PyResult<()> {
+    // where PyErr  represents a python exception; `?` converts it into the matching Error variant
+    pub fn simple_inline_python_code() -> Result<(), Error> {
         // Initialize Python interpreter and acquire Global Interpreter Lock
         println!("\nInitializing py interpreter...");
         Python::with_gil(|py| {
@@ -45,7 +80,7 @@
             py.eval(code, None, None)?;
             println!("\n-----end of py output-----\nEvaluation completed\n");
     
-            // Finished--> PyResult = Ok
+            // Finished--> Result = Ok
             Ok(())
         })
     }
@@ -56,7 +91,7 @@
     // that has been installed for the active python interpreter
     // the python snippet in this example depends on import sys and 
     // import os, two default packages
-    pub fn print_python_version() -> PyResult<()> {
+    pub fn print_python_version() -> Result<(), Error> {
         // Initialize Python interpreter and acquire Global Interpreter Lock
         println!("\nInitializing py interpreter...");
         Python::with_gil(|py| {
@@ -87,13 +122,13 @@
             // remember to pass in the libraries via "locals"
             // and convert the result from a python type to rust type using .extract()
             println!("\nEvaluating...\n-----start of py output-----\n");
-            let user: String = py.eval(code, None, Some(&locals))?.extract()?;
+            let user: String = py.eval(code, None, Some(locals))?.extract()?;
             println!("\n-----end of py output-----\nEvaluation completed");
     
             // the values we got from python can now be used in Rust
             println!("\nRust Output:\n\tUser: {}, \n\tVersion: {}\n", user, version);
     
-            // Finished--> PyResult = Ok
+            // Finished--> Result = Ok
             Ok(())
         })
     }
@@ -101,7 +136,7 @@
     // Example 3
     // This example shows some ways to pass one or more args from Rust to Python
     // It also shows how to evaluate a python code containing multiple expressions
-    pub fn python_function_with_args() -> PyResult<()> {
+    pub fn python_function_with_args() -> Result<(), Error> {
         // Define the arguments that will be sent to the python function
         let arg1 = "arg1";
         let arg2 = "arg2";
@@ -124,11 +159,13 @@
             // for longer code we can create a pyModule instead of using .eval()
             // it can contain one or more function definitions
             // the functions can then be accessed as attributes of the pymodule
-            let example_function: Py<PyAny> = PyModule::from_code(
+            // module_from_code() is PyModule::from_code with a name of its own in sys.modules ("_examples.example_<n>"),
+            // so this module can't replace (or be replaced by) another one called "example"
+            let example_function: Py<PyAny> = module_from_code(
                 py,
-                &code,
-                "",
+                code,
                 "",
+                "example",
             )? // create a PyModule from the provided snippet of code
             .getattr("example")? // extract the function called "example"
             .into(); // type conversion
@@ -142,7 +179,7 @@
             // args can be provided via PyTuple
             // To call the function with one or more arguments, use .call1()
             println!("\nDemo#3.2 Call function with PyTuple arguments\nEvaluating...\n-----start of py output-----\n");
-            let args = PyTuple::new(py, &[arg1, arg2, arg3]);
+            let args = PyTuple::new(py, [arg1, arg2, arg3]);
             example_function.call1(py, args)?;
             println!("\n-----end of py output-----\nDemo#3.2 completed\n");
     
@@ -153,14 +190,14 @@
             example_function.call1(py, args)?;
             println!("\n-----end of py output-----\nDemo#3.3 completed\n");
     
-            // Finished--> PyResult = Ok
+            // Finished--> Result = Ok
             Ok(())
         })
     }
     
     // Example 4
     // This example shows some ways to pass one or more kwargs from Rust to Python
-    pub fn python_function_with_kwargs() -> PyResult<()> {
+    pub fn python_function_with_kwargs() -> Result<(), Error> {
         // Define the keywords arguments that will be sent to the python function
         let key1 = "key1";
         let val1 = 1;
@@ -181,11 +218,11 @@
             println!("\nPython code to evaluate:\n-----start of py code-----\n\n{code}\n\n-----end of py code-----");
             
             // create a new PyModule containing the python code and extract the function using .getattr()
-            let example_function: Py<PyAny> = PyModule::from_code(
+            let example_function: Py<PyAny> = module_from_code(
                 py,
                 code,
                 "",
-                "",
+                "example",
             )?
             .getattr("example")?
             .into();
@@ -220,30 +257,39 @@
     // can be used similar to previous examples
     // This example based in part on 
     // https://python.plainenglish.io/using-python-in-rus-and-trust-in-python-ac5cf77d5ece
-    pub fn python_function_from_file() -> PyResult<i32> {
+    pub fn python_function_from_file() -> Result<i32, Error> {
         
         // Initialize Python interpreter and acquire Global Interpreter Lock
         println!("\nInitializing py interpreter...");
         Python::with_gil(|py| {
             // first we need to grab the python code from a local file
             // Create a path to the desired file
-            let code = get_py_file_contents("py/functions.py")?; 
+            // the file is looked up with the default SourceSet: py/functions.py on disk if it can be found
+            // (from any working directory), otherwise the copy compiled into the binary
+            let source = SourceSet::default().load("functions.py")?;
+            println!("Using {}", source.info);
+            let file_name = source.file_name();
+            let code = source.code; 
+            // let linecache know about the code so tracebacks can show the failing line
+            traceback::register_source(py, &file_name, &code)?;
             println!("\nPython code to evaluate:\n-----start of py code-----\n\n{code}\n\n-----end of py code-----");
             
             // create PyModule from contents of file
             // this is used to access individual functions separately
-            let functions = PyModule::from_code(
+            // (registered as "_examples.functions_<n>", so it doesn't clash with py/functions.py imported elsewhere)
+            let functions = module_from_code(
                 py,
                 &code,
-                "functions.py",
+                &file_name,
                 "functions"
-            ).unwrap();
+            )?;
     
             // grab the desired function using .getattr("function_name")
             // call the function using .call0() exe without args (or in this case use default args)
+            // `?` hands any PyErr back to the caller as an Error
             println!("\nDemo#5.1 Call function without args\nEvaluating...\n-----start of py output-----\n");
-            let add_function = functions.getattr("add_numbers").unwrap();
-            let function_result = add_function.call0().unwrap(); // instead of unwrap, try to handle the pyresult directly?
+            let add_function = functions.getattr("add_numbers")?;
+            let function_result = add_function.call0()?;
             println!("\n-----end of py output-----\n");
             // the values we got from python can now be used in Rust
             println!("\nRust Output:\n\tThe sum is {}", function_result);
@@ -251,9 +297,10 @@
             // grab the desired function using .getattr("function_name")
             // then create some args and call the function using .call1(args)
             println!("\nDemo#5.2 Call function with PyTuple args\nEvaluating...\n-----start of py output-----\n");
-            let add_function = functions.getattr("add_numbers").unwrap();
-            let args = PyTuple::new(py, &[11,23]);
-            let function_result = add_function.call1(args).unwrap().extract()?; // instead of unwrap, try to handle the pyresult directly?
+            let add_function = functions.getattr("add_numbers")?;
+            let args = PyTuple::new(py, [11,23]);
+            // extract() failures are reported as Error::Conversion
+            let function_result = extract(add_function.call1(args)?)?;
             println!("\n-----end of py output-----\n");
             // the values we got from python can now be used in Rust
             println!("\nRust Output:\n\tThe sum is {}", function_result);
@@ -266,30 +313,49 @@
     
     // Example 6
     // 
-    pub fn python_function_err_handling()-> PyResult<i32> {
+    pub fn python_function_err_handling()-> Result<i32, Error> {
         
         // Initialize Python interpreter and acquire Global Interpreter Lock
         println!("\nInitializing py interpreter...");
         Python::with_gil(|py| {
             // first we need to grab the python code from a local file
             // Create a path to the desired file
-            let code = get_py_file_contents("py/functions.py")?; 
+            // the file is looked up with the default SourceSet: py/functions.py on disk if it can be found
+            // (from any working directory), otherwise the copy compiled into the binary
+            let source = SourceSet::default().load("functions.py")?;
+            println!("Using {}", source.info);
+            let file_name = source.file_name();
+            let code = source.code; 
+            // let linecache know about the code so tracebacks can show the failing line
+            traceback::register_source(py, &file_name, &code)?;
             println!("\nPython code to evaluate:\n-----start of py code-----\n\n{code}\n\n-----end of py code-----");
             
             // create PyModule from contents of file
             // this is used to access individual functions separately
-            let functions = PyModule::from_code(
+            // (registered as "_examples.functions_<n>", so it doesn't clash with py/functions.py imported elsewhere)
+            let functions = module_from_code(
                 py,
                 &code,
-                "functions.py",
+                &file_name,
                 "functions"
-            ).unwrap();
-    
+            )?;
+    
+            // the mistake below can be caught without running any Python: a PyFunction compares its Rust
+            // types with the type hints in functions.py (add_numbers(a: int = 1, b: int = 2) -> int)
+            println!("\nDemo#6.0 Check the Rust types against the Python signature\n");
+            let add_numbers = PyFunction::<(char, char), i32>::from_module(functions.into(), "add_numbers").with_arg_names(&["a", "b"]);
+            match add_numbers.validate(py) {
+                Ok(signature) => println!("Rust Output:\n\tadd_numbers matches its {} parameters", signature.parameters.len()),
+                Err(e) => println!("Rust Output:\n\t{e}"),
+            }
+
             // wrong type (float) args
             println!("\nDemo#6.1 Call function with wrong type args(f32)\nEvaluating...\n-----start of py output-----\n");
-            let add_function = functions.getattr("add_numbers").unwrap();
-            let args = PyTuple::new(py, &['a','b']);
-            let function_result = add_function.call1(args).unwrap().extract()?; // instead of unwrap, try to handle the pyresult directly?
+            // 'a' + 'b' is valid python, so the call succeeds but the result "ab" can't become an i32
+            // the caller receives Error::Conversion
+            let add_function = functions.getattr("add_numbers")?;
+            let args = PyTuple::new(py, ['a','b']);
+            let function_result = extract(add_function.call1(args)?)?;
             println!("\n-----end of py output-----\n");
             
     
@@ -301,487 +367,175 @@
         })
     }
     
+    // Rust functions for py/functions_venv.py, generated from its type hints into py_functions.rs
+    // py_bindings! writes the load-module / getattr / call1 / extract steps for each function, so
+    // examples 7a-7d just call functions_venv::random_number(10, 20) like any other Rust function
+    // the module is imported once through the shared ModuleLoader and reused (and hot reloaded) after that
+    pub use py_functions::functions_venv;
+
+    // the emoji functions of py/functions_venv.py again, returning Emoji instead of the generated Option<String>
+    // Emoji converts from a Python str (FromPyObject) and is annotated as one (PyAnnotation), so the
+    // signature check and the EMOJI_DATA lookup both happen inside the binding
+    mod venv_emoji {
+        use crate::Emoji;
+
+        crate::py_bindings! {
+            module "functions_venv";
+            pub fn color_emoji(color: &str) -> Option<Emoji>;
+            pub fn find_emoji(name: &str) -> Option<Emoji>;
+        }
+    }
+
     // Example 7a
     // Python functions that require packages installed on a virtual environment
-    pub fn python_function_venv_a()-> PyResult<i32> {
-        
-        // Initialize Python interpreter and acquire Global Interpreter Lock
+    pub fn python_function_venv_a()-> Result<i64, Error> {
+        // each binding acquires the GIL and initializes the interpreter on its first call
         println!("\nInitializing py interpreter...");
-        Python::with_gil(|py| {
-    
-            // first we need to grab the python code from a local file
-            let code = get_py_file_contents("py/functions_venv.py")?; 
-            println!("\nPython code to evaluate:\n-----start of py code-----\n\n{code}\n\n-----end of py code-----");
-            
-            // attempt create PyModule from contents of file
-            // this module can be used to access individual functions separately
-            let functions_pymodule: PyResult<&PyModule> = PyModule::from_code(
-                py,
-                &code,
-                "functions.py",
-                "functions"
-            );
-            
-            // The next action to take depends on whether result of creating PyModule was OK or Err
-            // if Ok, then load a function with .getattr(), create some args (if needed), and execute the function using .call0() or .call1()
-            // if Err, then display the reason and return the relevant PyErr to main()
-            // Err may occur for reasons such as:
-            //  - Python cant compile because of syntax error in code
-            //  - Python cant compile because imported libraries cant be found
-            // ...etc. 
-            match functions_pymodule {
-                Ok(functions) => {
-                    println!("\nResult: OK\nPython module was successfully created");
-    
-                    // Example 1: display emoji 
-                    println!("\nDemo#7a 1 - Emoji\n");
-                    
-                    println!("Accessing function 'emoji_test()'");
-                    let load_emoji_function = functions.getattr("emoji_test");
-                    
-                    // unpack Result of loading function and decide what to do next
-                    // if Ok, call the function (nothing is returned)
-                    // if Err, display the reason why (nothing is returned)
-                    match load_emoji_function {
-                        Ok(emoji_function) => {
-                            // loaded successfully -> call the function and print whether the result was Ok or Err
-                            println!("Successfully accessed function 'emoji_test()'");
-                            println!("Evaluating...\n-----start of py output-----\n");
-                            match emoji_function.call0() {
-                                Ok(_) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("emoji_test() function call succeeded");
-                                },
-                                Err(pyerr) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("emoji_test() function call failed because: {}", pyerr);
-                                },
-                            };
-                        },
-                        Err(pyerr) => {
-                            // failed to load function
-                            println!("Failed to access function 'emoji_test' because: {}", pyerr);     
-                        }
-                    };
-                  
-                    // Example 2: Random Number 
-                    println!("\n\nDemo#7a 2 - Random\n");
-    
-                    println!("Accessing function 'random_number()'");
-                    let load_rand_function = functions.getattr("random_number");
-                    
-                    
-                    // unpack Result of loading function and decide what to do next
-                    // if Ok, create some args and call the function with them. If the call succeeds, the value is returned
-                    // if Err, display the reason why and return the relevent PyErr 
-                    // Err may occur for reasons such as:
-                    //  - no function by that name (ex. incorrect spelling)
-                    match load_rand_function {
-                        Ok(rand_function) => {
-                            println!("Successfully accessed function 'random_number()'");
-                            
-                            // random number test 1
-                            let args = PyTuple::new(py, &[10, 20]);
-                            println!("\nTesting function using args {:?}", args);
-                            
-                            println!("Evaluating...\n-----start of py output-----\n");
-                            
-                            // call the function and decide what to do next based on Result
-                            // if Ok, convert the new PyAny value to i32 and return it
-                            // if Err, display the reason why and return the relevent PyErr 
-                            // Err may occur for reasons such as:
-                            //  - Supplied arguments are the wrong type
-                            //  - Supplied arguments have invalid value(s) (ex. lower_limt > higher_limit)
-                            // ...etc. 
-                            match rand_function.call1(args) {
-                                Ok(num) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("random_number() function call succeeded");
-                                    println!("A random number from Python: {}\n", num);
-                                    let num = num.extract()?;
-                                    return Ok(num);
-                                },
-                                Err(pyerr) if pyerr.is_instance_of::<PyTypeError>(py) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("random_number() function call failed because PyTypeError: {}", pyerr);
-                                    return Err(pyerr);
-                                },
-                                Err(pyerr) if pyerr.is_instance_of::<PyValueError>(py) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("random_number() function call failed because PyValueError: {}", pyerr);
-                                    return Err(pyerr);
-                                },
-                                Err(pyerr) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("random_number() function call failed because unspecified Python error: {}", pyerr);
-                                    return Err(pyerr);
-                                },
-                            };
-                        },
-                        Err(pyerr) => {
-                            println!("Failed to access function 'random_number()' because: {}", pyerr);   
-                            return Err(pyerr);   
-                        }
-                    }
-                },
-    
-                Err(pyerr) if pyerr.is_instance_of::<PySyntaxError>(py) => {
-                    println!("\nResult: ERR (Syntax Error) \nPython module could not be created"); 
-                    return Err(pyerr);
-                },
-                Err(pyerr) if pyerr.is_instance_of::<PyModuleNotFoundError>(py) => {
-                    println!("\nResult: ERR (ModuleNotFoundError) \nPython module could not be created"); 
-                    return Err(pyerr);
-                },
-                Err(pyerr) => {
-                    println!("\nResult: ERR (Unspecified Error)\nPython module could not be created"); 
-                    return Err(pyerr);
-                },
-            };     
-        })
+
+        // Example 1: display emoji
+        // emoji_test() returns nothing, so the binding returns Result<(), Error>
+        // a failure here is only displayed, the example goes on to the next function
+        // Capture::Tee still prints what Python prints, but flushes it before the call returns so it
+        // can't show up after the "end of py output" banner
+        println!("\nDemo#7a 1 - Emoji\n");
+        println!("Evaluating...\n-----start of py output-----\n");
+        let captured = capture(&Capture::Tee, functions_venv::emoji_test);
+        match captured.value {
+            Ok(()) => println!("\n-----end of py output-----\n\nemoji_test() function call succeeded"),
+            Err(e) => println!("\n-----end of py output-----\n\nemoji_test() function call failed because: {e}"),
+        };
+        println!("emoji_test() printed {} line(s)", captured.stdout.lines().count());
+
+        // Example 2: Random Number
+        // the args are plain Rust values and the python int comes back as i64 (int -> i64 in the generated bindings)
+        // `?` returns any Error to main(), ex. Error::ModuleNotFound if emoji isn't installed
+        println!("\n\nDemo#7a 2 - Random\n");
+        println!("Testing function using args (10, 20)");
+        // Capture::Silent keeps Python's output out of the terminal; it comes back with the result
+        println!("Evaluating...");
+        let captured = capture(&Capture::Silent, || functions_venv::random_number(10, 20));
+        println!("-----start of py output-----\n\n{}\n-----end of py output-----\n", captured.output());
+        let num = captured.value?;
+        println!("random_number() function call succeeded");
+        println!("A random number from Python: {num}\n");
+        Ok(num)
     }
     
     
     // Example 7b
     // Python functions that require packages installed on a virtual environment
-    pub fn python_function_venv_b()-> Result<i32, Error> {
-        
-        // Initialize Python interpreter and acquire Global Interpreter Lock
+    // same calls as 7a, but the Error returned by the binding is matched to explain what went wrong
+    pub fn python_function_venv_b()-> Result<i64, Error> {
         println!("\nInitializing py interpreter...");
-        Python::with_gil(|py| {
-    
-            // first we need to grab the python code from a local file
-            let code = get_py_file_contents("py/functions_venv.py")?; 
-            println!("\nPython code to evaluate:\n-----start of py code-----\n\n{code}\n\n-----end of py code-----");
-            
-            // attempt create PyModule from contents of file
-            // this module can be used to access individual functions separately
-            let functions_pymodule: Result<&PyModule, PyErr> = PyModule::from_code(
-                py,
-                &code,
-                "functions.py",
-                "functions"
-            );
-            
-            // The next action to take depends on whether result of creating PyModule was OK or Err
-            // if Ok, then load a function with .getattr(), create some args (if needed), and execute the function using .call0() or .call1()
-            // if Err, then display the reason and return the relevant PyErr to main()
-            // Err may occur for reasons such as:
-            //  - Python cant compile because of syntax error in code
-            //  - Python cant compile because imported libraries cant be found
-            // ...etc. 
-            match functions_pymodule {
-                Ok(functions) => {
-                    println!("\nResult: OK\nPython module was successfully created");
-    
-                    // Example 1: display emoji 
-                    println!("\nDemo#7b 1 - Emoji\n");
-                    
-                    println!("Accessing function 'emoji_test()'");
-                    let load_emoji_function = functions.getattr("emoji_test");
-                    
-                    // unpack Result of loading function and decide what to do next
-                    // if Ok, call the function (nothing is returned)
-                    // if Err, display the reason why (nothing is returned)
-                    match load_emoji_function {
-                        Ok(emoji_function) => {
-                            // loaded successfully -> call the function and print whether the result was Ok or Err
-                            println!("Successfully accessed function 'emoji_test()'");
-                            println!("Evaluating...\n-----start of py output-----\n");
-                            match emoji_function.call0() {
-                                Ok(_) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("emoji_test() function call succeeded");
-                                },
-                                Err(pyerr) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("emoji_test() function call failed because: {}", pyerr);
-                                },
-                            };
-                        },
-                        Err(pyerr) => {
-                            // failed to load function
-                            println!("Failed to access function 'emoji_test' because: {}", pyerr);     
-                        }
-                    };
-                  
-                    // Example 2: Random Number 
-                    println!("\n\nDemo#7b 2 - Random\n");
-    
-                    println!("Accessing function 'random_number()'");
-                    let load_rand_function = functions.getattr("random_number");
-                    
-                    
-                    // unpack Result of loading function and decide what to do next
-                    // if Ok, create some args and call the function with them. If the call succeeds, the value is returned
-                    // if Err, display the reason why and return the relevent PyErr 
-                    // Err may occur for reasons such as:
-                    //  - no function by that name (ex. incorrect spelling)
-                    match load_rand_function {
-                        Ok(rand_function) => {
-                            println!("Successfully accessed function 'random_number()'");
-                            
-                            // random number test 1
-                            let args = PyTuple::new(py, &[10, 20]);
-                            println!("\nTesting function using args {:?}", args);
-                            
-                            println!("Evaluating...\n-----start of py output-----\n");
-                            
-                            // call the function and decide what to do next based on Result
-                            // if Ok, convert the new PyAny value to i32 and return it
-                            // if Err, display the reason why and return the relevent PyErr 
-                            // Err may occur for reasons such as:
-                            //  - Supplied arguments are the wrong type
-                            //  - Supplied arguments have invalid value(s) (ex. lower_limt > higher_limit)
-                            // ...etc. 
-                            match rand_function.call1(args) {
-                                Ok(num) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("random_number() function call succeeded");
-                                    println!("A random number from Python: {}\n", num);
-                                    let num:i32 = num.extract()?;
-                                    return Ok(num);
-                                },
-                                Err(pyerr) if pyerr.is_instance_of::<PyTypeError>(py) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("random_number() function call failed because PyTypeError: {}", pyerr);
-                                    return Err(Error::new(ErrorKind::InvalidInput, pyerr));
-                                },
-                                Err(pyerr) if pyerr.is_instance_of::<PyValueError>(py) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("random_number() function call failed because PyValueError: {}", pyerr);
-                                    return Err(Error::new(ErrorKind::InvalidInput, pyerr));
-                                },
-                                Err(pyerr) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("random_number() function call failed because unspecified Python error: {}", pyerr);
-                                    return Err(Error::new(ErrorKind::Other, pyerr));
-                                },
-                            };
-                        },
-                        Err(pyerr) => {
-                            println!("Failed to access function 'random_number()' because: {}", pyerr); 
-                            return Err(Error::new(ErrorKind::Other, pyerr));
-        
-                        }
-                    }
-                },
-                Err(pyerr) if pyerr.is_instance_of::<PySyntaxError>(py) => {
-                    println!("\nResult: ERR (InvalidInput) \nPython module could not be created due to syntax error"); 
-                    return Err(Error::new(ErrorKind::InvalidInput, pyerr));
-                },
-                Err(pyerr) if pyerr.is_instance_of::<PyModuleNotFoundError>(py) => {
-                    println!("\nResult: ERR (NotFound) \nPython module could not be created because something wasnt found"); 
-                    return Err(Error::new(ErrorKind::NotFound, pyerr));
-                },
-                Err(pyerr) => {
-                    println!("\nResult: ERR (Unspecified Error)\nPython module could not be created"); 
-                    return Err(Error::new(ErrorKind::Other, pyerr));
-                },
-            };     
-        })
+
+        // Example 1: display emoji
+        println!("\nDemo#7b 1 - Emoji\n");
+        println!("Evaluating...\n-----start of py output-----\n");
+        let emoji_result = functions_venv::emoji_test();
+        println!("\n-----end of py output-----\n");
+        if let Err(e) = emoji_result {
+            println!("emoji_test() function call failed because: {e}");
+        } else {
+            println!("emoji_test() function call succeeded");
+        }
+
+        // Example 2: Random Number
+        // every binding sorts failures into the same Error variants, so this match works for any of them
+        println!("\n\nDemo#7b 2 - Random\n");
+        println!("Testing function using args (10, 20)");
+        println!("Evaluating...\n-----start of py output-----\n");
+        let random_result = functions_venv::random_number(10, 20);
+        println!("\n-----end of py output-----\n");
+        match &random_result {
+            Ok(num) => println!("random_number() function call succeeded\nA random number from Python: {num}\n"),
+            Err(Error::Syntax(_)) => println!("Python module could not be created due to syntax error"),
+            Err(Error::ModuleNotFound(_)) => println!("Python module could not be created because an import wasnt found"),
+            Err(Error::MissingFunction { .. }) => println!("random_number() doesn't exist (ex. incorrect spelling)"),
+            Err(Error::Type(e)) => println!("random_number() function call failed because of wrong argument types: {e}"),
+            Err(Error::Value(e)) => println!("random_number() function call failed because of invalid argument values: {e}"),
+            Err(e) => println!("random_number() function call failed because unspecified Python error: {e}"),
+        };
+        random_result
     }
     
     
     // Example 7c
     // Python functions that require packages installed on a virtual environment
-    // fn python_function_venv_c()-> Result<Option<String>, Error> {
-    pub fn python_function_venv_c(color_name:&str)-> Result<Option<char>, Error> {
-        // Initialize Python interpreter and acquire Global Interpreter Lock
+    pub fn python_function_venv_c(color_name:&str)-> Result<Option<Emoji>, Error> {
         println!("\nInitializing py interpreter...");
-        Python::with_gil(|py| {
-    
-            // first we need to grab the python code from a local file
-            let code = get_py_file_contents("py/functions_venv.py")?; 
-            println!("\nPython code to evaluate:\n-----start of py code-----\n\n{code}\n\n-----end of py code-----");
-            
-            // attempt create PyModule from contents of file
-            // this module can be used to access individual functions separately
-            let functions_pymodule: Result<&PyModule, PyErr> = PyModule::from_code(
-                py,
-                &code,
-                "functions.py",
-                "functions"
-            );
-
-            let args = PyTuple::new(py, &[color_name]);
-            let out:Option<char> = match functions_pymodule?.getattr("color_emoji")?.call1(args) {
-                Ok(n) => {
-                    // python function completd successfully 
-                    // need to check if Some or None
-                    let cha:char = n.extract()?;
-                    if cha=='0' {
-                        return Ok(None);
-                    } else {
-                        return Ok(Some(cha));
-                    }
-                },
-                Err(pyerr) if pyerr.is_instance_of::<PySyntaxError>(py) => {
-                    println!("\nResult: ERR (InvalidInput) \nPython module could not be created due to syntax error"); 
-                    return Err(Error::new(ErrorKind::InvalidInput, pyerr));
-                },
-                Err(e) => {return Err(Error::new(ErrorKind::Other, e));}
-            };
 
-     
-            /* 
-            // The next action to take depends on whether result of creating PyModule was OK or Err
-            // if Ok, then load a function with .getattr(), create some args (if needed), and execute the function using .call0() or .call1()
-            // if Err, then display the reason and return the relevant PyErr to main()
-            // Err may occur for reasons such as:
-            //  - Python cant compile because of syntax error in code
-            //  - Python cant compile because imported libraries cant be found
-            // ...etc. 
-            match functions_pymodule {
-                Ok(functions) => {
-                    println!("\nResult: OK\nPython module was successfully created");
-    
-                    // Example 1: display emoji 
-                    println!("\nDemo#7c 1 - Emoji\n");
-                    
-                    println!("Accessing function 'color_emoji()'");
-                    let load_emoji_function = functions.getattr("color_emoji");
-                    
-                    // unpack Result of loading function and decide what to do next
-                    // if Ok, call the function and return the value as an Option
-                    // if Err, display the reason why and return the Error 
-                    match load_emoji_function {
-                        Ok(emoji_function) => {
-                            // loaded successfully -> call the function and print whether the result was Ok or Err
-                            println!("Successfully accessed function 'color_emoji()'");
-                            
-                            // create args to supply to function
-                            let args = PyTuple::new(py, &[color_name]);
-                            println!("\nTesting function using args {:?}", args);
-                            
-                            // call the function and choose next actions based on Result Ok or Err
-                            println!("Evaluating...\n-----start of py output-----\n");
-                            match emoji_function.call1(args) {
-                                Ok(output) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("color_emoji() function call succeeded");
-    
-                                    // python result is returned as an option which pyo3 doesn't recognize
-                                    // need to extract the value manually
-                                    println!("function returned: {:?}\n", output);
-                                    let out = extract_py_emoji_optional_output(output);
-                                
-                                    return Ok(out);
-                                },
-                                Err(pyerr) => {
-                                    println!("\n-----end of py output-----\n");
-                                    println!("color_emoji() function call failed because: {}", pyerr);
-                                    return Err(Error::new(ErrorKind::Other, pyerr));
-                                },
-                            };
-                        },
-                        Err(pyerr) => {
-                            // failed to load function
-                            println!("Failed to access function 'color_emoji' because: {}", pyerr); 
-                            return Err(Error::new(ErrorKind::Other, pyerr));    
-                        }
-                    };
-                  
-                },
-                Err(pyerr) if pyerr.is_instance_of::<PySyntaxError>(py) => {
-                    println!("\nResult: ERR (InvalidInput) \nPython module could not be created due to syntax error"); 
-                    return Err(Error::new(ErrorKind::InvalidInput, pyerr));
-                },
-                Err(pyerr) if pyerr.is_instance_of::<PyModuleNotFoundError>(py) => {
-                    println!("\nResult: ERR (NotFound) \nPython module could not be created because something wasnt found"); 
-                    return Err(Error::new(ErrorKind::NotFound, pyerr));
-                },
-                Err(pyerr) => {
-                    println!("\nResult: ERR (Unspecified Error)\nPython module could not be created"); 
-                    return Err(Error::new(ErrorKind::Other, pyerr));
-                },
-            }; */    
-        })
+        // color_emoji() raises ValueError for names that aren't only letters -> Error::Value
+        // a renamed function is Error::MissingFunction, a return value that isn't a known emoji or None is Error::ReturnType
+        // color_emoji() is annotated Optional[str]: Python's None is already None here, and a str becomes an Emoji
+        // with its CLDR name, aliases and version from emoji.EMOJI_DATA
+        venv_emoji::color_emoji(color_name)
     }
     
 
-    
-    
     // Example 7d
     // Python functions that require packages installed on a virtual environment
-    // fn python_function_venv_c()-> Result<Option<int>, Error> {
-        pub fn python_function_venv_d(upper_limit:i32, lower_limit:i32)-> Result<Option<i32>, Error> {
-            // Initialize Python interpreter and acquire Global Interpreter Lock
-            println!("\nInitializing py interpreter...");
-            Python::with_gil(|py| {
-        
-                // first we need to grab the python code from a local file
-                let code = get_py_file_contents("py/functions_venv.py")?; 
-                println!("\nPython code to evaluate:\n-----start of py code-----\n\n{code}\n\n-----end of py code-----");
-                
-                // attempt create PyModule from contents of file
-                // this module can be used to access individual functions separately
-                let functions_pymodule: Result<&PyModule, PyErr> = PyModule::from_code(
-                    py,
-                    &code,
-                    "functions.py",
-                    "functions"
-                );
+    pub fn python_function_venv_d(upper_limit:i64, lower_limit:i64)-> Result<Option<i64>, Error> {
+        println!("\nInitializing py interpreter...");
 
-                let args = PyTuple::new(py, &[lower_limit, upper_limit]);
-                println!("\nEvaluating python code using args {args:?}...\n-----start of py output-----\n");
-                let out:Option<i32> = match functions_pymodule?.getattr("random_number")?.call1(args) {
-                    Ok(n) => {
-                        // python function completed successfully 
-                        println!("\n-----end of py output-----\n");
-                        println!("random_number() function call succeeded");
-                        // need to decide if value correponds to Some or None
-                        let num:i32 = n.extract()?; //change python int to rust i32
-                        if num & 1 == 1 {
-                            println!("The random number {num} is ODD - return Some");
-                            Some(num)
-                        } else {
-                            println!("The random number {num} is EVEN - return None");
-                            None
-                        }
-    
-                    },
-                    Err(pyerr) if pyerr.is_instance_of::<PySyntaxError>(py) => {
-                        println!("\nResult: ERR (InvalidInput) \nPython module could not be created due to syntax error"); 
-                        return Err(Error::new(ErrorKind::InvalidInput, pyerr));
-                    },
-                    Err(e) => {return Err(Error::new(ErrorKind::Other, e));}
-                };
-    
-                // do some math on out, you could use match out {} or out.map()
-                // return the result after this math instead of in the previous match
-                println!("\nProcessing Option(num) from Python in Rust...");
-                println!("Double number if it is ODD, do nothing if number is EVEN...");
-                let out = out.map(|n| n*2);
-                println!("\nProcessed result: {:?}", &out);
+        println!("\nEvaluating python code using args {:?}...\n-----start of py output-----\n", (lower_limit, upper_limit));
+        let num = functions_venv::random_number(lower_limit, upper_limit)?;
+        println!("\n-----end of py output-----\n");
+        println!("random_number() function call succeeded");
+        // need to decide if value correponds to Some or None
+        let out:Option<i64> = if num & 1 == 1 {
+            println!("The random number {num} is ODD - return Some");
+            Some(num)
+        } else {
+            println!("The random number {num} is EVEN - return None");
+            None
+        };
 
-                Ok(out)
-             
-            })
-        }
+        // do some math on out, you could use match out {} or out.map()
+        println!("\nProcessing Option(num) from Python in Rust...");
+        println!("Double number if it is ODD, do nothing if number is EVEN...");
+        let out = out.map(|n| n*2);
+        println!("\nProcessed result: {:?}", &out);
+
+        Ok(out)
+    }
+
+    // Example 7e
+    // Like 7c, but any emoji by CLDR short name or alias (ex. "thumbsup", "jp" or "family_man_woman_girl")
+    // the result can be several code points (skin tones, flags, keycaps, ZWJ sequences), which an Emoji holds
+    pub fn python_function_venv_e(name: &str) -> Result<Option<Emoji>, Error> {
+        venv_emoji::find_emoji(name)
+    }
         
     
         
     // Misc Helper functions
     
-    pub fn display_package_info() {
-        println!("\nRunning PIP to see packages...");
-    
-        println!("\nLocal Packages:");
-        let output = Command::new("bash")
-            .arg("-c")
-            .arg("pip3 freeze --local")
-            .output()
-            .expect("bash command failed");
-        println!("pip3 freeze --local:\tStatus: {:?}",output.status);
-        match output.stdout.len() {
-            0 =>println!("No virtual environment detected\nNo packages to display"),
-            _=> {
-                io::stdout().write_all(&output.stdout).unwrap();
-                io::stderr().write_all(&output.stderr).unwrap();
+    // Use the project virtualenv inside the embedded interpreter ($VIRTUAL_ENV or env/, see venv::VenvLocator)
+    // call this first thing in main() so emoji etc. can be imported without `source env/bin/activate`
+    pub fn activate_venv() -> Result<&'static ActiveEnvironment, Error> {
+        Python::with_gil(|py| venv::activate(py, &VenvLocator::default()))
+    }
+
+    // Compare requirements.txt with the packages the embedded interpreter can import
+    // with RequirementsPolicy::Enforce a missing package or wrong version is an error (Error::RequirementsNotMet)
+    // instead of an AttributeError somewhere inside color_emoji()
+    pub fn verify_requirements(policy: RequirementsPolicy) -> Result<RequirementsReport, Error> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(bootstrap::REQUIREMENTS_FILE);
+        Python::with_gil(|py| requirements::check_requirements(py, path, policy))
+    }
+
+    // Print the packages the embedded interpreter can import, read with importlib.metadata
+    // (not the pip on $PATH, which may belong to a different environment)
+    pub fn display_package_info(format: OutputFormat) -> Result<(), Error> {
+        let packages = Python::with_gil(packages::installed_packages)?;
+        match format {
+            OutputFormat::Text => {
+                println!("\nInstalled Packages:");
+                println!("{}", packages::render_text(&packages));
             }
+            OutputFormat::Json => println!("{}", packages::render_json(&packages)?),
         }
-    
+        Ok(())
     }
     
     
@@ -789,51 +543,47 @@
     //  - path is valid and exists
     //  - path leads to a file with .py extension
     //  - the file can be read and its contents are not empty
-    fn validate_py_path(path:&Path) -> Result<(), String> {
+    // failures are returned as Error::InvalidFile along with the offending path
+    fn validate_py_path(path:&Path) -> Result<(), Error> {
         // confirm path exists and is a file rather than a directory
         if !path.exists() { 
-            return Err("Path does not exist".to_owned());    }
+            return Err(Error::invalid_file(path, "Path does not exist"));    }
         if !path.is_file() { 
-            return Err("Path does not lead to a file (maybe a directory?)".to_owned());   }
+            return Err(Error::invalid_file(path, "Path does not lead to a file (maybe a directory?)"));   }
     
         // file extension must be a .py 
         let expected_extension = OsStr::new("py");
         match path.extension() {
             Some(ext) => {
-                println!("file ext: {:?}", ext);
+                log::debug!("file ext: {:?}", ext);
                 if ext == expected_extension {
                     Ok(())
                 } else {
-                    Err("Invalid file extension".to_owned())
+                    Err(Error::invalid_file(path, "Invalid file extension"))
                 }
             },
             None => {
-                println!("c");
-                Err("path.extension() failed--maybe path does not have a period delimiting the extension?".to_owned())
+                Err(Error::invalid_file(path, "path.extension() failed--maybe path does not have a period delimiting the extension?"))
             },
     
         }
     }
     
-    fn get_py_file_contents(file_name:&str) -> Result<String, Error> {
+    // the steps are logged at debug level (RUST_LOG=debug) so they don't clutter the output of every load
+    fn get_py_file_contents(path:&Path) -> Result<String, Error> {
     
-        // Create a path to the desired file
-        println!("Opening file: {}", &file_name);
-        let path = Path::new(file_name);
+        log::debug!("Opening file: {}", path.display());
     
         // validate path
-        let path_validation =  validate_py_path(&path);
-        if path_validation.is_err() {
-            return Err(Error::new(ErrorKind::Other, path_validation.err().unwrap_or("Could not validate path".to_owned())));
-        }
+        validate_py_path(path)?;
     
         // try to open the file
-        let file = File::open(&path);
+        let file = File::open(path);
         
         // match on file to examine result of open operation
-        let result:Result<String, Error> = match file {
+        match file {
             Ok(mut file) => {
-                println!("File was opened successfully");
+                log::debug!("File was opened successfully");
     
                 // create a new String and read the file contents into it
                 let mut s = String::new();
@@ -841,78 +591,49 @@
                 
                 // make sure file is not empty
                 if s.is_empty() {
-                    return Err(Error::new(ErrorKind::Other, String::from("Py file was empty...")));
+                    return Err(Error::invalid_file(path, "Py file was empty..."));
                 }
                 // finished inner actions for successful file read--return file content string inside Result
-                Ok(s.to_owned())
+                Ok(s)
             },
             Err(e) => { 
-                println!("Failed to open file");
+                log::debug!("Failed to open file");
                 // can use match to create different behavior depending on what kind of error occcured
                 match e.kind() {
-                    ErrorKind::NotFound => println!("File not Found"),
-                    ErrorKind::PermissionDenied => println!("Permission Denied"),
-                    _ => println!("Unknown Error Occured: {}", e),
+                    ErrorKind::NotFound => log::debug!("File not Found"),
+                    ErrorKind::PermissionDenied => log::debug!("Permission Denied"),
+                    _ => log::debug!("Unknown Error Occured: {}", e),
                 } 
                 // finished inner actions for unsuccessful file read--return error inside Result
-                Err(e)
+                Err(Error::Io(e))
             }
-        };
-        return result;
-    
-    }
-    
-    // convert python type "Optional" into Rust type "Option"
-    fn extract_py_emoji_optional_output(py_option:&PyAny) -> Option<char> {
-        println!("Converting Py Option to Rust...");
-        
-        // TO-DO: replace unwrap()s used to unpack getattr() and call0()
-        match py_option.getattr("is_present").unwrap().call0().unwrap().to_string().as_str() {
-            "True" => {
-                // is_present == True
-                println!("Sucess -> 'SOME'");
-                let value = py_option.getattr("get").unwrap().call0().unwrap().extract().unwrap();
-               
-                return Some(value);
-            },
-            "False" => {
-                // is_present == False
-    
-                println!("Sucess -> 'NONE'");
-                return None;
-            },
-            _ => {
-                println!("Failure -> Something went wrong");
-                return None;
-            },
         }
+    
     }
     
-    pub fn get_user_input() -> String {
+    pub fn get_user_input() -> Result<String, Error> {
         let mut input = String::new();
         io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
-        input.trim().to_owned()
+            .read_line(&mut input)?;
+        Ok(input.trim().to_owned())
     }
 
-    pub fn get_user_confirmation() -> bool {
+    pub fn get_user_confirmation() -> Result<bool, Error> {
         loop {
             println!("Yes or No?");
             
             let mut input = String::new();
             io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read line");
+                .read_line(&mut input)?;
             
             match input.trim().to_ascii_uppercase().as_str() {
                 "YES" | "Y" | "TRUE" | "t" => {
                     println!("Continue");
-                    break true;
+                    break Ok(true);
                 },
                 "NO" | "N" | "FALSE" | "F" => {
                     println!("Do not Continue");
-                    break false;
+                    break Ok(false);
                 },
                 _ => {
                     println!("Error, invalid response. Please try again");
@@ -0,0 +1,881 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, TryLockError};
+use std::time::SystemTime;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyModule};
+
+use crate::error::Error;
+use crate::host;
+use crate::logging;
+use crate::search_path::SearchPath;
+use crate::sources::{SourceInfo, SourceLocation, SourcePreference, SourceSet};
+use crate::traceback;
+
+// Keeps compiled Python modules around so a loop calling the same function over and over
+// only reads and compiles the .py file once
+// file names are resolved with a SourceSet (SourceSet::default() unless set_sources() is used), so a
+// module comes either from disk or from the copy embedded in the binary
+// each disk entry remembers the file's modification time; if the file changes it is recompiled
+// on the next load()
+// with hot reload turned on, a file that no longer compiles doesn't break the caller: the last
+// version that compiled keeps being used and the failure is reported as a ReloadEvent
+//
+// load() compiles a single file with module_from_code, so every file gets a name of its own in sys.modules;
+// import() goes through Python's import system instead, so modules can import each other and packages
+// with __init__.py work
+// unload() drops a module from the cache and from sys.modules, so the next load() or import() starts from a
+// fresh copy without the state left behind by earlier calls
+// imported modules keep their real names ("functions_venv", "pkg.sub"), since that is what other modules
+// import them as; so they are shared with every other ModuleLoader and with any Python code importing them,
+// and unloading one in one loader takes it out of sys.modules for all of them
+// one loader won't hold a file both ways: import("functions") after load("functions.py"), or the other way
+// around, is Error::ModuleNameClash (the two copies would have separate state)
+#[derive(Default)]
+pub struct ModuleLoader {
+    modules: HashMap<PathBuf, CachedModule>,
+    imports: HashMap<String, ImportedModule>,
+    stats: CacheStats,
+    sources: SourceSet,
+    hot_reload: bool,
+    events: Vec<ReloadEvent>,
+    // the embedded import hook, once sys.path and sys.meta_path have been set up
+    embedded_finder: Option<PyObject>,
+    import_paths_installed: bool,
+}
+
+struct CachedModule {
+    // where the code was read from, used for recompiling
+    location: SourceLocation,
+    info: SourceInfo,
+    module: Py<PyModule>,
+    // None for embedded sources, which never change
+    modified: Option<SystemTime>,
+}
+
+struct ImportedModule {
+    module: Py<PyModule>,
+    info: SourceInfo,
+    // every file on disk behind the module and its imported submodules, with the mtime seen at import
+    files: Vec<ModuleFile>,
+}
+
+struct ModuleFile {
+    module_name: String,
+    path: PathBuf,
+    modified: SystemTime,
+}
+
+// A module held by a ModuleLoader, as listed by loaded_modules()
+#[derive(Debug, Clone)]
+pub struct LoadedModule {
+    // the key in sys.modules, ex. "_examples.functions_1" for load() or "functions_venv" for import()
+    pub module_name: String,
+    pub source: SourceInfo,
+    // submodules in sys.modules, ex. "pkg.sub" for "pkg"
+    pub submodules: Vec<String>,
+}
+
+// package name in front of every module created by module_from_code(), and of the crate's own Python
+// helpers ("_examples._capture", ...)
+pub const MODULE_NAMESPACE: &str = "_examples";
+
+// the number in "_examples.functions_<n>", so a recompiled file doesn't reuse the name of the old version
+static NEXT_MODULE_ID: AtomicU64 = AtomicU64::new(1);
+
+// bumped every time a module is reloaded or unloaded, by any ModuleLoader
+// anything checked against the old version (ex. PyFunction::validate_once) is checked again when it changes
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// the Python side of the embedded import hook
+const EMBEDDED_IMPORTER: &str = include_str!("embedded_importer.py");
+
+// what happened when a changed file was recompiled
+#[derive(Debug)]
+pub enum ReloadEvent {
+    Reloaded { path: PathBuf },
+    // the new version failed to compile; the previous module is still in use
+    Failed { path: PathBuf, error: Error },
+}
+
+// counters for diagnostics
+//  - hits: load() returned an already compiled module
+//  - misses: the file had never been loaded and was compiled
+//  - reloads: the file changed on disk and was compiled again
+//  - failed_reloads: the file changed but didn't compile, so the last good version was kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub reloads: u64,
+    pub failed_reloads: u64,
+}
+
+static GLOBAL_LOADER: OnceLock<Mutex<ModuleLoader>> = OnceLock::new();
+
+thread_local! {
+    // this thread is inside ModuleLoader::with_global()
+    static HOLDING_GLOBAL_LOADER: Cell<bool> = const { Cell::new(false) };
+}
+
+// sets HOLDING_GLOBAL_LOADER until it is dropped, also when `f` panics
+struct HoldingGlobalLoader;
+
+impl ModuleLoader {
+    pub fn new() -> ModuleLoader {
+        ModuleLoader::default()
+    }
+
+    // Run `f` with the loader shared by the example functions
+    // compiling a module runs Python code, which may hand the GIL to another thread while the lock
+    // is held; waiting threads therefore release the GIL while they wait for the lock
+    // Error::LoaderReentered when `f` ends up calling with_global() again on the same thread, ex. a module
+    // whose top-level code calls an example function: that call would wait for the lock forever
+    pub fn with_global<T>(py: Python<'_>, f: impl FnOnce(&mut ModuleLoader) -> Result<T, Error>) -> Result<T, Error> {
+        if HOLDING_GLOBAL_LOADER.with(Cell::get) {
+            return Err(Error::LoaderReentered);
+        }
+        let loader = GLOBAL_LOADER.get_or_init(|| Mutex::new(ModuleLoader::new()));
+        let mut guard = loop {
+            match loader.try_lock() {
+                Ok(guard) => break guard,
+                Err(TryLockError::Poisoned(poisoned)) => break poisoned.into_inner(),
+                // a MutexGuard can't leave allow_threads(), so block there until the lock is free, then take
+                // it with the GIL held; this only goes around again if another thread got the lock first
+                Err(TryLockError::WouldBlock) => py.allow_threads(|| drop(loader.lock())),
+            }
+        };
+        let _holding = HoldingGlobalLoader::set();
+        f(&mut guard)
+    }
+
+    // cache statistics of the shared loader
+    pub fn global_stats() -> Result<CacheStats, Error> {
+        Python::with_gil(|py| ModuleLoader::with_global(py, |loader| Ok(loader.stats())))
+    }
+
+    // Return the compiled module for the file `name`, compiling it only if it isn't cached yet
+    // or the file was modified since it was last compiled
+    pub fn load(&mut self, py: Python<'_>, name: impl AsRef<Path>) -> Result<Py<PyModule>, Error> {
+        let location = self.sources.locate(name)?;
+        let key = cache_key(&location);
+        let modified = modified_time(&location)?;
+
+        match self.modules.get(&key) {
+            Some(cached) if cached.modified == modified => {
+                self.stats.hits += 1;
+                Ok(cached.module.clone_ref(py))
+            }
+            Some(_) => self.recompile(py, &key, modified, self.hot_reload),
+            None => {
+                let name = module_stem(&location);
+                if self.imports.keys().any(|imported| top_level(imported) == name) {
+                    return Err(Error::ModuleNameClash { name });
+                }
+                let (module, info) = compile(py, &location)?;
+                self.stats.misses += 1;
+                let cached = CachedModule { location, info, module: module.clone_ref(py), modified };
+                self.modules.insert(key, cached);
+                Ok(module)
+            }
+        }
+    }
+
+    // replacing the sources after import() was used adds the new directories to sys.path,
+    // but the old ones stay there
+    pub fn set_sources(&mut self, sources: SourceSet) {
+        self.sources = sources;
+        self.import_paths_installed = false;
+    }
+
+    pub fn sources(&self) -> &SourceSet {
+        &self.sources
+    }
+
+    // shortcut for replacing only the search path of the SourceSet
+    pub fn set_search_path(&mut self, search_path: SearchPath) {
+        self.sources.set_search_path(search_path);
+        self.import_paths_installed = false;
+    }
+
+    // which copy (embedded or disk, with path and hash) each cached module was compiled from
+    pub fn loaded_sources(&self) -> Vec<&SourceInfo> {
+        let loaded = self.modules.values().map(|cached| &cached.info);
+        loaded.chain(self.imports.values().map(|imported| &imported.info)).collect()
+    }
+
+    // Import a module by dotted name ("functions_venv", "pkg.sub", ...) and keep it cached
+    // the first call puts the source directories on sys.path and installs an import hook for the
+    // embedded sources, so the modules can import each other (including relative imports in packages)
+    // if a file behind the module or one of its submodules changed, the changed modules are reloaded
+    pub fn import(&mut self, py: Python<'_>, name: &str) -> Result<Py<PyModule>, Error> {
+        self.install_import_paths(py)?;
+
+        if let Some(imported) = self.imports.get(name) {
+            let changed = changed_files(&imported.files);
+            if changed.is_empty() {
+                self.stats.hits += 1;
+                return Ok(imported.module.clone_ref(py));
+            }
+            return self.reimport(py, name, changed, self.hot_reload);
+        }
+        let clashes = |cached: &CachedModule| module_stem(&cached.location) == top_level(name);
+        if self.modules.values().any(clashes) {
+            return Err(Error::ModuleNameClash { name: top_level(name).to_owned() });
+        }
+
+        let module = py.import(name)?;
+        let info = self.imported_source_info(module)?;
+        log::debug!("Imported {name} from {info}");
+        self.stats.misses += 1;
+        let imported = ImportedModule { module: module.into(), info, files: module_files(py, name)? };
+        let module = imported.module.clone_ref(py);
+        self.imports.insert(name.to_owned(), imported);
+        Ok(module)
+    }
+
+    // Turn hot reload on or off (off by default)
+    // when on, load() falls back to the last good module if a changed file fails to compile
+    pub fn set_hot_reload(&mut self, enabled: bool) {
+        self.hot_reload = enabled;
+    }
+
+    pub fn hot_reload(&self) -> bool {
+        self.hot_reload
+    }
+
+    // Check every cached file for changes and recompile the ones that were edited
+    // meant to be called once per pass of an interactive loop, so edits to the .py files are picked up
+    // before the next call; broken edits keep the last good version (even with hot reload off)
+    // returns everything that happened since the last call, including events recorded by load()
+    pub fn reload_changed(&mut self, py: Python<'_>) -> Vec<ReloadEvent> {
+        let changed: Vec<(PathBuf, Option<SystemTime>)> = self
+            .modules
+            .iter()
+            .filter_map(|(key, cached)| {
+                let modified = modified_time(&cached.location).ok()?;
+                (modified != cached.modified).then(|| (key.clone(), modified))
+            })
+            .collect();
+        for (key, modified) in changed {
+            // keep_last_good is set, so this can't fail; failures end up in self.events
+            let _ = self.recompile(py, &key, modified, true);
+        }
+        let changed_imports: Vec<(String, Vec<String>)> = self
+            .imports
+            .iter()
+            .map(|(name, imported)| (name.clone(), changed_files(&imported.files)))
+            .filter(|(_, changed)| !changed.is_empty())
+            .collect();
+        for (name, changed) in changed_imports {
+            let _ = self.reimport(py, &name, changed, true);
+        }
+        self.take_reload_events()
+    }
+
+    // reload events recorded since the last call
+    pub fn take_reload_events(&mut self) -> Vec<ReloadEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    // forget a single module; the next load() compiles it again
+    pub fn invalidate(&mut self, name: impl AsRef<Path>) -> bool {
+        match self.sources.locate(name) {
+            Ok(location) => self.modules.remove(&cache_key(&location)).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    // forget every module but leave them in sys.modules, see unload_all()
+    pub fn clear(&mut self) {
+        bump_generation();
+        self.modules.clear();
+        self.imports.clear();
+    }
+
+    // Forget a module and remove it and its submodules from sys.modules
+    // `name` is what it was loaded with: a dotted name for import() ("functions_venv", "pkg") or a file
+    // name for load() ("functions.py"); returns the names removed from sys.modules, empty if it wasn't loaded
+    // code still holding the module object (ex. a PyFunction made with from_module) keeps working with it
+    pub fn unload(&mut self, py: Python<'_>, name: &str) -> Result<Vec<String>, Error> {
+        let module = match self.imports.remove(name) {
+            Some(imported) => imported.module,
+            None => match self.sources.locate(name).ok().and_then(|location| self.modules.remove(&cache_key(&location))) {
+                Some(cached) => cached.module,
+                None => return Ok(Vec::new()),
+            },
+        };
+        bump_generation();
+        remove_from_sys_modules(py, &module_name(module.as_ref(py)))
+    }
+
+    // unload() every module
+    pub fn unload_all(&mut self, py: Python<'_>) -> Result<Vec<String>, Error> {
+        let modules = self.modules.drain().map(|(_, cached)| cached.module);
+        let modules: Vec<Py<PyModule>> = modules.chain(self.imports.drain().map(|(_, imported)| imported.module)).collect();
+        bump_generation();
+        let mut removed = Vec::new();
+        for module in modules {
+            removed.extend(remove_from_sys_modules(py, &module_name(module.as_ref(py)))?);
+        }
+        Ok(removed)
+    }
+
+    // every module the loader holds, sorted by name
+    pub fn loaded_modules(&self, py: Python<'_>) -> Result<Vec<LoadedModule>, Error> {
+        let loaded = self.modules.values().map(|cached| (&cached.module, &cached.info));
+        let loaded = loaded.chain(self.imports.values().map(|imported| (&imported.module, &imported.info)));
+        let mut modules = Vec::new();
+        for (module, info) in loaded {
+            let module_name = module_name(module.as_ref(py));
+            let submodules = sys_module_names(py, &module_name)?.into_iter().filter(|name| *name != module_name).collect();
+            modules.push(LoadedModule { module_name, source: info.clone(), submodules });
+        }
+        modules.sort_by(|a, b| a.module_name.cmp(&b.module_name));
+        Ok(modules)
+    }
+
+    // Put the existing source directories at the front of sys.path and register the embedded
+    // sources with the import hook, following the SourcePreference:
+    //  - PreferDisk: directories on sys.path, the hook after the normal finders so disk files win
+    //  - EmbeddedOnly: only the hook, in front of the normal finders
+    //  - DiskOnly: only the directories
+    fn install_import_paths(&mut self, py: Python<'_>) -> Result<(), Error> {
+        if self.import_paths_installed {
+            return Ok(());
+        }
+        // before anything is imported, so the modules can `import rust_host` and their logging reaches Rust
+        host::install(py)?;
+        logging::install(py)?;
+        let sys = py.import("sys")?;
+        let preference = self.sources.preference();
+
+        if preference != SourcePreference::EmbeddedOnly {
+            let sys_path: &PyList = sys.getattr("path")?.downcast().map_err(PyErr::from)?;
+            let dirs = self.sources.search_path().dirs();
+            for (index, dir) in dirs.iter().filter(|dir| dir.is_dir()).enumerate() {
+                let dir = dir.to_string_lossy();
+                if !sys_path.contains(dir.as_ref())? {
+                    sys_path.insert(index, dir.as_ref())?;
+                }
+            }
+        }
+
+        if preference != SourcePreference::DiskOnly {
+            let finder = match &self.embedded_finder {
+                Some(finder) => finder.as_ref(py),
+                None => {
+                    let importer = helper_module(py, EMBEDDED_IMPORTER, "embedded_importer.py", "embedded_importer")?;
+                    let finder = importer.getattr("EmbeddedFinder")?.call0()?;
+                    let meta_path: &PyList = sys.getattr("meta_path")?.downcast().map_err(PyErr::from)?;
+                    if preference == SourcePreference::EmbeddedOnly {
+                        meta_path.insert(0, finder)?;
+                    } else {
+                        meta_path.append(finder)?;
+                    }
+                    self.embedded_finder = Some(finder.into());
+                    finder
+                }
+            };
+            for source in self.sources.embedded_sources() {
+                let (module_name, is_package) = source.module_name();
+                finder.call_method1("add", (module_name, source.name, source.code, is_package))?;
+            }
+        }
+
+        self.import_paths_installed = true;
+        Ok(())
+    }
+
+    // import `name` and its submodules again into fresh module objects, the way recompile() does for load()
+    // the old modules are taken out of sys.modules first and put back if the new code fails to compile or
+    // raises while it runs (NameError, ImportError, ...), so a broken edit never leaves a half-updated module
+    // behind; `changed` are the modules whose file was edited
+    // code still holding the old module object (ex. a PyFunction made with from_module) keeps the old code
+    fn reimport(&mut self, py: Python<'_>, name: &str, changed: Vec<String>, keep_last_good: bool) -> Result<Py<PyModule>, Error> {
+        let Some(imported) = self.imports.get(name) else {
+            return Err(Error::invalid_file(name, "module was not imported"));
+        };
+        // the deepest edited module, ex. pkg/sub.py rather than pkg/__init__.py
+        let edited = changed.iter().max_by_key(|module_name| module_name.matches('.').count()).map_or(name, String::as_str);
+        let edited_path = imported.files.iter().find(|file| file.module_name == edited).map_or_else(|| PathBuf::from(edited), |file| file.path.clone());
+        let sys_modules: &PyDict = py.import("sys")?.getattr("modules")?.downcast().map_err(PyErr::from)?;
+
+        let mut old_modules = Vec::new();
+        for module_name in sys_module_names(py, name)? {
+            if let Some(module) = sys_modules.get_item(module_name.as_str()) {
+                old_modules.push((module_name, PyObject::from(module)));
+            }
+        }
+        remove_from_sys_modules(py, name)?;
+        py.import("importlib")?.call_method0("invalidate_caches")?;
+        let result = py.import(name);
+        if result.is_err() {
+            // whatever part of the new version got imported before the failure goes, the old modules come back
+            remove_from_sys_modules(py, name)?;
+            for (module_name, module) in &old_modules {
+                sys_modules.set_item(module_name, module)?;
+            }
+        }
+
+        // re-read the mtimes after a reload, and after a failed one that keeps the last good version so the
+        // broken file isn't reloaded on every call; without hot reload every call retries and returns the
+        // error, the same as load()
+        if result.is_ok() || keep_last_good {
+            let files = module_files(py, name)?;
+            self.imports.get_mut(name).expect("checked above").files = files;
+        }
+        match result {
+            Ok(module) => {
+                let info = self.imported_source_info(module)?;
+                let imported = self.imports.get_mut(name).expect("checked above");
+                imported.module = module.into();
+                imported.info = info;
+                self.stats.reloads += 1;
+                bump_generation();
+                if keep_last_good {
+                    self.events.push(ReloadEvent::Reloaded { path: edited_path });
+                }
+                Ok(module.into())
+            }
+            Err(err) if keep_last_good => {
+                self.stats.failed_reloads += 1;
+                self.events.push(ReloadEvent::Failed { path: edited_path, error: Error::from_pyerr(py, err) });
+                Ok(self.imports[name].module.clone_ref(py))
+            }
+            Err(err) => Err(Error::from_pyerr(py, err)),
+        }
+    }
+
+    // origin and hash of an imported module, from the embedded copy or the file in __file__
+    fn imported_source_info(&self, module: &PyModule) -> Result<SourceInfo, Error> {
+        let file: String = module.getattr("__file__").and_then(|f| f.extract()).unwrap_or_default();
+        let location = match self.sources.embedded(&file) {
+            Some(source) if module.hasattr("__embedded__")? => SourceLocation::Embedded(source),
+            _ => SourceLocation::Disk(PathBuf::from(file)),
+        };
+        Ok(location.read()?.info)
+    }
+
+    fn recompile(&mut self, py: Python<'_>, key: &Path, modified: Option<SystemTime>, keep_last_good: bool) -> Result<Py<PyModule>, Error> {
+        let Some(cached) = self.modules.get_mut(key) else {
+            return Err(Error::invalid_file(key, "module is not cached"));
+        };
+        let path = PathBuf::from(cached.info.file_name());
+        match compile(py, &cached.location) {
+            Ok((module, info)) => {
+                // the new version has a new name, the old one would stay in sys.modules forever
+                let old_name = module_name(cached.module.as_ref(py));
+                remove_from_sys_modules(py, &old_name)?;
+                cached.module = module.clone_ref(py);
+                cached.info = info;
+                cached.modified = modified;
+                self.stats.reloads += 1;
+                bump_generation();
+                if keep_last_good {
+                    self.events.push(ReloadEvent::Reloaded { path });
+                }
+                Ok(module)
+            }
+            Err(error) if keep_last_good => {
+                // remember the new mtime so the broken file isn't recompiled on every call
+                cached.modified = modified;
+                self.stats.failed_reloads += 1;
+                self.events.push(ReloadEvent::Failed { path, error });
+                Ok(cached.module.clone_ref(py))
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl HoldingGlobalLoader {
+    fn set() -> HoldingGlobalLoader {
+        HOLDING_GLOBAL_LOADER.with(|holding| holding.set(true));
+        HoldingGlobalLoader
+    }
+}
+
+impl Drop for HoldingGlobalLoader {
+    fn drop(&mut self) {
+        HOLDING_GLOBAL_LOADER.with(|holding| holding.set(false));
+    }
+}
+
+impl fmt::Display for ReloadEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadEvent::Reloaded { path } => write!(f, "Reloaded {}", path.display()),
+            ReloadEvent::Failed { path, error } => write!(
+                f,
+                "Could not reload {}, still using the last version that compiled\n{}",
+                path.display(),
+                error.report()
+            ),
+        }
+    }
+}
+
+// the same file may be reached through different relative paths
+fn cache_key(location: &SourceLocation) -> PathBuf {
+    match location {
+        SourceLocation::Disk(path) => fs::canonicalize(path).unwrap_or_else(|_| path.clone()),
+        SourceLocation::Embedded(source) => PathBuf::from("<embedded>").join(source.name),
+    }
+}
+
+// every file on disk behind `name` and the submodules imported from it
+fn module_files(py: Python<'_>, name: &str) -> Result<Vec<ModuleFile>, Error> {
+    let sys_modules: &PyDict = py.import("sys")?.getattr("modules")?.downcast().map_err(PyErr::from)?;
+    let prefix = format!("{name}.");
+    let mut files = Vec::new();
+    for (module_name, module) in sys_modules.iter() {
+        let module_name: String = module_name.extract()?;
+        if module_name != name && !module_name.starts_with(&prefix) {
+            continue;
+        }
+        // embedded modules never change
+        if module.hasattr("__embedded__").unwrap_or(false) {
+            continue;
+        }
+        let Some(file) = module.getattr("__file__").ok().and_then(|f| f.extract::<String>().ok()) else {
+            continue;
+        };
+        let path = PathBuf::from(file);
+        if let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) {
+            files.push(ModuleFile { module_name, path, modified });
+        }
+    }
+    Ok(files)
+}
+
+// names of the modules whose file changed since it was imported
+fn changed_files(files: &[ModuleFile]) -> Vec<String> {
+    files
+        .iter()
+        .filter(|file| fs::metadata(&file.path).and_then(|m| m.modified()).ok() != Some(file.modified))
+        .map(|file| file.module_name.clone())
+        .collect()
+}
+
+fn modified_time(location: &SourceLocation) -> Result<Option<SystemTime>, Error> {
+    match location {
+        SourceLocation::Disk(path) => Ok(Some(fs::metadata(path)?.modified()?)),
+        SourceLocation::Embedded(_) => Ok(None),
+    }
+}
+
+fn compile(py: Python<'_>, location: &SourceLocation) -> Result<(Py<PyModule>, SourceInfo), Error> {
+    let source = location.read()?;
+    let file_name = source.file_name();
+    let module_name = module_stem(location);
+
+    traceback::register_source(py, &file_name, &source.code)?;
+    let module = module_from_code(py, &source.code, &file_name, &module_name)?;
+    log::debug!("Loaded {}", source.info);
+    Ok((module.into(), source.info))
+}
+
+// "functions" for functions.py, embedded or on disk
+fn module_stem(location: &SourceLocation) -> String {
+    let path = match location {
+        SourceLocation::Embedded(source) => Path::new(source.name),
+        SourceLocation::Disk(path) => path.as_path(),
+    };
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| "module".to_owned())
+}
+
+// "pkg" for "pkg.sub"
+fn top_level(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
+// Create a module from source code, like PyModule::from_code, but registered in sys.modules as
+// "_examples.<name>_<n>" instead of `name`
+// from_code puts the module in sys.modules under the name it is given, so two files loaded as "functions"
+// would replace each other there, and a later `import functions` would pick up whichever came last
+pub fn module_from_code<'py>(py: Python<'py>, code: &str, file_name: &str, name: &str) -> Result<&'py PyModule, Error> {
+    // the code runs now, and may `import rust_host` or log
+    host::install(py)?;
+    logging::install(py)?;
+    let id = NEXT_MODULE_ID.fetch_add(1, Ordering::Relaxed);
+    let name = if name.is_empty() { "module" } else { name };
+    Ok(PyModule::from_code(py, code, file_name, &format!("{MODULE_NAMESPACE}.{name}_{id}"))?)
+}
+
+// Create one of the crate's own Python helpers (capture.py, log_handler.py, ...) as "_examples._<name>", so
+// it is listed by namespaced_modules() next to the user's modules instead of taking a top-level name
+// unlike module_from_code() nothing is installed first, the install functions use this themselves
+pub(crate) fn helper_module<'py>(py: Python<'py>, code: &str, file_name: &str, name: &str) -> PyResult<&'py PyModule> {
+    PyModule::from_code(py, code, file_name, &format!("{MODULE_NAMESPACE}._{name}"))
+}
+
+// Remove a module made with module_from_code (or any other module) and its submodules from sys.modules
+// returns the names that were removed
+pub fn unload_module(py: Python<'_>, module: &PyModule) -> Result<Vec<String>, Error> {
+    remove_from_sys_modules(py, &module_name(module))
+}
+
+// every module in sys.modules created by module_from_code(), whether or not a ModuleLoader holds it,
+// and the helpers made with helper_module() (their names start with "_examples._")
+pub fn namespaced_modules(py: Python<'_>) -> Result<Vec<String>, Error> {
+    let names = sys_module_names(py, MODULE_NAMESPACE)?;
+    Ok(names.into_iter().filter(|name| name != MODULE_NAMESPACE).collect())
+}
+
+// Changes whenever any module is reloaded or unloaded, so a check made against a module can tell it's stale
+pub fn module_generation() -> u64 {
+    GENERATION.load(Ordering::Acquire)
+}
+
+// what a reload or unload does to module_generation()
+pub(crate) fn bump_generation() {
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+// "functions" for "_examples.functions_3", for messages; other names are returned as they are
+pub(crate) fn display_name(module_name: &str) -> &str {
+    let Some(name) = module_name.strip_prefix(MODULE_NAMESPACE).and_then(|name| name.strip_prefix('.')) else {
+        return module_name;
+    };
+    match name.rsplit_once('_') {
+        Some((name, id)) if id.parse::<u64>().is_ok() => name,
+        _ => name,
+    }
+}
+
+fn module_name(module: &PyModule) -> String {
+    module.name().unwrap_or("<module>").to_owned()
+}
+
+// `name` and its submodules ("name.*") present in sys.modules
+fn sys_module_names(py: Python<'_>, name: &str) -> Result<Vec<String>, Error> {
+    let sys_modules: &PyDict = py.import("sys")?.getattr("modules")?.downcast().map_err(PyErr::from)?;
+    let prefix = format!("{name}.");
+    let mut names = Vec::new();
+    for key in sys_modules.keys() {
+        // sys.modules may hold non-str keys put there by other code
+        if let Ok(key) = key.extract::<String>() {
+            if key == name || key.starts_with(&prefix) {
+                names.push(key);
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn remove_from_sys_modules(py: Python<'_>, name: &str) -> Result<Vec<String>, Error> {
+    let sys_modules: &PyDict = py.import("sys")?.getattr("modules")?.downcast().map_err(PyErr::from)?;
+    let names = sys_module_names(py, name)?;
+    for name in &names {
+        sys_modules.del_item(name)?;
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::venv::{self, VenvLocator};
+
+    // an empty directory for one test, and a loader that only looks there
+    fn scratch(test: &str) -> (PathBuf, ModuleLoader) {
+        let dir = env::temp_dir().join(format!("examples-loader-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut loader = ModuleLoader::new();
+        loader.set_sources(SourceSet::new(&[], SearchPath::empty().dir(&dir)));
+        (dir, loader)
+    }
+
+    // write `code` with a modification time `age` seconds in the past, so every edit gets a new mtime
+    fn write(path: &Path, code: &str, age: u64) {
+        fs::write(path, code).unwrap();
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age)).unwrap();
+    }
+
+    fn value(py: Python<'_>, module: &Py<PyModule>) -> i64 {
+        module.as_ref(py).getattr("VALUE").unwrap().extract().unwrap()
+    }
+
+    #[test]
+    fn load_compiles_once_until_the_file_changes() {
+        let (dir, mut loader) = scratch("load");
+        write(&dir.join("counter.py"), "VALUE = 1\n", 100);
+        Python::with_gil(|py| {
+            let first = loader.load(py, "counter.py").unwrap();
+            let second = loader.load(py, "counter.py").unwrap();
+            assert!(first.is(&second));
+            assert_eq!(loader.stats(), CacheStats { hits: 1, misses: 1, reloads: 0, failed_reloads: 0 });
+
+            write(&dir.join("counter.py"), "VALUE = 2\n", 50);
+            let reloaded = loader.load(py, "counter.py").unwrap();
+            assert_eq!(value(py, &reloaded), 2);
+            assert_eq!(loader.stats(), CacheStats { hits: 1, misses: 1, reloads: 1, failed_reloads: 0 });
+            // the old version's name is gone from sys.modules
+            assert!(sys_module_names(py, &module_name(first.as_ref(py))).unwrap().is_empty());
+
+            assert!(loader.invalidate("counter.py"));
+            loader.load(py, "counter.py").unwrap();
+            assert_eq!(loader.stats().misses, 2);
+        });
+    }
+
+    #[test]
+    fn broken_edits_keep_the_last_good_version() {
+        let (dir, mut loader) = scratch("broken");
+        let path = dir.join("broken.py");
+        write(&path, "VALUE = 1\n", 100);
+        Python::with_gil(|py| {
+            loader.load(py, "broken.py").unwrap();
+
+            write(&path, "VALUE = (\n", 50);
+            assert!(matches!(loader.load(py, "broken.py"), Err(Error::Syntax(_))));
+
+            loader.set_hot_reload(true);
+            write(&path, "VALUE = [\n", 25);
+            let module = loader.load(py, "broken.py").unwrap();
+            assert_eq!(value(py, &module), 1);
+            assert_eq!(loader.stats().failed_reloads, 1);
+            assert!(matches!(loader.take_reload_events().as_slice(), [ReloadEvent::Failed { .. }]));
+        });
+    }
+
+    #[test]
+    fn import_is_cached_by_dotted_name() {
+        let (dir, mut loader) = scratch("import");
+        fs::create_dir_all(dir.join("loader_test_pkg")).unwrap();
+        write(&dir.join("loader_test_pkg/__init__.py"), "from .values import VALUE\n", 100);
+        write(&dir.join("loader_test_pkg/values.py"), "VALUE = 1\n", 100);
+        Python::with_gil(|py| {
+            let module = loader.import(py, "loader_test_pkg").unwrap();
+            assert_eq!(value(py, &module), 1);
+            loader.import(py, "loader_test_pkg").unwrap();
+            assert_eq!(loader.stats(), CacheStats { hits: 1, misses: 1, reloads: 0, failed_reloads: 0 });
+
+            // an edited submodule reloads the package too
+            write(&dir.join("loader_test_pkg/values.py"), "VALUE = 2\n", 50);
+            let module = loader.import(py, "loader_test_pkg").unwrap();
+            assert_eq!(value(py, &module), 2);
+            assert_eq!(loader.stats().reloads, 1);
+
+            let removed = loader.unload(py, "loader_test_pkg").unwrap();
+            assert_eq!(removed, ["loader_test_pkg", "loader_test_pkg.values"]);
+        });
+    }
+
+    #[test]
+    fn with_global_rejects_reentry() {
+        Python::with_gil(|py| {
+            let nested = ModuleLoader::with_global(py, |_| ModuleLoader::with_global(py, |loader| Ok(loader.stats())));
+            assert!(matches!(nested, Err(Error::LoaderReentered)));
+            // the flag is cleared once the outer call returns
+            assert!(ModuleLoader::with_global(py, |loader| Ok(loader.stats())).is_ok());
+        });
+    }
+    #[test]
+    fn failed_reimports_leave_the_old_module_untouched() {
+        let (dir, mut loader) = scratch("reimport");
+        let path = dir.join("loader_test_runtime.py");
+        write(&path, "VALUE = 1\n", 100);
+        Python::with_gil(|py| {
+            let old = loader.import(py, "loader_test_runtime").unwrap();
+
+            // compiles, but raises halfway through running; VALUE must not be 2 afterwards
+            write(&path, "VALUE = 2\nundefined_name\n", 50);
+            assert!(matches!(loader.import(py, "loader_test_runtime"), Err(Error::Python(e)) if e.type_name == "NameError"));
+            // without hot reload the error keeps coming back until the file is fixed, as with load()
+            assert!(matches!(loader.import(py, "loader_test_runtime"), Err(Error::Python(e)) if e.type_name == "NameError"));
+            loader.set_hot_reload(true);
+            write(&path, "VALUE = 3\nimport no_such_module_here\n", 25);
+            let module = loader.import(py, "loader_test_runtime").unwrap();
+            assert!(module.is(&old));
+            assert_eq!(value(py, &old), 1);
+            assert!(py.import("loader_test_runtime").unwrap().is(old.as_ref(py)));
+            assert_eq!(loader.stats().failed_reloads, 1);
+
+            write(&path, "VALUE = 4\n", 10);
+            let module = loader.import(py, "loader_test_runtime").unwrap();
+            assert_eq!(value(py, &module), 4);
+            assert_eq!(value(py, &old), 1);
+        });
+    }
+
+    #[test]
+    fn display_name_strips_the_namespace() {
+        let cases = [
+            ("_examples.functions_3", "functions"),
+            ("_examples.my_module_12", "my_module"),
+            ("_examples.no_number", "no_number"),
+            ("_examples._capture", "_capture"),
+            ("functions_venv", "functions_venv"),
+            ("pkg.sub_1", "pkg.sub_1"),
+        ];
+        for (module_name, expected) in cases {
+            assert_eq!(display_name(module_name), expected, "{module_name}");
+        }
+    }
+
+    #[test]
+    fn helpers_are_namespaced_like_loaded_modules() {
+        Python::with_gil(|py| {
+            let module = module_from_code(py, "VALUE = 1\n", "namespaced.py", "namespaced").unwrap();
+            let names = namespaced_modules(py).unwrap();
+            // module_from_code() installs rust_host and the log handler first
+            for helper in ["_examples._host_module", "_examples._log_handler", module.name().unwrap()] {
+                assert!(names.iter().any(|name| name == helper), "{helper} not in {names:?}");
+            }
+            let sys_modules = py.import("sys").unwrap().getattr("modules").unwrap();
+            for old_name in ["_examples_host_module", "_examples_log_handler"] {
+                assert!(!sys_modules.contains(old_name).unwrap(), "{old_name}");
+            }
+            assert_eq!(unload_module(py, module).unwrap(), [module.name().unwrap()]);
+        });
+    }
+
+    // the crate's own py/ files, held by two loaders: one load()s them, the other import()s them
+    #[test]
+    fn loaded_and_imported_copies_stay_apart() {
+        Python::with_gil(|py| {
+            venv::activate(py, &VenvLocator::default()).unwrap();
+            let (mut loading, mut importing) = (ModuleLoader::new(), ModuleLoader::new());
+            for (file, name) in [("functions.py", "functions"), ("functions_venv.py", "functions_venv")] {
+                let loaded = loading.load(py, file).unwrap();
+                let imported = importing.import(py, name).unwrap();
+                assert!(!loaded.is(&imported), "{name}");
+                assert_eq!(display_name(loaded.as_ref(py).name().unwrap()), name);
+                assert_eq!(imported.as_ref(py).name().unwrap(), name);
+                assert!(py.import(name).unwrap().is(imported.as_ref(py)));
+
+                // state set on one copy doesn't show up in the other
+                loaded.as_ref(py).setattr("LOADED_COPY", true).unwrap();
+                assert!(!imported.as_ref(py).hasattr("LOADED_COPY").unwrap());
+
+                // and one loader won't hold a module both ways
+                assert!(matches!(loading.import(py, name), Err(Error::ModuleNameClash { name: clash }) if clash == name));
+                assert!(matches!(importing.load(py, file), Err(Error::ModuleNameClash { name: clash }) if clash == name));
+            }
+
+            // unloading the imported copy leaves the loaded one alone
+            importing.unload(py, "functions").unwrap();
+            let loaded = loading.load(py, "functions.py").unwrap();
+            let sum: i64 = loaded.as_ref(py).getattr("add_numbers").unwrap().call1((2, 3)).unwrap().extract().unwrap();
+            assert_eq!(sum, 5);
+            assert!(loaded.as_ref(py).hasattr("LOADED_COPY").unwrap());
+        });
+    }
+}
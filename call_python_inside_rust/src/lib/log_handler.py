@@ -0,0 +1,38 @@
+# logging.Handler that hands every LogRecord to Rust's `log` facade (see logging.rs)
+# it sits on the root logger, so records from any library (boto3, botocore, urllib3, ...) reach it
+# unless a logger on the way stops propagation
+import logging
+
+_exception_formatter = logging.Formatter()
+
+
+class RustHandler(logging.Handler):
+    def __init__(self, emit_record):
+        super().__init__(logging.NOTSET)
+        self.emit_record = emit_record
+
+    def emit(self, record):
+        try:
+            # getMessage() applies the % args; a traceback is appended when the record has exc_info
+            message = record.getMessage()
+            if record.exc_info:
+                message += "\n" + _exception_formatter.formatException(record.exc_info)
+            handled = self.emit_record(record.levelno, record.name, message, record.pathname, record.lineno, record.module)
+            # no Rust logger is set up: print warnings and errors the way Python does without any handler
+            if not handled and logging.lastResort and record.levelno >= logging.lastResort.level:
+                logging.lastResort.handle(record)
+        except Exception:
+            self.handleError(record)
+
+
+# root_level None leaves the root logger at Python's default (WARNING)
+def install(emit_record, root_level):
+    root = logging.getLogger()
+    for handler in root.handlers:
+        if isinstance(handler, RustHandler):
+            return handler
+    handler = RustHandler(emit_record)
+    root.addHandler(handler)
+    if root_level is not None:
+        root.setLevel(root_level)
+    return handler
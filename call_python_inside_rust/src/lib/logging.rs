@@ -0,0 +1,133 @@
+use log::{Level, LevelFilter, Record};
+use pyo3::once_cell::GILOnceCell;
+use pyo3::prelude::*;
+use pyo3::types::{PyCFunction, PyDict, PyTuple};
+
+use crate::error::Error;
+use crate::loader::helper_module;
+
+const LOG_HANDLER: &str = include_str!("log_handler.py");
+
+// Forward Python's `logging` to the Rust `log` facade
+// every LogRecord becomes a log::Record with the logger name as target (ex. "botocore.endpoint") and the
+// Python file and line, so RUST_LOG=botocore=debug works the same as for a Rust module
+// the root logger's level follows log::max_level(), so set up the Rust logger (env_logger, ...) first
+// without one (log::max_level() is Off) the root level is left alone and records go to Python's
+// logging.lastResort, so warnings and errors still show up on stderr the way they do without the handler
+// module_from_code, ModuleLoader::import and activate() call this; calling it again does nothing
+pub fn install(py: Python<'_>) -> Result<(), Error> {
+    static INSTALLED: GILOnceCell<()> = GILOnceCell::new();
+    INSTALLED.get_or_try_init(py, || -> PyResult<()> {
+        let handler = helper_module(py, LOG_HANDLER, "log_handler.py", "log_handler")?;
+        // returns false when there is no Rust logger to take the record, read on every record since it
+        // may be set up after this runs
+        let emit = PyCFunction::new_closure(py, None, None, |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<bool> {
+            if log::max_level() == LevelFilter::Off {
+                return Ok(false);
+            }
+            let (level, name, message, file, line, module): (i32, &str, &str, &str, u32, &str) = args.extract()?;
+            log::logger().log(
+                &Record::builder()
+                    .level(rust_level(level))
+                    .target(name)
+                    .args(format_args!("{message}"))
+                    .file(Some(file))
+                    .line(Some(line))
+                    .module_path(Some(module))
+                    .build(),
+            );
+            Ok(true)
+        })?;
+        let root_level = match log::max_level() {
+            LevelFilter::Off => py.None(),
+            level => python_level(level).into_py(py),
+        };
+        handler.getattr("install")?.call1((emit, root_level))?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+// Only forward records from `logger` (and its children) at `level` or above, ex. ("botocore", LevelFilter::Warn)
+// "" is the root logger; LevelFilter::Off silences the logger entirely
+// this sets the level of the Python logger, so filtered records aren't even formatted
+pub fn set_level(py: Python<'_>, logger: &str, level: LevelFilter) -> Result<(), Error> {
+    let logging = py.import("logging")?;
+    let logger = if logger.is_empty() { logging.call_method0("getLogger")? } else { logging.call_method1("getLogger", (logger,))? };
+    logger.call_method1("setLevel", (python_level(level),))?;
+    Ok(())
+}
+
+// set_level() for several loggers
+pub fn set_levels<'a>(py: Python<'_>, levels: impl IntoIterator<Item = (&'a str, LevelFilter)>) -> Result<(), Error> {
+    for (logger, level) in levels {
+        set_level(py, logger, level)?;
+    }
+    Ok(())
+}
+
+// CRITICAL and ERROR are both Error; anything below DEBUG is Trace
+fn rust_level(levelno: i32) -> Level {
+    match levelno {
+        40.. => Level::Error,
+        30..=39 => Level::Warn,
+        20..=29 => Level::Info,
+        10..=19 => Level::Debug,
+        _ => Level::Trace,
+    }
+}
+
+// the lowest Python level that passes the filter
+fn python_level(filter: LevelFilter) -> i32 {
+    match filter {
+        // above CRITICAL
+        LevelFilter::Off => 60,
+        LevelFilter::Error => 40,
+        LevelFilter::Warn => 30,
+        LevelFilter::Info => 20,
+        LevelFilter::Debug => 10,
+        // not NOTSET (0), which on a named logger means "use the parent's level"
+        LevelFilter::Trace => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_round_trip() {
+        let cases = [
+            (50, Level::Error, LevelFilter::Error, 40),
+            (40, Level::Error, LevelFilter::Error, 40),
+            (30, Level::Warn, LevelFilter::Warn, 30),
+            (25, Level::Info, LevelFilter::Info, 20),
+            (10, Level::Debug, LevelFilter::Debug, 10),
+            (5, Level::Trace, LevelFilter::Trace, 1),
+        ];
+        for (levelno, level, filter, lowest) in cases {
+            assert_eq!(rust_level(levelno), level, "{levelno}");
+            assert_eq!(python_level(filter), lowest, "{filter}");
+        }
+        assert_eq!(python_level(LevelFilter::Off), 60);
+    }
+
+    // the tests don't set up a Rust logger, so log::max_level() is Off
+    #[test]
+    fn without_a_rust_logger_the_root_level_is_left_alone() {
+        Python::with_gil(|py| {
+            install(py).unwrap();
+            install(py).unwrap();
+            let root = py.import("logging").unwrap().call_method0("getLogger").unwrap();
+            assert_eq!(root.getattr("level").unwrap().extract::<i32>().unwrap(), 30);
+            let handlers: Vec<String> = root
+                .getattr("handlers")
+                .unwrap()
+                .iter()
+                .unwrap()
+                .map(|handler| handler.unwrap().get_type().name().unwrap().to_owned())
+                .collect();
+            assert_eq!(handlers.iter().filter(|name| *name == "RustHandler").count(), 1, "{handlers:?}");
+        });
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::requirements::normalize;
+
+// A distribution installed in the environment the embedded interpreter uses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageInfo {
+    pub name: String,
+    pub version: String,
+    // the directory the distribution was installed into (ex. env/lib/python3.11/site-packages)
+    pub location: Option<String>,
+    // requirement strings from the package metadata (ex. "botocore<1.28.0,>=1.27.85")
+    pub requires: Vec<String>,
+}
+
+// how display_package_info() prints the packages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+// List the distributions importlib.metadata can see from inside the embedded interpreter
+// this reflects sys.path as it is now, so it includes the venv set up by venv::activate()
+// when the same name is installed twice, the copy that `import` would pick (first on sys.path) is kept
+pub fn installed_packages(py: Python<'_>) -> Result<Vec<PackageInfo>, Error> {
+    let metadata = py.import("importlib.metadata")?;
+    let mut seen = HashSet::new();
+    let mut packages = Vec::new();
+    for dist in metadata.call_method0("distributions")?.iter()? {
+        let dist = dist?;
+        // a broken .dist-info directory can be missing its Name
+        let Some(name) = dist.getattr("metadata")?.call_method1("get", ("Name",))?.extract::<Option<String>>()? else {
+            continue;
+        };
+        if !seen.insert(normalize(&name)) {
+            continue;
+        }
+        let location = dist.call_method1("locate_file", ("",))?.str()?.to_string_lossy().into_owned();
+        packages.push(PackageInfo {
+            name,
+            version: dist.getattr("version")?.extract()?,
+            location: Some(location).filter(|l| !l.is_empty()),
+            requires: dist.getattr("requires")?.extract::<Option<Vec<String>>>()?.unwrap_or_default(),
+        });
+    }
+    packages.sort_by_key(|p| p.name.to_ascii_lowercase());
+    Ok(packages)
+}
+
+// one line per package, with its requirements indented below it
+// requirements that only apply to optional extras (`; extra == "docs"`) are left out to keep it short
+pub fn render_text(packages: &[PackageInfo]) -> String {
+    if packages.is_empty() {
+        return "No packages installed".to_owned();
+    }
+    let width = packages.iter().map(|p| p.name.len() + p.version.len() + 2).max().unwrap_or(0);
+    let mut text = String::new();
+    for p in packages {
+        let name = format!("{}=={}", p.name, p.version);
+        let _ = writeln!(text, "{name:<width$}  {}", p.location.as_deref().unwrap_or("<unknown location>"));
+        for requirement in p.requires.iter().filter(|r| !r.contains("extra ==")) {
+            let _ = writeln!(text, "    requires {requirement}");
+        }
+    }
+    text.trim_end().to_owned()
+}
+
+// JSON array of the packages, ex. [{"name": "emoji", "version": "2.2.0", "location": "...", "requires": []}]
+pub fn render_json(packages: &[PackageInfo]) -> Result<String, Error> {
+    serde_json::to_string_pretty(packages).map_err(|e| Error::Conversion { target: "JSON", message: e.to_string() })
+}
@@ -0,0 +1,28 @@
+// @generated by `cargo run -- codegen` from the type hints in py/*.py
+// don't edit this file, change the Python functions and run the generator again
+
+// py/functions.py
+pub mod functions {
+    crate::py_bindings! {
+        module "functions";
+        pub fn add_numbers(a: i64, b: i64) -> i64;
+    }
+}
+
+// py/functions_venv.py
+pub mod functions_venv {
+    crate::py_bindings! {
+        module "functions_venv";
+        pub fn random_number(a: i64, b: i64) -> i64;
+        pub fn emoji_test();
+        pub fn color_emoji(color: &str) -> Option<String>;
+        pub fn find_emoji(name: &str) -> Option<String>;
+    }
+}
+
+// check every binding above against its Python function, ex. at startup
+pub fn validate_all() -> Result<(), crate::Error> {
+    functions::validate_all()?;
+    functions_venv::validate_all()?;
+    Ok(())
+}
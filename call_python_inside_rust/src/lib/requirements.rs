@@ -0,0 +1,727 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pyo3::prelude::*;
+
+use crate::error::Error;
+use crate::packages::{installed_packages, PackageInfo};
+
+// installers that end up in every venv without being listed in requirements.txt
+const TOOLING_PACKAGES: &[&str] = &["pip", "setuptools", "wheel", "distribute", "pkg-resources"];
+
+// One line of a requirements file, ex. `boto3>=1.24,<2.0 ; python_version >= "3.8"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    pub name: String,
+    pub extras: Vec<String>,
+    pub specifiers: Vec<VersionSpecifier>,
+    // environment marker after `;`, kept as written
+    pub marker: Option<String>,
+    // where the requirement was read from, for error messages
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    // ~= (compatible release)
+    Compatible,
+    // === (exact string match)
+    Arbitrary,
+}
+
+// ex. ">=1.24" or "==2.2.*"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionSpecifier {
+    pub comparison: Comparison,
+    pub version: String,
+}
+
+// PEP 440 version, enough of it to compare release, pre-, post- and dev-releases
+// local versions (+ubuntu1) are ignored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    // 0 = alpha, 1 = beta, 2 = release candidate
+    pub pre: Option<(u8, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+}
+
+// epoch, release without trailing zeros, pre-release, post-release, dev-release
+type VersionKey = (u64, Vec<u64>, (u8, u64), Option<u64>, (bool, u64));
+
+// what to do when check_requirements() finds a problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequirementsPolicy {
+    // return the report and let the caller decide
+    #[default]
+    Warn,
+    // refuse to run: missing or mismatched packages become Error::RequirementsNotMet
+    Enforce,
+}
+
+#[derive(Debug, Clone)]
+pub struct VersionMismatch {
+    pub requirement: Requirement,
+    pub installed: PackageInfo,
+}
+
+// Result of comparing a requirements file with the installed packages
+#[derive(Debug, Clone, Default)]
+pub struct RequirementsReport {
+    pub file: PathBuf,
+    pub satisfied: Vec<(Requirement, PackageInfo)>,
+    pub missing: Vec<Requirement>,
+    pub mismatched: Vec<VersionMismatch>,
+    // installed, but neither required nor needed by a required package
+    pub extra: Vec<PackageInfo>,
+    // requirements with an environment marker, which aren't evaluated
+    pub skipped: Vec<Requirement>,
+}
+
+// Read a requirements file, following `-r other.txt` includes
+// pip options (--index-url, --hash, ...) and URL requirements without a name are ignored
+pub fn parse_requirements(path: impl AsRef<Path>) -> Result<Vec<Requirement>, Error> {
+    let mut requirements = Vec::new();
+    let mut visited = HashSet::new();
+    parse_file(path.as_ref(), &mut visited, &mut requirements)?;
+    Ok(requirements)
+}
+
+fn parse_file(path: &Path, visited: &mut HashSet<PathBuf>, requirements: &mut Vec<Requirement>) -> Result<(), Error> {
+    let contents = fs::read_to_string(path).map_err(|e| Error::invalid_file(path, format!("could not read requirements: {e}")))?;
+    // a file that includes itself (directly or not) is only read once
+    if !visited.insert(path.canonicalize().unwrap_or_else(|_| path.to_path_buf())) {
+        return Ok(());
+    }
+
+    // lines ending in \ continue on the next line
+    let mut logical_lines: Vec<(usize, String)> = Vec::new();
+    let mut continued = false;
+    for (index, raw) in contents.lines().enumerate() {
+        let line = match raw.find(" #").or_else(|| raw.starts_with('#').then_some(0)) {
+            Some(comment) => &raw[..comment],
+            None => raw,
+        };
+        let (line, continues) = match line.trim_end().strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        match logical_lines.last_mut() {
+            Some((_, previous)) if continued => previous.push_str(line),
+            _ => logical_lines.push((index + 1, line.to_owned())),
+        }
+        continued = continues;
+    }
+
+    for (line_number, line) in logical_lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(include) = include_target(line) {
+            let include = path.parent().unwrap_or(Path::new("")).join(include);
+            parse_file(&include, visited, requirements)?;
+            continue;
+        }
+        if line.starts_with('-') {
+            continue;
+        }
+        let requirement = Requirement::parse(line)
+            .map_err(|reason| Error::invalid_file(path, format!("line {line_number}: {reason}")))?;
+        if let Some(requirement) = requirement {
+            requirements.push(Requirement { file: path.to_path_buf(), line: line_number, ..requirement });
+        }
+    }
+    Ok(())
+}
+
+impl Requirement {
+    // Parse a single requirement; Ok(None) for lines that don't name a package (URLs, paths)
+    pub fn parse(line: &str) -> Result<Option<Requirement>, String> {
+        // per-requirement options like --hash come after the requirement itself
+        let line = line.split(" --").next().unwrap_or_default().trim();
+        let (requirement, marker) = match line.split_once(';') {
+            Some((requirement, marker)) => (requirement.trim(), Some(marker.trim().to_owned())),
+            None => (line, None),
+        };
+
+        // a bare URL or path (https://.../x.whl, ./pkg) doesn't say which package it is
+        if !requirement.contains('@') && (requirement.contains("://") || requirement.starts_with(['.', '/'])) {
+            return Ok(None);
+        }
+        let name_end = requirement
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "-_.".contains(c)))
+            .unwrap_or(requirement.len());
+        let name = &requirement[..name_end];
+        if name.is_empty() {
+            return Err(format!("expected a package name in {requirement:?}"));
+        }
+        let mut rest = requirement[name_end..].trim_start();
+
+        let mut extras = Vec::new();
+        if let Some(after_bracket) = rest.strip_prefix('[') {
+            let (inside, after) = after_bracket.split_once(']').ok_or_else(|| format!("unclosed [ in {requirement:?}"))?;
+            extras = inside.split(',').map(|e| e.trim().to_owned()).filter(|e| !e.is_empty()).collect();
+            rest = after.trim_start();
+        }
+
+        // name @ https://... pins a location rather than a version
+        if rest.starts_with('@') {
+            return Ok(Some(Requirement::new(name, extras, Vec::new(), marker)));
+        }
+
+        // pip also accepts the specifiers in parentheses: name (>=1.0)
+        let rest = rest.trim_start_matches('(').trim_end_matches(')');
+        let specifiers = rest
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(VersionSpecifier::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(Requirement::new(name, extras, specifiers, marker)))
+    }
+
+    fn new(name: &str, extras: Vec<String>, specifiers: Vec<VersionSpecifier>, marker: Option<String>) -> Requirement {
+        Requirement { name: name.to_owned(), extras, specifiers, marker, file: PathBuf::new(), line: 0 }
+    }
+
+    // name compared the way pip does (case-insensitive, - _ and . are the same)
+    pub fn normalized_name(&self) -> String {
+        normalize(&self.name)
+    }
+
+    // true if `version` meets every specifier
+    pub fn matches(&self, version: &str) -> bool {
+        self.specifiers.iter().all(|s| s.matches(version))
+    }
+}
+
+impl VersionSpecifier {
+    pub fn parse(spec: &str) -> Result<VersionSpecifier, String> {
+        // longest operators first so "===" isn't read as "=="
+        const OPERATORS: &[(&str, Comparison)] = &[
+            ("===", Comparison::Arbitrary),
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("~=", Comparison::Compatible),
+            ("<=", Comparison::LessEqual),
+            (">=", Comparison::GreaterEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        let (comparison, version) = OPERATORS
+            .iter()
+            .find_map(|(op, comparison)| spec.strip_prefix(op).map(|version| (*comparison, version.trim())))
+            .ok_or_else(|| format!("expected a version specifier like ==1.0, found {spec:?}"))?;
+        if version.is_empty() {
+            return Err(format!("missing version in {spec:?}"));
+        }
+        let wildcard = version.strip_suffix(".*");
+        let valid = match (comparison, wildcard) {
+            (Comparison::Arbitrary, _) => true,
+            (Comparison::Equal | Comparison::NotEqual, Some(prefix)) => Version::parse(prefix).is_some(),
+            (_, Some(_)) => false,
+            (_, None) => Version::parse(version).is_some(),
+        };
+        if !valid {
+            return Err(format!("invalid version {version:?} in {spec:?}"));
+        }
+        Ok(VersionSpecifier { comparison, version: version.to_owned() })
+    }
+
+    // Does `installed` satisfy this specifier
+    // installed versions that aren't PEP 440 only match === and == with the exact same string
+    pub fn matches(&self, installed: &str) -> bool {
+        if self.comparison == Comparison::Arbitrary {
+            return installed.eq_ignore_ascii_case(&self.version);
+        }
+        let Some(installed_version) = Version::parse(installed) else {
+            return self.comparison == Comparison::Equal && installed == self.version;
+        };
+        if let Some(prefix) = self.version.strip_suffix(".*") {
+            let prefix = Version::parse(prefix).expect("checked in parse()");
+            let matches = installed_version.epoch == prefix.epoch && installed_version.starts_with(&prefix.release);
+            return matches == (self.comparison == Comparison::Equal);
+        }
+        let version = Version::parse(&self.version).expect("checked in parse()");
+        let ordering = installed_version.cmp(&version);
+        match self.comparison {
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::NotEqual => ordering != Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterEqual => ordering != Ordering::Less,
+            // ~=2.2.1 means >=2.2.1 and ==2.2.*
+            Comparison::Compatible => {
+                let prefix = &version.release[..version.release.len().saturating_sub(1).max(1)];
+                ordering != Ordering::Less && installed_version.starts_with(prefix)
+            }
+            Comparison::Arbitrary => unreachable!("handled above"),
+        }
+    }
+}
+
+impl Version {
+    pub fn parse(version: &str) -> Option<Version> {
+        let version = version.trim().to_ascii_lowercase();
+        let version = version.strip_prefix('v').unwrap_or(&version);
+        let version = version.split('+').next()?;
+        let (epoch, rest) = match version.split_once('!') {
+            Some((epoch, rest)) => (epoch.parse().ok()?, rest),
+            None => (0, version),
+        };
+
+        let release_end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        let release_text = rest[..release_end].trim_end_matches('.');
+        let release = release_text.split('.').map(|n| n.parse().ok()).collect::<Option<Vec<u64>>>()?;
+        let mut rest = &rest[release_text.len()..];
+
+        let mut pre = None;
+        let mut post = None;
+        let mut dev = None;
+        rest = rest.trim_start_matches(['.', '-', '_']);
+        for (label, phase) in [("alpha", 0), ("a", 0), ("beta", 1), ("b", 1), ("rc", 2), ("c", 2), ("preview", 2), ("pre", 2)] {
+            if let Some(after) = rest.strip_prefix(label) {
+                let (n, after) = leading_number(after.trim_start_matches(['.', '-', '_']));
+                pre = Some((phase, n));
+                rest = after.trim_start_matches(['.', '-', '_']);
+                break;
+            }
+        }
+        for label in ["post", "rev", "r"] {
+            if let Some(after) = rest.strip_prefix(label) {
+                let (n, after) = leading_number(after.trim_start_matches(['.', '-', '_']));
+                post = Some(n);
+                rest = after.trim_start_matches(['.', '-', '_']);
+                break;
+            }
+        }
+        // 1.0-1 is the implicit form of 1.0.post1
+        if post.is_none() && rest.starts_with(|c: char| c.is_ascii_digit()) && version.contains('-') {
+            let (n, after) = leading_number(rest);
+            post = Some(n);
+            rest = after.trim_start_matches(['.', '-', '_']);
+        }
+        if let Some(after) = rest.strip_prefix("dev") {
+            let (n, after) = leading_number(after.trim_start_matches(['.', '-', '_']));
+            dev = Some(n);
+            rest = after;
+        }
+        rest.is_empty().then_some(Version { epoch, release, pre, post, dev })
+    }
+
+    fn starts_with(&self, prefix: &[u64]) -> bool {
+        (0..prefix.len()).all(|i| self.release.get(i).copied().unwrap_or(0) == prefix[i])
+    }
+
+    // PEP 440 ordering: 1.0.dev0 < 1.0a1 < 1.0 < 1.0.post1
+    fn sort_key(&self) -> VersionKey {
+        let mut release = self.release.clone();
+        while release.last() == Some(&0) {
+            release.pop();
+        }
+        let pre = match (self.pre, self.post, self.dev) {
+            (Some(pre), _, _) => (pre.0 + 1, pre.1),
+            // a dev release of the final version sorts before its pre-releases
+            (None, None, Some(_)) => (0, 0),
+            (None, _, _) => (u8::MAX, 0),
+        };
+        (self.epoch, release, pre, self.post, (self.dev.is_none(), self.dev.unwrap_or(0)))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Compare the requirements with the installed packages
+// extra packages are the ones installed that nothing in the requirements needs, directly or through
+// the `requires` of a required package; installers like pip and setuptools are never extra
+pub fn verify(file: impl Into<PathBuf>, requirements: &[Requirement], installed: &[PackageInfo]) -> RequirementsReport {
+    let by_name: HashMap<String, &PackageInfo> = installed.iter().map(|p| (normalize(&p.name), p)).collect();
+    let mut report = RequirementsReport { file: file.into(), ..RequirementsReport::default() };
+
+    for requirement in requirements {
+        if requirement.marker.is_some() {
+            report.skipped.push(requirement.clone());
+            continue;
+        }
+        match by_name.get(&requirement.normalized_name()) {
+            None => report.missing.push(requirement.clone()),
+            Some(package) if requirement.matches(&package.version) => {
+                report.satisfied.push((requirement.clone(), (*package).clone()))
+            }
+            Some(package) => report.mismatched.push(VersionMismatch {
+                requirement: requirement.clone(),
+                installed: (*package).clone(),
+            }),
+        }
+    }
+
+    // everything reachable from the requirements through the installed packages' dependencies
+    let mut needed: HashSet<String> = requirements.iter().map(Requirement::normalized_name).collect();
+    let mut queue: Vec<String> = needed.iter().cloned().collect();
+    while let Some(name) = queue.pop() {
+        let Some(package) = by_name.get(&name) else { continue };
+        for dependency in &package.requires {
+            // optional extras aren't installed unless asked for
+            if dependency.contains("extra ==") {
+                continue;
+            }
+            if let Ok(Some(dependency)) = Requirement::parse(dependency) {
+                if needed.insert(dependency.normalized_name()) {
+                    queue.push(dependency.normalized_name());
+                }
+            }
+        }
+    }
+    report.extra = installed
+        .iter()
+        .filter(|p| !needed.contains(&normalize(&p.name)) && !TOOLING_PACKAGES.contains(&normalize(&p.name).as_str()))
+        .cloned()
+        .collect();
+    report
+}
+
+// Parse `path` and compare it with the packages installed in the embedded interpreter
+// with RequirementsPolicy::Enforce a failed check is returned as Error::RequirementsNotMet
+pub fn check_requirements(py: Python<'_>, path: impl AsRef<Path>, policy: RequirementsPolicy) -> Result<RequirementsReport, Error> {
+    let path = path.as_ref();
+    let requirements = parse_requirements(path)?;
+    let report = verify(path, &requirements, &installed_packages(py)?);
+    if policy == RequirementsPolicy::Enforce && !report.is_ok() {
+        return Err(Error::RequirementsNotMet(Box::new(report)));
+    }
+    Ok(report)
+}
+
+impl RequirementsReport {
+    // nothing missing and every version matches (extra packages don't count)
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.extras.is_empty() {
+            write!(f, "[{}]", self.extras.join(","))?;
+        }
+        let specifiers: Vec<String> = self.specifiers.iter().map(ToString::to_string).collect();
+        write!(f, "{}", specifiers.join(","))?;
+        if let Some(marker) = &self.marker {
+            write!(f, " ; {marker}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for VersionSpecifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.comparison {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterEqual => ">=",
+            Comparison::Compatible => "~=",
+            Comparison::Arbitrary => "===",
+        };
+        write!(f, "{op}{}", self.version)
+    }
+}
+
+impl fmt::Display for RequirementsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.is_ok() { "OK" } else { "FAILED" };
+        write!(f, "Requirements check for {}: {status}", self.file.display())?;
+        for (requirement, package) in &self.satisfied {
+            write!(f, "\n    ok        {requirement} (installed {})", package.version)?;
+        }
+        for requirement in &self.missing {
+            write!(f, "\n    missing   {requirement} ({}:{})", requirement.file.display(), requirement.line)?;
+        }
+        for mismatch in &self.mismatched {
+            write!(f, "\n    mismatch  {} (installed {})", mismatch.requirement, mismatch.installed.version)?;
+        }
+        for requirement in &self.skipped {
+            write!(f, "\n    skipped   {requirement} (environment markers aren't checked)")?;
+        }
+        for package in &self.extra {
+            write!(f, "\n    extra     {}=={}", package.name, package.version)?;
+        }
+        Ok(())
+    }
+}
+
+// pip treats "Foo_Bar", "foo-bar" and "foo.bar" as the same package
+pub(crate) fn normalize(name: &str) -> String {
+    name.to_ascii_lowercase().replace(['_', '.'], "-")
+}
+
+// the file named by `-r other.txt`, `-rother.txt`, `--requirement other.txt` or `--requirement=other.txt`
+fn include_target(line: &str) -> Option<&str> {
+    let rest = match line.strip_prefix("--requirement") {
+        Some(rest) if rest.starts_with([' ', '\t', '=']) => rest,
+        // some other option, ex. --requirements-only
+        Some(_) => return None,
+        None => line.strip_prefix("-r")?,
+    };
+    let target = rest.trim_start();
+    let target = target.strip_prefix('=').unwrap_or(target).trim();
+    (!target.is_empty()).then_some(target)
+}
+
+fn leading_number(text: &str) -> (u64, &str) {
+    let end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    (text[..end].parse().unwrap_or(0), &text[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn version(text: &str) -> Version {
+        Version::parse(text).unwrap_or_else(|| panic!("{text:?} should parse"))
+    }
+
+    fn package(name: &str, version: &str, requires: &[&str]) -> PackageInfo {
+        PackageInfo { name: name.to_owned(), version: version.to_owned(), location: None, requires: requires.iter().map(|r| (*r).to_owned()).collect() }
+    }
+
+    fn requirement(line: &str) -> Requirement {
+        Requirement::parse(line).unwrap().unwrap_or_else(|| panic!("{line:?} names no package"))
+    }
+
+    // a requirements file (and the files it includes) in a directory of its own
+    fn requirements_file(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("examples-requirements-{test}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        dir.join(files[0].0)
+    }
+
+    #[test]
+    fn versions_sort_like_pep_440() {
+        // each version sorts before the next one
+        let ordered = [
+            "0.9", "1.0.dev0", "1.0.dev1", "1.0a1.dev0", "1.0a1", "1.0a2", "1.0b1", "1.0rc1", "1.0rc2.post1", "1.0",
+            "1.0.post1.dev0", "1.0.post1", "1.0.post2", "1.0.1", "1.1.dev0", "1.10", "2.0", "1!0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+
+        // spellings of the same version
+        let equal = [
+            ("1.0", "1.0.0"),
+            ("1.0", "1"),
+            ("1.0a1", "1.0alpha1"),
+            ("1.0a1", "1.0-a.1"),
+            ("1.0b2", "1.0beta2"),
+            ("1.0rc1", "1.0c1"),
+            ("1.0rc1", "1.0pre1"),
+            ("1.0.post1", "1.0-1"),
+            ("1.0.post1", "1.0.rev1"),
+            ("1.0.dev0", "1.0dev"),
+            ("v2.2.0", "2.2.0"),
+            ("0!1.0", "1.0"),
+            // local versions are ignored
+            ("1.0+ubuntu1", "1.0"),
+            ("1.0.post1+local.7", "1.0.post1"),
+        ];
+        for (a, b) in equal {
+            assert_eq!(version(a).cmp(&version(b)), Ordering::Equal, "{a} == {b}");
+        }
+
+        for invalid in ["", "abc", "1.0-beta-x", "1..0", "1.0.x"] {
+            assert!(Version::parse(invalid).is_none(), "{invalid:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn specifiers_match_installed_versions() {
+        let cases = [
+            ("==2.2.0", "2.2.0", true),
+            ("==2.2.0", "2.2", true),
+            ("==2.2.0", "2.2.1", false),
+            ("==2.2.0", "2.2.0+local", true),
+            ("!=2.2.0", "2.2.0", false),
+            ("!=2.2.0", "2.2.1", true),
+            ("<2.0", "1.9.9", true),
+            ("<2.0", "2.0", false),
+            ("<=2.0", "2.0", true),
+            (">1.0", "1.0", false),
+            (">1.0", "1.0.post1", true),
+            (">=1.24.85", "1.24.85", true),
+            (">=1.24.85", "1.24.9", false),
+            // ~=2.2 is >=2.2, ==2.*; ~=2.2.0 is >=2.2.0, ==2.2.*
+            ("~=2.2", "2.2", true),
+            ("~=2.2", "2.9.1", true),
+            ("~=2.2", "3.0", false),
+            ("~=2.2", "2.1", false),
+            ("~=2.2.0", "2.2.5", true),
+            ("~=2.2.0", "2.3.0", false),
+            ("~=2.2.0", "2.1.9", false),
+            ("~=1.4.5a4", "1.4.5", true),
+            ("~=1.4.5a4", "1.4.5a3", false),
+            // prefix matching
+            ("==1.24.*", "1.24", true),
+            ("==1.24.*", "1.24.85", true),
+            ("==1.24.*", "1.24.1rc1", true),
+            ("==1.24.*", "1.25.0", false),
+            ("==1.24.*", "1.240", false),
+            ("!=1.24.*", "1.24.3", false),
+            ("!=1.24.*", "1.23.9", true),
+            // === compares the strings
+            ("===1.0", "1.0", true),
+            ("===1.0", "1.0.0", false),
+            ("===foobar", "FooBar", true),
+            // a version that isn't PEP 440 only matches the exact same string
+            ("==1.0", "foobar", false),
+            (">=1.0", "foobar", false),
+        ];
+        for (spec, installed, expected) in cases {
+            let specifier = VersionSpecifier::parse(spec).unwrap_or_else(|e| panic!("{spec}: {e}"));
+            assert_eq!(specifier.matches(installed), expected, "{spec} vs {installed}");
+        }
+
+        for invalid in ["2.2.0", "==", ">=1.*", "~=1.*", "==1.2.*.*", ">=abc"] {
+            assert!(VersionSpecifier::parse(invalid).is_err(), "{invalid:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn requirement_lines() {
+        let r = requirement("emoji==2.2.0");
+        assert_eq!((r.name.as_str(), r.specifiers.len(), r.marker), ("emoji", 1, None));
+
+        let r = requirement("boto3[crt, s3] >=1.24, <2.0 ; python_version >= \"3.8\"");
+        assert_eq!(r.name, "boto3");
+        assert_eq!(r.extras, ["crt", "s3"]);
+        assert_eq!(r.specifiers.iter().map(ToString::to_string).collect::<Vec<_>>(), [">=1.24", "<2.0"]);
+        assert_eq!(r.marker.as_deref(), Some("python_version >= \"3.8\""));
+        assert_eq!(r.to_string(), "boto3[crt,s3]>=1.24,<2.0 ; python_version >= \"3.8\"");
+
+        // parenthesized specifiers, per-requirement options and direct references
+        assert_eq!(requirement("Foo.Bar (>=1.0)").specifiers.len(), 1);
+        assert_eq!(requirement("Foo.Bar (>=1.0)").normalized_name(), "foo-bar");
+        assert_eq!(requirement("emoji==2.2.0 --hash=sha256:abc").specifiers.len(), 1);
+        assert!(requirement("pkg @ https://example.com/pkg-1.0.whl").specifiers.is_empty());
+
+        // lines that don't name a package
+        for line in ["https://example.com/pkg-1.0.whl", "./local/pkg", "/abs/pkg"] {
+            assert_eq!(Requirement::parse(line), Ok(None), "{line}");
+        }
+        for line in [">=1.0", "pkg[extra", "pkg >=", "pkg ?1.0"] {
+            assert!(Requirement::parse(line).is_err(), "{line:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn requirements_files() {
+        let path = requirements_file(
+            "files",
+            &[
+                (
+                    "requirements.txt",
+                    "# pinned for the examples\n\
+                     emoji==2.2.0  # trailing comment\n\
+                     \n\
+                     --index-url https://pypi.org/simple\n\
+                     boto3>=1.24,\\\n    <2.0\n\
+                     -r more.txt\n\
+                     -rinline.txt\n\
+                     --requirement=option.txt\n\
+                     https://example.com/unnamed.whl\n\
+                     colorama ; sys_platform == \"win32\"\n",
+                ),
+                ("more.txt", "requests[socks]~=2.28\n-r requirements.txt\n"),
+                ("inline.txt", "six\n"),
+                ("option.txt", "attrs!=22.1.0\n"),
+            ],
+        );
+        let requirements = parse_requirements(&path).unwrap();
+        let summary: Vec<(String, usize, String)> =
+            requirements.iter().map(|r| (r.file.file_name().unwrap().to_string_lossy().into_owned(), r.line, r.to_string())).collect();
+        let expected = [
+            ("requirements.txt", 2, "emoji==2.2.0"),
+            ("requirements.txt", 5, "boto3>=1.24,<2.0"),
+            ("more.txt", 1, "requests[socks]~=2.28"),
+            ("inline.txt", 1, "six"),
+            ("option.txt", 1, "attrs!=22.1.0"),
+            ("requirements.txt", 11, "colorama ; sys_platform == \"win32\""),
+        ];
+        assert_eq!(summary.len(), expected.len(), "{summary:?}");
+        for (found, (file, line, text)) in summary.iter().zip(expected) {
+            assert_eq!((found.0.as_str(), found.1, found.2.as_str()), (file, line, text));
+        }
+
+        let broken = requirements_file("broken", &[("requirements.txt", "emoji==2.2.0\nboto3 >= \n")]);
+        match parse_requirements(&broken) {
+            Err(Error::InvalidFile { reason, .. }) => assert!(reason.starts_with("line 2:"), "{reason}"),
+            other => panic!("expected Error::InvalidFile, got {other:?}"),
+        }
+        assert!(matches!(parse_requirements(path.with_file_name("missing.txt")), Err(Error::InvalidFile { .. })));
+    }
+
+    #[test]
+    fn report_sorts_packages_into_missing_mismatched_and_extra() {
+        let requirements: Vec<Requirement> =
+            ["emoji==2.2.0", "boto3==1.24.85", "Requests>=2.0", "colorama ; sys_platform == \"win32\""].into_iter().map(requirement).collect();
+        let installed = [
+            package("emoji", "2.1.0", &[]),
+            package("requests", "2.28.1", &["urllib3<1.27,>=1.21.1", "PySocks!=1.5.7,>=1.5.6; extra == \"socks\""]),
+            package("urllib3", "1.26.12", &[]),
+            package("PySocks", "1.7.1", &[]),
+            package("pip", "23.0", &[]),
+            package("setuptools", "65.5.0", &[]),
+            package("numpy", "1.24.2", &[]),
+        ];
+        let report = verify("requirements.txt", &requirements, &installed);
+
+        let names = |requirements: &[Requirement]| requirements.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&report.missing), ["boto3"]);
+        assert_eq!(report.mismatched.iter().map(|m| (m.requirement.name.as_str(), m.installed.version.as_str())).collect::<Vec<_>>(), [("emoji", "2.1.0")]);
+        assert_eq!(report.satisfied.iter().map(|(r, p)| (r.name.as_str(), p.name.as_str())).collect::<Vec<_>>(), [("Requests", "requests")]);
+        assert_eq!(names(&report.skipped), ["colorama"]);
+        // urllib3 is needed by requests, PySocks only by an extra nobody asked for, pip and setuptools are tooling
+        assert_eq!(report.extra.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), ["PySocks", "numpy"]);
+        assert!(!report.is_ok());
+
+        let rendered = report.to_string();
+        assert!(rendered.starts_with("Requirements check for requirements.txt: FAILED"));
+        assert!(rendered.contains("\n    mismatch  emoji==2.2.0 (installed 2.1.0)"), "{rendered}");
+        assert!(rendered.contains("\n    extra     numpy==1.24.2"), "{rendered}");
+
+        let installed = [package("emoji", "2.2.0", &[]), package("boto3", "1.24.85", &[]), package("requests", "2.31.0", &[])];
+        assert!(verify("requirements.txt", &requirements, &installed).is_ok());
+    }
+}
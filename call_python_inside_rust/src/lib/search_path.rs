@@ -0,0 +1,165 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+// name of the directory holding the .py files, next to the executable or inside the crate
+pub const PY_DIR_NAME: &str = "py";
+// environment variable read by SearchPath::default()
+pub const PY_PATH_ENV_VAR: &str = "EXAMPLES_PY_PATH";
+
+// Finds .py files without depending on the current working directory
+// a file name is looked up in these places, in order:
+//  1. every directory listed in the environment variable (same format as $PATH)
+//  2. the configured directories
+//  3. py/ next to the running executable
+//  4. py/ inside the crate directory (CARGO_MANIFEST_DIR at build time)
+// absolute paths are used as they are
+#[derive(Debug, Clone)]
+pub struct SearchPath {
+    env_var: Option<String>,
+    dirs: Vec<PathBuf>,
+    exe_dir: bool,
+    manifest_dir: Option<PathBuf>,
+}
+
+impl SearchPath {
+    // a search path with nothing in it; add locations with the builder methods below
+    pub fn empty() -> SearchPath {
+        SearchPath { env_var: None, dirs: Vec::new(), exe_dir: false, manifest_dir: None }
+    }
+
+    // search path for a binary crate; pass env!("CARGO_MANIFEST_DIR") from that crate
+    pub fn for_crate(env_var: &str, manifest_dir: impl Into<PathBuf>) -> SearchPath {
+        SearchPath::empty().env_var(env_var).exe_dir(true).manifest_dir(manifest_dir)
+    }
+
+    pub fn env_var(mut self, name: &str) -> SearchPath {
+        self.env_var = Some(name.to_owned());
+        self
+    }
+
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> SearchPath {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    pub fn exe_dir(mut self, enabled: bool) -> SearchPath {
+        self.exe_dir = enabled;
+        self
+    }
+
+    pub fn manifest_dir(mut self, dir: impl Into<PathBuf>) -> SearchPath {
+        self.manifest_dir = Some(dir.into());
+        self
+    }
+
+    // every directory that will be searched, in order
+    pub fn dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        if let Some(value) = self.env_var.as_ref().and_then(env::var_os) {
+            dirs.extend(env::split_paths(&value).filter(|dir| !dir.as_os_str().is_empty()));
+        }
+        dirs.extend(self.dirs.iter().cloned());
+        if self.exe_dir {
+            if let Some(exe_dir) = env::current_exe().ok().as_deref().and_then(Path::parent) {
+                dirs.push(exe_dir.join(PY_DIR_NAME));
+            }
+        }
+        if let Some(manifest_dir) = &self.manifest_dir {
+            dirs.push(manifest_dir.join(PY_DIR_NAME));
+        }
+        dirs
+    }
+
+    // the full paths that resolve() checks for `name`
+    pub fn candidates(&self, name: impl AsRef<Path>) -> Vec<PathBuf> {
+        let name = name.as_ref();
+        if name.is_absolute() {
+            return vec![name.to_path_buf()];
+        }
+        self.dirs().into_iter().map(|dir| dir.join(name)).collect()
+    }
+
+    // Return the first candidate that is an existing file
+    // Error::NotFound lists every path that was tried
+    pub fn resolve(&self, name: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        let name = name.as_ref();
+        let tried = self.candidates(name);
+        match tried.iter().find(|path| path.is_file()) {
+            Some(path) => Ok(path.clone()),
+            None => Err(Error::NotFound { name: name.display().to_string(), tried }),
+        }
+    }
+}
+
+// search path used by the examples crate: $EXAMPLES_PY_PATH, then py/ next to the executable,
+// then call_python_inside_rust/py
+impl Default for SearchPath {
+    fn default() -> SearchPath {
+        SearchPath::for_crate(PY_PATH_ENV_VAR, env!("CARGO_MANIFEST_DIR"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn dirs_are_searched_in_order() {
+        // a variable only this test uses, so other tests can't see it change
+        env::set_var("EXAMPLES_TEST_DIRS_ORDER", env::join_paths(["/from/env/a", "", "/from/env/b"]).unwrap());
+        let search_path = SearchPath::empty()
+            .env_var("EXAMPLES_TEST_DIRS_ORDER")
+            .dir("/configured/1")
+            .dir("/configured/2")
+            .exe_dir(true)
+            .manifest_dir("/crate");
+        let exe_py = env::current_exe().unwrap().parent().unwrap().join(PY_DIR_NAME);
+        let expected: Vec<PathBuf> = vec![
+            "/from/env/a".into(),
+            "/from/env/b".into(),
+            "/configured/1".into(),
+            "/configured/2".into(),
+            exe_py,
+            "/crate/py".into(),
+        ];
+        assert_eq!(search_path.dirs(), expected);
+
+        // an unset variable and disabled locations add nothing
+        let search_path = SearchPath::empty().env_var("EXAMPLES_TEST_UNSET_VARIABLE").dir("/only");
+        assert_eq!(search_path.dirs(), [PathBuf::from("/only")]);
+    }
+
+    #[test]
+    fn resolve_returns_the_first_existing_file() {
+        let root = env::temp_dir().join(format!("examples-search-path-{}", std::process::id()));
+        let (first, second) = (root.join("first"), root.join("second"));
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        fs::write(second.join("both.py"), "").unwrap();
+        fs::write(first.join("both.py"), "").unwrap();
+        fs::write(second.join("second_only.py"), "").unwrap();
+        let search_path = SearchPath::empty().dir(&first).dir(&second);
+
+        let cases = [("both.py", first.join("both.py")), ("second_only.py", second.join("second_only.py"))];
+        for (name, expected) in cases {
+            assert_eq!(search_path.resolve(name).unwrap(), expected, "{name}");
+        }
+
+        // absolute paths aren't searched for
+        let absolute = second.join("second_only.py");
+        assert_eq!(search_path.candidates(&absolute), vec![absolute.clone()]);
+        assert_eq!(search_path.resolve(&absolute).unwrap(), absolute);
+
+        match search_path.resolve("missing.py") {
+            Err(Error::NotFound { name, tried }) => {
+                assert_eq!(name, "missing.py");
+                assert_eq!(tried, [first.join("missing.py"), second.join("missing.py")]);
+            }
+            other => panic!("expected Error::NotFound, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+
+use crate::error::Error;
+
+// A type annotation, on the Python side (from a type hint) or the Rust side (from PyAnnotation)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeHint {
+    Int,
+    Float,
+    Str,
+    Bool,
+    Bytes,
+    // the None type, ex. `-> None`
+    None,
+    List(Box<TypeHint>),
+    Dict(Box<TypeHint>, Box<TypeHint>),
+    Tuple(Vec<TypeHint>),
+    Optional(Box<TypeHint>),
+    // no annotation, or one that isn't checked (classes, Callable, Union of several types, ...)
+    Any,
+}
+
+// One parameter of a Python function, as reported by inspect.signature()
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub kind: ParameterKind,
+    pub has_default: bool,
+    pub hint: TypeHint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterKind {
+    PositionalOnly,
+    PositionalOrKeyword,
+    // *args
+    VarPositional,
+    KeywordOnly,
+    // **kwargs
+    VarKeyword,
+}
+
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub parameters: Vec<Parameter>,
+    pub returns: TypeHint,
+}
+
+// The Python type a Rust type corresponds to when it is passed to or returned from Python
+// implement this for your own types to use them with PyFunction and py_bindings!
+pub trait PyAnnotation {
+    fn type_hint() -> TypeHint;
+}
+
+// The argument types of a PyFunction, implemented for tuples of PyAnnotation types
+pub trait PyArgs {
+    fn type_hints() -> Vec<TypeHint>;
+}
+
+impl Signature {
+    // Read the signature and type hints of a Python function
+    // string annotations (`from __future__ import annotations`) are resolved with typing.get_type_hints()
+    pub fn of(py: Python<'_>, function: &PyAny) -> Result<Signature, Error> {
+        let inspect = py.import("inspect")?;
+        let typing = py.import("typing")?;
+        let signature = inspect.call_method1("signature", (function,))?;
+        let empty = inspect.getattr("Parameter")?.getattr("empty")?;
+        // fails for annotations that name things that don't exist; fall back to the raw annotations then
+        let resolved: HashMap<String, &PyAny> = typing
+            .call_method1("get_type_hints", (function,))
+            .and_then(|hints| hints.extract())
+            .unwrap_or_default();
+        let hint_for = |name: &str, annotation: &PyAny| -> PyResult<TypeHint> {
+            let annotation = resolved.get(name).copied().unwrap_or(annotation);
+            if annotation.is(empty) {
+                Ok(TypeHint::Any)
+            } else {
+                TypeHint::from_annotation(py, annotation)
+            }
+        };
+
+        let mut parameters = Vec::new();
+        for parameter in signature.getattr("parameters")?.call_method0("values")?.iter()? {
+            let parameter = parameter?;
+            let name: String = parameter.getattr("name")?.extract()?;
+            let kind = match parameter.getattr("kind")?.getattr("name")?.extract::<&str>()? {
+                "POSITIONAL_ONLY" => ParameterKind::PositionalOnly,
+                "VAR_POSITIONAL" => ParameterKind::VarPositional,
+                "KEYWORD_ONLY" => ParameterKind::KeywordOnly,
+                "VAR_KEYWORD" => ParameterKind::VarKeyword,
+                _ => ParameterKind::PositionalOrKeyword,
+            };
+            let has_default = !parameter.getattr("default")?.is(empty);
+            let hint = hint_for(&name, parameter.getattr("annotation")?)?;
+            parameters.push(Parameter { name, kind, has_default, hint });
+        }
+        let returns = hint_for("return", signature.getattr("return_annotation")?)?;
+        Ok(Signature { parameters, returns })
+    }
+
+    // Compare the signature with a call made from Rust with positional arguments
+    // returns one line per problem: wrong number of arguments, a name that differs (when the Rust
+    // side has names, ex. from py_bindings!) or an annotated type that doesn't match
+    pub fn check_call(&self, arg_names: Option<&[String]>, arg_hints: &[TypeHint], return_hint: &TypeHint) -> Vec<String> {
+        let mut problems = Vec::new();
+        let positional: Vec<&Parameter> = self
+            .parameters
+            .iter()
+            .filter(|p| matches!(p.kind, ParameterKind::PositionalOnly | ParameterKind::PositionalOrKeyword))
+            .collect();
+        let var_positional = self.parameters.iter().find(|p| p.kind == ParameterKind::VarPositional);
+        let required = positional.iter().filter(|p| !p.has_default).count();
+
+        if arg_hints.len() < required {
+            problems.push(format!("takes at least {required} argument(s) but Rust passes {}", arg_hints.len()));
+        } else if arg_hints.len() > positional.len() && var_positional.is_none() {
+            problems.push(format!("takes at most {} argument(s) but Rust passes {}", positional.len(), arg_hints.len()));
+        }
+        for parameter in self.parameters.iter().filter(|p| p.kind == ParameterKind::KeywordOnly && !p.has_default) {
+            problems.push(format!("keyword-only argument `{}` can't be passed from Rust", parameter.name));
+        }
+
+        for (index, rust_hint) in arg_hints.iter().enumerate() {
+            let Some(parameter) = positional.get(index).copied().or(var_positional) else {
+                continue;
+            };
+            if let Some(rust_name) = arg_names.and_then(|names| names.get(index)) {
+                if parameter.kind == ParameterKind::PositionalOrKeyword && rust_name != &parameter.name {
+                    problems.push(format!("argument {} is `{rust_name}` in Rust but `{}` in Python", index + 1, parameter.name));
+                }
+            }
+            if !rust_hint.converts_to(&parameter.hint) {
+                problems.push(format!(
+                    "argument `{}` is annotated {} in Python but Rust passes {}",
+                    parameter.name, parameter.hint, rust_hint
+                ));
+            }
+        }
+
+        if !self.returns.converts_to(return_hint) {
+            problems.push(format!("returns {} in Python but Rust expects {}", self.returns, return_hint));
+        }
+        problems
+    }
+}
+
+impl TypeHint {
+    // Translate a Python annotation object (int, list[str], Optional[int], ...)
+    pub fn from_annotation(py: Python<'_>, annotation: &PyAny) -> PyResult<TypeHint> {
+        let builtins = py.import("builtins")?;
+        let typing = py.import("typing")?;
+        let none_type = py.None().into_ref(py).get_type();
+        if annotation.is_none() || annotation.is(none_type) {
+            return Ok(TypeHint::None);
+        }
+        for (name, hint) in [("int", TypeHint::Int), ("float", TypeHint::Float), ("str", TypeHint::Str), ("bool", TypeHint::Bool), ("bytes", TypeHint::Bytes)] {
+            if annotation.is(builtins.getattr(name)?) {
+                return Ok(hint);
+            }
+        }
+
+        let origin = typing.call_method1("get_origin", (annotation,))?;
+        let args: &PyTuple = typing.call_method1("get_args", (annotation,))?.downcast()?;
+        let arg = |index: usize| -> PyResult<TypeHint> {
+            match args.get_item(index) {
+                Ok(arg) => TypeHint::from_annotation(py, arg),
+                Err(_) => Ok(TypeHint::Any),
+            }
+        };
+        if origin.is_none() {
+            // bare `list` / `dict` / `tuple` without element types
+            for (name, hint) in [
+                ("list", TypeHint::List(Box::new(TypeHint::Any))),
+                ("dict", TypeHint::Dict(Box::new(TypeHint::Any), Box::new(TypeHint::Any))),
+            ] {
+                if annotation.is(builtins.getattr(name)?) {
+                    return Ok(hint);
+                }
+            }
+            return Ok(TypeHint::Any);
+        }
+        if origin.is(builtins.getattr("list")?) {
+            return Ok(TypeHint::List(Box::new(arg(0)?)));
+        }
+        if origin.is(builtins.getattr("dict")?) {
+            return Ok(TypeHint::Dict(Box::new(arg(0)?), Box::new(arg(1)?)));
+        }
+        if origin.is(builtins.getattr("tuple")?) {
+            let elements = (0..args.len()).map(arg).collect::<PyResult<Vec<_>>>()?;
+            // tuple[int, ...] is a tuple of any length
+            let ellipsis = builtins.getattr("Ellipsis")?;
+            let variable_length = args.iter().any(|a| a.is(ellipsis));
+            return Ok(if variable_length { TypeHint::Any } else { TypeHint::Tuple(elements) });
+        }
+        // Optional[T], Union[T, None] and T | None
+        let is_union = origin.is(typing.getattr("Union")?) || origin.is(py.import("types")?.getattr("UnionType")?);
+        if is_union {
+            let members: Vec<&PyAny> = args.iter().filter(|a| !a.is(none_type)).collect();
+            if members.len() == 1 && members.len() < args.len() {
+                return Ok(TypeHint::Optional(Box::new(TypeHint::from_annotation(py, members[0])?)));
+            }
+        }
+        Ok(TypeHint::Any)
+    }
+
+    // Can a value of this type be used where `target` is expected
+    // for arguments self is the Rust type and target the Python annotation; for return values it's
+    // the other way around. Any matches everything, int goes where float is expected (Python's
+    // numeric tower, and pyo3 extracts f64 from an int), and a value that may be None only fits an Optional
+    pub fn converts_to(&self, target: &TypeHint) -> bool {
+        match (self, target) {
+            (TypeHint::Any, _) | (_, TypeHint::Any) => true,
+            (TypeHint::None, TypeHint::None | TypeHint::Optional(_)) => true,
+            (TypeHint::Optional(from), TypeHint::Optional(to)) => from.converts_to(to),
+            (TypeHint::Optional(_), _) => false,
+            (from, TypeHint::Optional(to)) => from.converts_to(to),
+            (TypeHint::Int | TypeHint::Bool, TypeHint::Float) | (TypeHint::Bool, TypeHint::Int) => true,
+            (TypeHint::List(from), TypeHint::List(to)) => from.converts_to(to),
+            (TypeHint::Dict(from_key, from_value), TypeHint::Dict(to_key, to_value)) => {
+                from_key.converts_to(to_key) && from_value.converts_to(to_value)
+            }
+            (TypeHint::Tuple(from), TypeHint::Tuple(to)) => {
+                from.len() == to.len() && from.iter().zip(to).all(|(from, to)| from.converts_to(to))
+            }
+            (from, to) => from == to,
+        }
+    }
+
+    // The Rust type used for this hint by the binding generator, ex. Option<Vec<i64>> for Optional[list[int]]
+    // Any, bytes, None and dicts keyed by something that can't be hashed in Rust fall back to PyObject
+    pub fn rust_type(&self) -> String {
+        match self {
+            TypeHint::Int => "i64".to_owned(),
+            TypeHint::Float => "f64".to_owned(),
+            TypeHint::Str => "String".to_owned(),
+            TypeHint::Bool => "bool".to_owned(),
+            TypeHint::List(item) => format!("Vec<{}>", item.rust_type()),
+            TypeHint::Dict(key, value) if matches!(**key, TypeHint::Int | TypeHint::Str | TypeHint::Bool) => {
+                format!("std::collections::HashMap<{}, {}>", key.rust_type(), value.rust_type())
+            }
+            // PyArgs and PyAnnotation are implemented for tuples of up to 8 items
+            TypeHint::Tuple(items) if (1..=8).contains(&items.len()) => {
+                let items: Vec<String> = items.iter().map(TypeHint::rust_type).collect();
+                if items.len() == 1 {
+                    format!("({},)", items[0])
+                } else {
+                    format!("({})", items.join(", "))
+                }
+            }
+            TypeHint::Optional(inner) => format!("Option<{}>", inner.rust_type()),
+            _ => "pyo3::PyObject".to_owned(),
+        }
+    }
+
+    // The Rust type for a parameter with this hint: rust_type(), except that strings are borrowed
+    // (&str for str, Option<&str> for Optional[str]) so callers don't need .to_owned()
+    pub fn rust_arg_type(&self) -> String {
+        match self {
+            TypeHint::Str => "&str".to_owned(),
+            TypeHint::Optional(inner) if **inner == TypeHint::Str => "Option<&str>".to_owned(),
+            hint => hint.rust_type(),
+        }
+    }
+}
+
+// written the way Python spells it, ex. Optional[list[int]]
+impl fmt::Display for TypeHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeHint::Int => write!(f, "int"),
+            TypeHint::Float => write!(f, "float"),
+            TypeHint::Str => write!(f, "str"),
+            TypeHint::Bool => write!(f, "bool"),
+            TypeHint::Bytes => write!(f, "bytes"),
+            TypeHint::None => write!(f, "None"),
+            TypeHint::List(item) => write!(f, "list[{item}]"),
+            TypeHint::Dict(key, value) => write!(f, "dict[{key}, {value}]"),
+            TypeHint::Tuple(items) => {
+                let items: Vec<String> = items.iter().map(ToString::to_string).collect();
+                write!(f, "tuple[{}]", items.join(", "))
+            }
+            TypeHint::Optional(inner) => write!(f, "Optional[{inner}]"),
+            TypeHint::Any => write!(f, "Any"),
+        }
+    }
+}
+
+macro_rules! annotate {
+    ($hint:expr => $($ty:ty),+) => {
+        $(impl PyAnnotation for $ty {
+            fn type_hint() -> TypeHint {
+                $hint
+            }
+        })+
+    };
+}
+
+annotate!(TypeHint::Int => i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+annotate!(TypeHint::Float => f32, f64);
+annotate!(TypeHint::Str => String, char);
+annotate!(TypeHint::Bool => bool);
+annotate!(TypeHint::Any => PyObject);
+
+impl PyAnnotation for &str {
+    fn type_hint() -> TypeHint {
+        TypeHint::Str
+    }
+}
+
+impl PyAnnotation for () {
+    fn type_hint() -> TypeHint {
+        TypeHint::None
+    }
+}
+
+impl<T: PyAnnotation> PyAnnotation for Option<T> {
+    fn type_hint() -> TypeHint {
+        TypeHint::Optional(Box::new(T::type_hint()))
+    }
+}
+
+impl<T: PyAnnotation> PyAnnotation for Vec<T> {
+    fn type_hint() -> TypeHint {
+        TypeHint::List(Box::new(T::type_hint()))
+    }
+}
+
+impl<T: PyAnnotation> PyAnnotation for &[T] {
+    fn type_hint() -> TypeHint {
+        TypeHint::List(Box::new(T::type_hint()))
+    }
+}
+
+impl<K: PyAnnotation, V: PyAnnotation, S> PyAnnotation for HashMap<K, V, S> {
+    fn type_hint() -> TypeHint {
+        TypeHint::Dict(Box::new(K::type_hint()), Box::new(V::type_hint()))
+    }
+}
+
+impl PyArgs for () {
+    fn type_hints() -> Vec<TypeHint> {
+        Vec::new()
+    }
+}
+
+// tuples are both argument lists (PyArgs) and values (PyAnnotation, as tuple[...])
+macro_rules! tuple_annotations {
+    ($($name:ident),+) => {
+        impl<$($name: PyAnnotation),+> PyArgs for ($($name,)+) {
+            fn type_hints() -> Vec<TypeHint> {
+                vec![$($name::type_hint()),+]
+            }
+        }
+
+        impl<$($name: PyAnnotation),+> PyAnnotation for ($($name,)+) {
+            fn type_hint() -> TypeHint {
+                TypeHint::Tuple(vec![$($name::type_hint()),+])
+            }
+        }
+    };
+}
+
+tuple_annotations!(A);
+tuple_annotations!(A, B);
+tuple_annotations!(A, B, C);
+tuple_annotations!(A, B, C, D);
+tuple_annotations!(A, B, C, D, E);
+tuple_annotations!(A, B, C, D, E, F);
+tuple_annotations!(A, B, C, D, E, F, G);
+tuple_annotations!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyDict;
+
+    use super::*;
+
+    // the annotation written as in a .py file with `from typing import *`
+    fn hint(py: Python<'_>, annotation: &str) -> TypeHint {
+        let globals = PyDict::new(py);
+        py.run("from typing import *", Some(globals), None).unwrap();
+        let annotation = py.eval(annotation, Some(globals), None).unwrap_or_else(|e| panic!("{annotation}: {e}"));
+        TypeHint::from_annotation(py, annotation).unwrap()
+    }
+
+    fn list(item: TypeHint) -> TypeHint {
+        TypeHint::List(Box::new(item))
+    }
+
+    fn optional(inner: TypeHint) -> TypeHint {
+        TypeHint::Optional(Box::new(inner))
+    }
+
+    #[test]
+    fn annotations_parse_into_type_hints() {
+        Python::with_gil(|py| {
+            let cases = [
+                ("int", TypeHint::Int, "int", "i64"),
+                ("float", TypeHint::Float, "float", "f64"),
+                ("str", TypeHint::Str, "str", "String"),
+                ("bool", TypeHint::Bool, "bool", "bool"),
+                ("bytes", TypeHint::Bytes, "bytes", "pyo3::PyObject"),
+                ("None", TypeHint::None, "None", "pyo3::PyObject"),
+                ("type(None)", TypeHint::None, "None", "pyo3::PyObject"),
+                ("list", list(TypeHint::Any), "list[Any]", "Vec<pyo3::PyObject>"),
+                ("list[int]", list(TypeHint::Int), "list[int]", "Vec<i64>"),
+                ("List[str]", list(TypeHint::Str), "list[str]", "Vec<String>"),
+                (
+                    "dict[str, list[float]]",
+                    TypeHint::Dict(Box::new(TypeHint::Str), Box::new(list(TypeHint::Float))),
+                    "dict[str, list[float]]",
+                    "std::collections::HashMap<String, Vec<f64>>",
+                ),
+                // float keys aren't Hash in Rust
+                ("dict[float, int]", TypeHint::Dict(Box::new(TypeHint::Float), Box::new(TypeHint::Int)), "dict[float, int]", "pyo3::PyObject"),
+                ("tuple[int]", TypeHint::Tuple(vec![TypeHint::Int]), "tuple[int]", "(i64,)"),
+                ("tuple[int, str]", TypeHint::Tuple(vec![TypeHint::Int, TypeHint::Str]), "tuple[int, str]", "(i64, String)"),
+                ("tuple[int, ...]", TypeHint::Any, "Any", "pyo3::PyObject"),
+                ("Optional[int]", optional(TypeHint::Int), "Optional[int]", "Option<i64>"),
+                ("Union[None, str]", optional(TypeHint::Str), "Optional[str]", "Option<String>"),
+                ("list[int] | None", optional(list(TypeHint::Int)), "Optional[list[int]]", "Option<Vec<i64>>"),
+                ("Optional[list[Optional[int]]]", optional(list(optional(TypeHint::Int))), "Optional[list[Optional[int]]]", "Option<Vec<Option<i64>>>"),
+                // annotations that aren't checked
+                ("Union[int, str]", TypeHint::Any, "Any", "pyo3::PyObject"),
+                ("Callable[[int], int]", TypeHint::Any, "Any", "pyo3::PyObject"),
+                ("object", TypeHint::Any, "Any", "pyo3::PyObject"),
+            ];
+            for (annotation, expected, display, rust_type) in cases {
+                let parsed = hint(py, annotation);
+                assert_eq!(parsed, expected, "{annotation}");
+                assert_eq!(parsed.to_string(), display, "{annotation}");
+                assert_eq!(parsed.rust_type(), rust_type, "{annotation}");
+            }
+            for (annotation, rust_arg_type) in [("str", "&str"), ("Optional[str]", "Option<&str>"), ("list[str]", "Vec<String>"), ("int", "i64")] {
+                assert_eq!(hint(py, annotation).rust_arg_type(), rust_arg_type, "{annotation}");
+            }
+        });
+    }
+
+    #[test]
+    fn rust_types_convert_to_python_annotations() {
+        let cases = [
+            (i32::type_hint(), TypeHint::Int, true),
+            (i32::type_hint(), TypeHint::Float, true),
+            (bool::type_hint(), TypeHint::Int, true),
+            (f64::type_hint(), TypeHint::Int, false),
+            (<&str>::type_hint(), TypeHint::Str, true),
+            (String::type_hint(), TypeHint::Int, false),
+            (PyObject::type_hint(), TypeHint::Str, true),
+            (i64::type_hint(), TypeHint::Any, true),
+            (i64::type_hint(), optional(TypeHint::Int), true),
+            (Option::<i64>::type_hint(), TypeHint::Int, false),
+            (<()>::type_hint(), optional(TypeHint::Str), true),
+            (Vec::<u8>::type_hint(), list(TypeHint::Float), true),
+            (Vec::<String>::type_hint(), list(TypeHint::Int), false),
+            (<(i64, String)>::type_hint(), TypeHint::Tuple(vec![TypeHint::Int, TypeHint::Str]), true),
+            (<(i64, String)>::type_hint(), TypeHint::Tuple(vec![TypeHint::Int]), false),
+            (HashMap::<String, i64>::type_hint(), TypeHint::Dict(Box::new(TypeHint::Str), Box::new(TypeHint::Float)), true),
+        ];
+        for (from, to, expected) in cases {
+            assert_eq!(from.converts_to(&to), expected, "{from} -> {to}");
+        }
+    }
+
+    #[test]
+    fn check_call_reports_each_problem() {
+        Python::with_gil(|py| {
+            let code = "def f(color: str, n: int = 1, *, size: int) -> Optional[str]: ...\ndef g(*values: float) -> None: ...";
+            let module = PyModule::from_code(py, &format!("from typing import Optional\n{code}"), "check_call.py", "signature_test").unwrap();
+            let f = Signature::of(py, module.getattr("f").unwrap()).unwrap();
+            let g = Signature::of(py, module.getattr("g").unwrap()).unwrap();
+            let names = ["colour".to_owned()];
+
+            assert_eq!(
+                f.check_call(Some(&names), &[TypeHint::Int], &TypeHint::Str),
+                vec![
+                    "keyword-only argument `size` can't be passed from Rust",
+                    "argument 1 is `colour` in Rust but `color` in Python",
+                    "argument `color` is annotated str in Python but Rust passes int",
+                    "returns Optional[str] in Python but Rust expects str",
+                ]
+            );
+            assert_eq!(
+                f.check_call(None, &[], &TypeHint::Any),
+                vec!["takes at least 1 argument(s) but Rust passes 0", "keyword-only argument `size` can't be passed from Rust"]
+            );
+            assert!(g.check_call(None, &[TypeHint::Int, TypeHint::Float, TypeHint::Bool], &TypeHint::None).is_empty());
+            assert_eq!(g.check_call(None, &[TypeHint::Str], &TypeHint::None), vec!["argument `values` is annotated float in Python but Rust passes str"]);
+        });
+    }
+}
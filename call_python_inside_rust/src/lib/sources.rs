@@ -0,0 +1,198 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+use crate::search_path::SearchPath;
+
+// A .py file compiled into the binary with include_str!()
+// use the embed_py! macro to create one so the path is relative to the calling crate
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedSource {
+    pub name: &'static str,
+    pub code: &'static str,
+}
+
+// Embed py/<name> from the crate that calls the macro
+//     const SOURCES: &[EmbeddedSource] = &[examples::embed_py!("polly.py")];
+#[macro_export]
+macro_rules! embed_py {
+    ($name:literal) => {
+        $crate::EmbeddedSource {
+            name: $name,
+            code: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/py/", $name)),
+        }
+    };
+}
+
+// the .py files of this crate, available even when py/ isn't shipped next to the binary
+pub const EMBEDDED_SOURCES: &[EmbeddedSource] = &[embed_py!("functions.py"), embed_py!("functions_venv.py")];
+
+impl EmbeddedSource {
+    // Dotted module name for the file, and whether it is a package's __init__.py
+    //  "functions_venv.py" -> ("functions_venv", false)
+    //  "pkg/__init__.py"   -> ("pkg", true)
+    //  "pkg/sub.py"        -> ("pkg.sub", false)
+    pub fn module_name(&self) -> (String, bool) {
+        let dotted = self.name.trim_end_matches(".py").replace(['/', '\\'], ".");
+        match dotted.strip_suffix(".__init__") {
+            Some(package) => (package.to_owned(), true),
+            None => (dotted, false),
+        }
+    }
+}
+
+// where the code for a module came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceOrigin {
+    Embedded,
+    Disk(PathBuf),
+}
+
+// which copy of a file to use when both exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourcePreference {
+    // use the file on disk if the search path finds one, otherwise the embedded copy (for development)
+    #[default]
+    PreferDisk,
+    // always use the copy compiled into the binary
+    EmbeddedOnly,
+    // never fall back to the embedded copy
+    DiskOnly,
+}
+
+// name, origin and sha256 of a loaded file, without the code itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceInfo {
+    pub name: String,
+    pub origin: SourceOrigin,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PySource {
+    pub info: SourceInfo,
+    pub code: String,
+}
+
+// the file a name resolves to, before it is read
+#[derive(Debug, Clone)]
+pub enum SourceLocation {
+    Embedded(EmbeddedSource),
+    Disk(PathBuf),
+}
+
+// Embedded sources plus a search path for on-disk copies
+#[derive(Debug, Clone)]
+pub struct SourceSet {
+    embedded: Vec<EmbeddedSource>,
+    search_path: SearchPath,
+    preference: SourcePreference,
+}
+
+impl SourceSet {
+    pub fn new(embedded: &[EmbeddedSource], search_path: SearchPath) -> SourceSet {
+        SourceSet { embedded: embedded.to_vec(), search_path, preference: SourcePreference::default() }
+    }
+
+    pub fn with_preference(mut self, preference: SourcePreference) -> SourceSet {
+        self.preference = preference;
+        self
+    }
+
+    pub fn preference(&self) -> SourcePreference {
+        self.preference
+    }
+
+    pub fn search_path(&self) -> &SearchPath {
+        &self.search_path
+    }
+
+    pub fn set_search_path(&mut self, search_path: SearchPath) {
+        self.search_path = search_path;
+    }
+
+    pub fn embedded_sources(&self) -> &[EmbeddedSource] {
+        &self.embedded
+    }
+
+    pub fn embedded(&self, name: &str) -> Option<EmbeddedSource> {
+        self.embedded.iter().find(|source| source.name == name).copied()
+    }
+
+    // Decide which copy of `name` would be used, without reading it
+    pub fn locate(&self, name: impl AsRef<Path>) -> Result<SourceLocation, Error> {
+        let name = name.as_ref();
+        let embedded = name.to_str().and_then(|name| self.embedded(name));
+        match (self.preference, embedded) {
+            (SourcePreference::EmbeddedOnly, Some(source)) => Ok(SourceLocation::Embedded(source)),
+            (SourcePreference::EmbeddedOnly, None) => Err(Error::NotFound {
+                name: name.display().to_string(),
+                tried: vec![PathBuf::from("<embedded>").join(name)],
+            }),
+            (SourcePreference::DiskOnly, _) => self.search_path.resolve(name).map(SourceLocation::Disk),
+            (SourcePreference::PreferDisk, embedded) => match (self.search_path.resolve(name), embedded) {
+                (Ok(path), _) => Ok(SourceLocation::Disk(path)),
+                (Err(_), Some(source)) => Ok(SourceLocation::Embedded(source)),
+                (Err(e), None) => Err(e),
+            },
+        }
+    }
+
+    // Find and read `name`
+    pub fn load(&self, name: impl AsRef<Path>) -> Result<PySource, Error> {
+        self.locate(name)?.read()
+    }
+}
+
+// the examples crate's own files, searched with SearchPath::default()
+impl Default for SourceSet {
+    fn default() -> SourceSet {
+        SourceSet::new(EMBEDDED_SOURCES, SearchPath::default())
+    }
+}
+
+impl SourceLocation {
+    pub fn read(&self) -> Result<PySource, Error> {
+        let (name, origin, code) = match self {
+            SourceLocation::Embedded(source) => (source.name.to_owned(), SourceOrigin::Embedded, source.code.to_owned()),
+            SourceLocation::Disk(path) => {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                (name, SourceOrigin::Disk(path.clone()), crate::get_py_file_contents(path)?)
+            }
+        };
+        let sha256 = sha256_hex(&code);
+        Ok(PySource { info: SourceInfo { name, origin, sha256 }, code })
+    }
+}
+
+impl SourceInfo {
+    // file name handed to Python (__file__ and tracebacks)
+    // embedded files use their bare name so __file__ still points somewhere sensible (the working directory)
+    pub fn file_name(&self) -> String {
+        match &self.origin {
+            SourceOrigin::Embedded => self.name.clone(),
+            SourceOrigin::Disk(path) => path.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+impl PySource {
+    pub fn file_name(&self) -> String {
+        self.info.file_name()
+    }
+}
+
+impl fmt::Display for SourceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.origin {
+            SourceOrigin::Embedded => write!(f, "{} (embedded, sha256 {})", self.name, &self.sha256[..12]),
+            SourceOrigin::Disk(path) => write!(f, "{} (disk: {}, sha256 {})", self.name, path.display(), &self.sha256[..12]),
+        }
+    }
+}
+
+fn sha256_hex(code: &str) -> String {
+    Sha256::digest(code.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
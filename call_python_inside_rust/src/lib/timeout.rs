@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::os::raw::c_long;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Once, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pyo3::exceptions::PyBaseException;
+use pyo3::once_cell::GILOnceCell;
+use pyo3::prelude::*;
+use pyo3::types::PyType;
+use pyo3::{ffi, AsPyPointer, PyTypeInfo};
+
+use crate::error::Error;
+
+// examples.CallTimeout, raised inside Python code that ran past its deadline
+// derives from BaseException so `except Exception:` in the Python code doesn't swallow it
+fn call_timeout(py: Python<'_>) -> PyResult<&PyType> {
+    static CALL_TIMEOUT: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+    let class = CALL_TIMEOUT.get_or_try_init(py, || {
+        PyErr::new_type(py, "examples.CallTimeout", Some("the call from Rust ran past its deadline"), Some(PyBaseException::type_object(py)), None)
+    })?;
+    Ok(class.as_ref(py))
+}
+
+// applies to every PyFunction (and py_bindings! function) without a timeout of its own
+static DEFAULT_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CallState {
+    Running,
+    // the exception was injected, it may not have been raised yet
+    Interrupted,
+    Finished,
+}
+
+// Python code running on one thread that other threads can stop by raising `exception` inside it
+// (PyThreadState_SetAsyncExc); used for timeouts here and for dropped futures in async_python.rs
+#[derive(Clone)]
+pub(crate) struct Interrupter {
+    thread_id: c_long,
+    exception: Py<PyType>,
+    state: Arc<Mutex<CallState>>,
+}
+
+impl Interrupter {
+    // for the code about to run on the calling thread
+    pub(crate) fn for_current_thread(py: Python<'_>, exception: Py<PyType>) -> PyResult<Interrupter> {
+        let ident: u64 = py.import("threading")?.call_method0("get_ident")?.extract()?;
+        Ok(Interrupter { thread_id: ident as c_long, exception, state: Arc::new(Mutex::new(CallState::Running)) })
+    }
+
+    // Raise the exception in the running code; does nothing once finish() was called
+    // the GIL is always taken before the lock, here and in finish(), so the two threads can't deadlock
+    pub(crate) fn interrupt(&self) {
+        Python::with_gil(|_py| {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            if *state == CallState::Running {
+                unsafe { ffi::PyThreadState_SetAsyncExc(self.thread_id, self.exception.as_ptr()) };
+                *state = CallState::Interrupted;
+            }
+        })
+    }
+
+    // Call on the running thread once the code returned; true when it was interrupted
+    pub(crate) fn finish(&self) -> bool {
+        Python::with_gil(|_py| {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            let interrupted = *state == CallState::Interrupted;
+            if interrupted {
+                // the code may have returned before Python raised the exception; it must not go off later in unrelated code
+                unsafe { ffi::PyThreadState_SetAsyncExc(self.thread_id, ptr::null_mut()) };
+            }
+            *state = CallState::Finished;
+            interrupted
+        })
+    }
+}
+
+// Set the timeout used by calls that don't have their own, None for no limit (the default)
+pub fn set_default_timeout(timeout: Option<Duration>) {
+    *DEFAULT_TIMEOUT.lock().unwrap_or_else(PoisonError::into_inner) = timeout;
+}
+
+pub fn default_timeout() -> Option<Duration> {
+    *DEFAULT_TIMEOUT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// The deadlines of the calls running under run_with_timeout, earliest first
+// one thread, started by the first call, sleeps until the next deadline and interrupts that call
+struct Watchdog {
+    deadlines: Mutex<BTreeMap<(Instant, u64), Interrupter>>,
+    changed: Condvar,
+}
+
+static WATCHDOG: Watchdog = Watchdog { deadlines: Mutex::new(BTreeMap::new()), changed: Condvar::new() };
+
+impl Watchdog {
+    // returns the key to pass to unwatch() once the call is over
+    fn watch(&'static self, deadline: Instant, interrupter: Interrupter) -> (Instant, u64) {
+        static STARTED: Once = Once::new();
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        STARTED.call_once(|| {
+            thread::Builder::new().name("examples-timeout".to_owned()).spawn(|| self.run()).expect("could not start the timeout thread");
+        });
+        let key = (deadline, NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        self.lock().insert(key, interrupter);
+        // it may have to wake up earlier than it planned to
+        self.changed.notify_one();
+        key
+    }
+
+    fn unwatch(&self, key: (Instant, u64)) {
+        self.lock().remove(&key);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<(Instant, u64), Interrupter>> {
+        self.deadlines.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn run(&self) {
+        let mut deadlines = self.lock();
+        loop {
+            let now = Instant::now();
+            deadlines = match deadlines.keys().next().copied() {
+                None => self.changed.wait(deadlines).unwrap_or_else(PoisonError::into_inner),
+                Some(key) if key.0 <= now => {
+                    let interrupter = deadlines.remove(&key);
+                    // interrupt() waits for the GIL, and whoever holds it may be calling watch() or unwatch()
+                    drop(deadlines);
+                    if let Some(interrupter) = interrupter {
+                        interrupter.interrupt();
+                    }
+                    self.lock()
+                }
+                Some((deadline, _)) => self.changed.wait_timeout(deadlines, deadline - now).unwrap_or_else(PoisonError::into_inner).0,
+            };
+        }
+    }
+}
+
+// Run `f`, interrupting the Python code it runs on this thread once `timeout` has passed
+// the watchdog thread injects examples.CallTimeout into this thread with PyThreadState_SetAsyncExc, and the
+// result becomes Error::Timeout naming `label`
+// Python only notices the exception between bytecodes, so a call blocked inside C code (time.sleep,
+// a socket read without its own timeout) is interrupted when that C call returns, not before; give such
+// calls a timeout of their own as well (see polly_demo), or run them in a WorkerPool, whose workers are killed
+pub fn run_with_timeout<T>(timeout: Duration, label: &str, f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    let interrupter = Python::with_gil(|py| Interrupter::for_current_thread(py, call_timeout(py)?.into()))?;
+    let key = WATCHDOG.watch(Instant::now() + timeout, interrupter.clone());
+
+    let result = f();
+    // interrupt() does nothing after finish(), so the watchdog can't hit later code even if it is about to fire
+    let expired = interrupter.finish();
+    WATCHDOG.unwatch(key);
+
+    match result {
+        // whatever the Python code did with the exception (ex. a bare `except:` raising something else),
+        // the call failed because it ran out of time
+        Err(_) if expired => Err(Error::Timeout { function: label.to_owned(), after: timeout }),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::function::PyFunction;
+    use crate::loader::module_from_code;
+
+    const CODE: &str = "def spin():\n    while True:\n        pass\n\ndef nap(seconds: float) -> float:\n    import time\n    time.sleep(seconds)\n    return seconds\n";
+
+    #[test]
+    fn busy_loops_are_interrupted() {
+        let started = Instant::now();
+        let result = run_with_timeout(Duration::from_millis(200), "spin", || {
+            Python::with_gil(|py| Ok(py.run("while True:\n    pass\n", None, None)?))
+        });
+        match result {
+            Err(Error::Timeout { function, after }) => assert_eq!((function.as_str(), after), ("spin", Duration::from_millis(200))),
+            other => panic!("expected Error::Timeout, got {other:?}"),
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // calls that finish in time are left alone, and so is the code that runs after them
+        assert_eq!(run_with_timeout(Duration::from_millis(100), "quick", || Ok(1)).unwrap(), 1);
+        thread::sleep(Duration::from_millis(200));
+        Python::with_gil(|py| py.run("total = sum(range(100000))", None, None)).unwrap();
+    }
+
+    // the watchdog serves overlapping calls with different deadlines
+    #[test]
+    fn each_call_gets_its_own_deadline() {
+        let spin = |millis| thread::spawn(move || run_with_timeout(Duration::from_millis(millis), "spin", || {
+            Python::with_gil(|py| Ok(py.run("while True:\n    pass\n", None, None)?))
+        }));
+        let (long, short) = (spin(600), spin(150));
+        assert!(matches!(short.join().unwrap(), Err(Error::Timeout { after, .. }) if after == Duration::from_millis(150)));
+        assert!(matches!(long.join().unwrap(), Err(Error::Timeout { after, .. }) if after == Duration::from_millis(600)));
+    }
+
+    #[test]
+    fn per_function_timeouts_win_over_the_default() {
+        Python::with_gil(|py| {
+            let module: Py<PyModule> = module_from_code(py, CODE, "timeout_test.py", "timeout_test").unwrap().into();
+            // long enough not to cut short the other tests running meanwhile
+            set_default_timeout(Some(Duration::from_secs(30)));
+            let spin = PyFunction::<(), PyObject>::from_module(module.clone_ref(py), "spin").with_timeout(Duration::from_millis(200));
+            let result = spin.call_with_gil(py, ());
+            let nap = PyFunction::<(f64,), f64>::from_module(module, "nap").with_timeout(Duration::from_secs(5));
+            let napped = nap.call_with_gil(py, (0.05,));
+            set_default_timeout(None);
+
+            assert!(matches!(result, Err(Error::Timeout { after, .. }) if after == Duration::from_millis(200)), "{result:?}");
+            assert_eq!(napped.unwrap(), 0.05);
+        });
+    }
+}
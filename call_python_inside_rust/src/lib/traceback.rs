@@ -0,0 +1,318 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use pyo3::exceptions::PySyntaxError;
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use pyo3::AsPyPointer;
+
+// Structured copy of a Python traceback, taken from a PyErr while the GIL is held
+// exceptions are ordered the way Python prints them: the root cause first and the exception
+// that was actually raised to Rust last
+#[derive(Debug, Clone, Default)]
+pub struct Traceback {
+    pub exceptions: Vec<ExceptionReport>,
+    // a few lines of source around the line that failed, if the file could be found
+    pub failing_source: Option<SourceContext>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExceptionReport {
+    pub type_name: String,
+    pub message: String,
+    pub frames: Vec<Frame>,
+    // how this exception led to the next one in the list (None for the last one)
+    pub leads_to: Option<ChainRelation>,
+}
+
+// mirrors the two ways Python links exceptions together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainRelation {
+    // `raise B from A` -> A.__cause__ is set on B
+    Cause,
+    // an exception raised while handling another one -> A.__context__ is set on B
+    Context,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub file: String,
+    pub line: usize,
+    // 1-based column, only known for syntax errors
+    pub column: Option<usize>,
+    pub function: String,
+    pub source_line: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceContext {
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub lines: Vec<(usize, String)>,
+}
+
+// number of lines shown above and below the failing line
+const CONTEXT_LINES: usize = 2;
+// stop following __cause__/__context__ after this many exceptions
+const MAX_CHAIN: usize = 32;
+
+impl Traceback {
+    // Collect the traceback of `err` and every exception chained to it
+    // this never fails; anything that can't be read from Python is left out
+    pub fn from_pyerr(py: Python<'_>, err: &PyErr) -> Traceback {
+        let mut exceptions = Vec::new();
+        let mut seen = HashSet::new();
+        let mut current: Option<(&PyAny, Option<ChainRelation>)> = Some((err.value(py).as_ref(), None));
+
+        // walk from the raised exception back towards the root cause, then reverse
+        // the raised exception's traceback is kept on the PyErr rather than on __traceback__
+        let mut tb: Option<&PyAny> = err.traceback(py).map(|tb| tb.as_ref());
+        while let Some((value, leads_to)) = current {
+            if exceptions.len() == MAX_CHAIN || !seen.insert(value.as_ptr() as usize) {
+                break;
+            }
+            exceptions.push(exception_report(py, value, tb.take(), leads_to));
+            current = chained_exception(value);
+        }
+        exceptions.reverse();
+
+        let failing_source = exceptions
+            .last()
+            .and_then(|e| e.frames.last())
+            .and_then(|frame| source_context(py, frame));
+
+        Traceback { exceptions, failing_source }
+    }
+
+    // the frame where the raised exception happened
+    pub fn failing_frame(&self) -> Option<&Frame> {
+        self.exceptions.last().and_then(|e| e.frames.last())
+    }
+
+    // Render the traceback the way Python would print it, followed by the failing lines of the file
+    pub fn report(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Traceback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for exception in &self.exceptions {
+            if !exception.frames.is_empty() {
+                writeln!(f, "Traceback (most recent call last):")?;
+            }
+            for frame in &exception.frames {
+                writeln!(f, "  File \"{}\", line {}, in {}", frame.file, frame.line, frame.function)?;
+                if let Some(source) = &frame.source_line {
+                    writeln!(f, "    {}", source.trim())?;
+                }
+            }
+            if exception.message.is_empty() {
+                writeln!(f, "{}", exception.type_name)?;
+            } else {
+                writeln!(f, "{}: {}", exception.type_name, exception.message)?;
+            }
+            match exception.leads_to {
+                Some(ChainRelation::Cause) => {
+                    writeln!(f, "\nThe above exception was the direct cause of the following exception:\n")?
+                }
+                Some(ChainRelation::Context) => {
+                    writeln!(f, "\nDuring handling of the above exception, another exception occurred:\n")?
+                }
+                None => {}
+            }
+        }
+
+        if let Some(context) = &self.failing_source {
+            writeln!(f, "\n{}:{}", context.file, context.line)?;
+            let width = context.lines.last().map(|(n, _)| n.to_string().len()).unwrap_or(1);
+            for (n, text) in &context.lines {
+                let marker = if *n == context.line { "-->" } else { "   " };
+                writeln!(f, "{marker} {n:>width$} | {}", text.trim_end())?;
+                if *n == context.line {
+                    if let Some(column) = context.column {
+                        let indent = " ".repeat(column.saturating_sub(1));
+                        writeln!(f, "    {:>width$} | {indent}^", "")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Make source code that was passed to PyModule::from_code() visible to Python's linecache
+// without this, tracebacks for code that isn't read straight from disk have no source lines
+pub fn register_source(py: Python<'_>, file_name: &str, code: &str) -> PyResult<()> {
+    let lines = PyList::new(py, code.split_inclusive('\n').collect::<Vec<_>>());
+    let entry = (code.len(), py.None(), lines, file_name);
+    py.import("linecache")?.getattr("cache")?.set_item(file_name, entry)
+}
+
+fn exception_report(py: Python<'_>, value: &PyAny, tb: Option<&PyAny>, leads_to: Option<ChainRelation>) -> ExceptionReport {
+    let type_name = value
+        .get_type()
+        .name()
+        .map(str::to_owned)
+        .unwrap_or_else(|_| "<unknown>".to_owned());
+    let message = value.str().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut frames = extract_frames(py, value, tb).unwrap_or_default();
+
+    // a SyntaxError happens before any frame of the file runs, so its location lives on the exception
+    if value.is_instance_of::<PySyntaxError>().unwrap_or(false) {
+        if let Some(frame) = syntax_error_frame(value) {
+            frames.push(frame);
+        }
+    }
+
+    ExceptionReport { type_name, message, frames, leads_to }
+}
+
+fn extract_frames(py: Python<'_>, value: &PyAny, tb: Option<&PyAny>) -> PyResult<Vec<Frame>> {
+    let tb = match tb {
+        Some(tb) => tb,
+        None => value.getattr("__traceback__")?,
+    };
+    if tb.is_none() {
+        return Ok(Vec::new());
+    }
+    let summaries = py.import("traceback")?.call_method1("extract_tb", (tb,))?;
+    let mut frames = Vec::new();
+    for summary in summaries.iter()? {
+        let summary = summary?;
+        let source_line: Option<String> = summary.getattr("line")?.extract()?;
+        frames.push(Frame {
+            file: summary.getattr("filename")?.extract()?,
+            line: summary.getattr("lineno")?.extract::<Option<usize>>()?.unwrap_or(0),
+            column: None,
+            function: summary.getattr("name")?.extract()?,
+            source_line: source_line.filter(|s| !s.is_empty()),
+        });
+    }
+    Ok(frames)
+}
+
+fn syntax_error_frame(value: &PyAny) -> Option<Frame> {
+    let file: Option<String> = value.getattr("filename").ok()?.extract().ok()?;
+    let line: Option<usize> = value.getattr("lineno").ok()?.extract().ok()?;
+    let column: Option<usize> = value.getattr("offset").ok()?.extract().ok().flatten();
+    let text: Option<String> = value.getattr("text").ok()?.extract().ok().flatten();
+    Some(Frame {
+        file: file?,
+        line: line?,
+        column,
+        function: "<module>".to_owned(),
+        source_line: text.map(|t| t.trim_end().to_owned()),
+    })
+}
+
+// follow __cause__ first, then __context__ unless Python was told to hide it (`raise ... from None`)
+fn chained_exception(value: &PyAny) -> Option<(&PyAny, Option<ChainRelation>)> {
+    let cause = value.getattr("__cause__").ok().filter(|c| !c.is_none());
+    if let Some(cause) = cause {
+        return Some((cause, Some(ChainRelation::Cause)));
+    }
+    let suppressed = value
+        .getattr("__suppress_context__")
+        .and_then(|s| s.is_true())
+        .unwrap_or(false);
+    if suppressed {
+        return None;
+    }
+    value
+        .getattr("__context__")
+        .ok()
+        .filter(|c| !c.is_none())
+        .map(|c| (c, Some(ChainRelation::Context)))
+}
+
+fn source_context(py: Python<'_>, frame: &Frame) -> Option<SourceContext> {
+    let linecache = py.import("linecache").ok()?;
+    let first = frame.line.saturating_sub(CONTEXT_LINES).max(1);
+    let mut lines = Vec::new();
+    for n in first..=frame.line + CONTEXT_LINES {
+        let text: String = linecache.call_method1("getline", (frame.file.as_str(), n)).ok()?.extract().ok()?;
+        // getline() returns "" past the end of the file (or if the file can't be found)
+        if text.is_empty() {
+            break;
+        }
+        lines.push((n, text));
+    }
+    if !lines.iter().any(|(n, _)| *n == frame.line) {
+        return None;
+    }
+    Some(SourceContext { file: frame.file.clone(), line: frame.line, column: frame.column, lines })
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyModule;
+
+    use super::*;
+
+    // the traceback of what `code` raises, with `code` registered as `file_name`
+    fn traceback(file_name: &str, code: &str) -> Traceback {
+        Python::with_gil(|py| {
+            register_source(py, file_name, code).unwrap();
+            let err = PyModule::from_code(py, code, file_name, "traceback_test").expect_err(code);
+            Traceback::from_pyerr(py, &err)
+        })
+    }
+
+    // each exception's type and how it leads to the next one
+    type Chain<'a> = Vec<(&'a str, Option<ChainRelation>)>;
+
+    fn summary(traceback: &Traceback) -> Chain<'_> {
+        traceback.exceptions.iter().map(|e| (e.type_name.as_str(), e.leads_to)).collect()
+    }
+
+    #[test]
+    fn chains_are_ordered_root_cause_first() {
+        let cases: &[(&str, Chain, &str)] = &[
+            ("raise ValueError('plain')", vec![("ValueError", None)], ""),
+            (
+                "try:\n    {}['key']\nexcept KeyError as e:\n    raise ValueError('bad') from e",
+                vec![("KeyError", Some(ChainRelation::Cause)), ("ValueError", None)],
+                "The above exception was the direct cause of the following exception:",
+            ),
+            (
+                "try:\n    1 / 0\nexcept ZeroDivisionError:\n    undefined_name",
+                vec![("ZeroDivisionError", Some(ChainRelation::Context)), ("NameError", None)],
+                "During handling of the above exception, another exception occurred:",
+            ),
+            // `from None` hides the exception being handled
+            ("try:\n    1 / 0\nexcept ZeroDivisionError:\n    raise ValueError('hidden') from None", vec![("ValueError", None)], ""),
+        ];
+        for (code, expected, separator) in cases {
+            let traceback = traceback("chain.py", code);
+            assert_eq!(&summary(&traceback), expected, "{code:?}");
+            assert!(traceback.to_string().contains(separator), "{code:?}:\n{traceback}");
+        }
+    }
+
+    #[test]
+    fn frames_and_source_context() {
+        let code = "def inner():\n    raise ValueError('bad color')\n\ndef outer():\n    inner()\n\nouter()\n";
+        let traceback = traceback("frames.py", code);
+        let frames: Vec<(&str, usize)> = traceback.exceptions[0].frames.iter().map(|f| (f.function.as_str(), f.line)).collect();
+        assert_eq!(frames, [("<module>", 7), ("outer", 5), ("inner", 2)]);
+        assert_eq!(traceback.failing_frame().and_then(|f| f.source_line.as_deref()), Some("raise ValueError('bad color')"));
+
+        let context = traceback.failing_source.as_ref().expect("registered source");
+        assert_eq!(context.lines.iter().map(|(n, _)| *n).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        let rendered = traceback.to_string();
+        assert!(rendered.starts_with("Traceback (most recent call last):\n  File \"frames.py\", line 7, in <module>\n    outer()\n"));
+        assert!(rendered.contains("ValueError: bad color\n\nframes.py:2\n"), "{rendered}");
+        assert!(rendered.contains("--> 2 |     raise ValueError('bad color')"), "{rendered}");
+    }
+
+    #[test]
+    fn syntax_errors_point_at_the_column() {
+        let traceback = traceback("syntax.py", "x = 1\ny = (2 +\n");
+        let frame = traceback.failing_frame().expect("a frame from the SyntaxError");
+        assert_eq!((frame.file.as_str(), frame.function.as_str(), frame.line), ("syntax.py", "<module>", 2));
+        assert_eq!(frame.column, Some(5));
+        assert!(traceback.to_string().ends_with("--> 2 | y = (2 +\n      |     ^\n"), "{traceback}");
+    }
+}
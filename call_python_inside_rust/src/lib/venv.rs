@@ -0,0 +1,372 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, TryLockError};
+
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+
+use crate::error::Error;
+
+// name of the virtualenv directory created by `cargo run -- bootstrap` (see bootstrap.rs), next to the executable or inside the crate
+pub const VENV_DIR_NAME: &str = "env";
+// set by `source env/bin/activate`
+pub const VIRTUAL_ENV_VAR: &str = "VIRTUAL_ENV";
+
+// the environment activated by activate(), shared by every caller in the process
+static ACTIVE_ENVIRONMENT: OnceLock<ActiveEnvironment> = OnceLock::new();
+// held for the whole of activate(), so sys.path is only changed once even when Python switches threads
+// in the middle of it
+static ACTIVATING: Mutex<()> = Mutex::new(());
+
+// how a virtualenv was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VenvOrigin {
+    // VenvLocator::path()
+    Configured,
+    // $VIRTUAL_ENV (the shell ran `source env/bin/activate`)
+    VirtualEnvVar,
+    // env/ next to the executable or inside the crate
+    ProjectDir,
+}
+
+// A virtualenv on disk, checked against the embedded interpreter
+#[derive(Debug, Clone)]
+pub struct VirtualEnv {
+    pub root: PathBuf,
+    pub origin: VenvOrigin,
+    pub site_packages: PathBuf,
+    // python version the venv was created with (from pyvenv.cfg)
+    pub version: Option<String>,
+    // include-system-site-packages from pyvenv.cfg
+    pub include_system_site_packages: bool,
+}
+
+// Finds the virtualenv the embedded interpreter should use, in this order:
+//  1. the configured path
+//  2. $VIRTUAL_ENV
+//  3. env/ next to the running executable
+//  4. env/ inside the crate directory (CARGO_MANIFEST_DIR at build time)
+// the configured path and $VIRTUAL_ENV must be valid venvs; the project directories are skipped
+// when they don't exist
+#[derive(Debug, Clone)]
+pub struct VenvLocator {
+    configured: Option<PathBuf>,
+    use_env_var: bool,
+    exe_dir: bool,
+    manifest_dir: Option<PathBuf>,
+}
+
+// which interpreter environment the embedded Python is using
+#[derive(Debug, Clone)]
+pub struct ActiveEnvironment {
+    // None when no venv was found and the system interpreter's packages are used
+    pub venv: Option<VirtualEnv>,
+    pub prefix: String,
+    pub base_prefix: String,
+    pub python_version: String,
+    // the site-packages directories on sys.path
+    pub site_packages: Vec<String>,
+}
+
+impl VenvLocator {
+    // a locator that finds nothing; add locations with the builder methods below
+    pub fn empty() -> VenvLocator {
+        VenvLocator { configured: None, use_env_var: false, exe_dir: false, manifest_dir: None }
+    }
+
+    // locator for a binary crate; pass env!("CARGO_MANIFEST_DIR") from that crate
+    pub fn for_crate(manifest_dir: impl Into<PathBuf>) -> VenvLocator {
+        VenvLocator::empty().use_env_var(true).exe_dir(true).manifest_dir(manifest_dir)
+    }
+
+    pub fn path(mut self, root: impl Into<PathBuf>) -> VenvLocator {
+        self.configured = Some(root.into());
+        self
+    }
+
+    pub fn use_env_var(mut self, enabled: bool) -> VenvLocator {
+        self.use_env_var = enabled;
+        self
+    }
+
+    pub fn exe_dir(mut self, enabled: bool) -> VenvLocator {
+        self.exe_dir = enabled;
+        self
+    }
+
+    pub fn manifest_dir(mut self, dir: impl Into<PathBuf>) -> VenvLocator {
+        self.manifest_dir = Some(dir.into());
+        self
+    }
+
+    // Find the venv, checking that it matches the running interpreter's version
+    // Ok(None) means there is no venv to activate
+    pub fn locate(&self, py: Python<'_>) -> Result<Option<VirtualEnv>, Error> {
+        if let Some(root) = &self.configured {
+            return VirtualEnv::open(py, root, VenvOrigin::Configured).map(Some);
+        }
+        if let Some(root) = self.use_env_var.then(|| env::var_os(VIRTUAL_ENV_VAR)).flatten() {
+            return VirtualEnv::open(py, Path::new(&root), VenvOrigin::VirtualEnvVar).map(Some);
+        }
+        let mut project_dirs = Vec::new();
+        if self.exe_dir {
+            if let Some(exe_dir) = env::current_exe().ok().as_deref().and_then(Path::parent) {
+                project_dirs.push(exe_dir.join(VENV_DIR_NAME));
+            }
+        }
+        if let Some(manifest_dir) = &self.manifest_dir {
+            project_dirs.push(manifest_dir.join(VENV_DIR_NAME));
+        }
+        match project_dirs.iter().find(|root| root.join("pyvenv.cfg").is_file()) {
+            Some(root) => VirtualEnv::open(py, root, VenvOrigin::ProjectDir).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+// venv of the examples crate: $VIRTUAL_ENV, then env/ next to the executable, then call_python_inside_rust/env
+impl Default for VenvLocator {
+    fn default() -> VenvLocator {
+        VenvLocator::for_crate(env!("CARGO_MANIFEST_DIR"))
+    }
+}
+
+impl VirtualEnv {
+    // Read pyvenv.cfg and find site-packages for the running interpreter's version
+    pub fn open(py: Python<'_>, root: &Path, origin: VenvOrigin) -> Result<VirtualEnv, Error> {
+        let config_path = root.join("pyvenv.cfg");
+        let config = fs::read_to_string(&config_path)
+            .map_err(|e| Error::invalid_venv(root, format!("could not read {}: {e}", config_path.display())))?;
+        let setting = |key: &str| {
+            config
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .find(|(k, _)| k.trim() == key)
+                .map(|(_, v)| v.trim().to_owned())
+        };
+        let version = setting("version").or_else(|| setting("version_info"));
+        let include_system_site_packages = setting("include-system-site-packages").is_some_and(|v| v == "true");
+
+        // packages with compiled extensions only work with the python they were installed for
+        let version_info = py.import("sys")?.getattr("version_info")?;
+        let major: u8 = version_info.getattr("major")?.extract()?;
+        let minor: u8 = version_info.getattr("minor")?.extract()?;
+        let interpreter = format!("{major}.{minor}");
+        if let Some(version) = &version {
+            if !(version == &interpreter || version.starts_with(&format!("{interpreter}."))) {
+                return Err(Error::invalid_venv(
+                    root,
+                    format!("created with python {version} but the embedded interpreter is python {interpreter}"),
+                ));
+            }
+        }
+
+        let site_packages = if cfg!(windows) {
+            root.join("Lib").join("site-packages")
+        } else {
+            root.join("lib").join(format!("python{interpreter}")).join("site-packages")
+        };
+        if !site_packages.is_dir() {
+            return Err(Error::invalid_venv(root, format!("{} does not exist", site_packages.display())));
+        }
+
+        Ok(VirtualEnv { root: root.to_path_buf(), origin, site_packages, version, include_system_site_packages })
+    }
+
+    // Point the running interpreter at this venv, the same way starting env/bin/python would:
+    //  - sys.prefix and sys.exec_prefix are the venv root
+    //  - the venv's site-packages (and its .pth files) come before the system site-packages,
+    //    which are removed unless pyvenv.cfg says to include them
+    //  - $VIRTUAL_ENV and $PATH are set so subprocesses (pip, python3) use the venv too
+    fn activate_in(&self, py: Python<'_>) -> Result<(), Error> {
+        let sys = py.import("sys")?;
+        let site = py.import("site")?;
+        // getsitepackages() is based on sys.prefix, so ask before changing it
+        let mut system_site_packages: Vec<String> = site.call_method0("getsitepackages")?.extract()?;
+        if let Ok(user_site) = site.call_method0("getusersitepackages")?.extract::<String>() {
+            system_site_packages.push(user_site);
+        }
+
+        let root = self.root.to_string_lossy();
+        sys.setattr("prefix", root.as_ref())?;
+        sys.setattr("exec_prefix", root.as_ref())?;
+
+        let before: Vec<String> = sys.getattr("path")?.extract()?;
+        site.call_method1("addsitedir", (self.site_packages.to_string_lossy().as_ref(),))?;
+        let after: Vec<String> = sys.getattr("path")?.extract()?;
+        // site-packages is appended, but .pth files can insert their directories anywhere
+        let added: Vec<String> = after.into_iter().filter(|dir| !before.contains(dir)).collect();
+
+        let is_system_site = |dir: &String| system_site_packages.contains(dir);
+        let mut path: Vec<String> = before
+            .into_iter()
+            .filter(|dir| self.include_system_site_packages || !is_system_site(dir))
+            .collect();
+        let insert_at = path.iter().position(is_system_site).unwrap_or(path.len());
+        path.splice(insert_at..insert_at, added);
+        sys.setattr("path", PyList::new(py, path))?;
+
+        let environ = py.import("os")?.getattr("environ")?;
+        environ.set_item(VIRTUAL_ENV_VAR, root.as_ref())?;
+        let bin_dir = self.root.join(if cfg!(windows) { "Scripts" } else { "bin" });
+        let mut paths = vec![bin_dir];
+        paths.extend(env::var_os("PATH").iter().flat_map(env::split_paths));
+        let joined = env::join_paths(paths).map_err(|e| Error::invalid_venv(&self.root, e.to_string()))?;
+        environ.set_item("PATH", joined.to_string_lossy().as_ref())?;
+        Ok(())
+    }
+}
+
+impl ActiveEnvironment {
+    // Describe the environment the interpreter is currently using
+    pub fn current(py: Python<'_>, venv: Option<VirtualEnv>) -> Result<ActiveEnvironment, Error> {
+        let sys = py.import("sys")?;
+        let paths: Vec<String> = sys.getattr("path")?.extract()?;
+        Ok(ActiveEnvironment {
+            venv,
+            prefix: sys.getattr("prefix")?.extract()?,
+            base_prefix: sys.getattr("base_prefix")?.extract()?,
+            python_version: sys.getattr("version")?.extract::<String>()?.split_whitespace().next().unwrap_or_default().to_owned(),
+            site_packages: paths.into_iter().filter(|p| p.ends_with("site-packages") || p.ends_with("dist-packages")).collect(),
+        })
+    }
+
+    pub fn is_venv(&self) -> bool {
+        self.venv.is_some()
+    }
+}
+
+impl fmt::Display for VenvOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VenvOrigin::Configured => write!(f, "configured path"),
+            VenvOrigin::VirtualEnvVar => write!(f, "${VIRTUAL_ENV_VAR}"),
+            VenvOrigin::ProjectDir => write!(f, "project {VENV_DIR_NAME}/ directory"),
+        }
+    }
+}
+
+impl fmt::Display for ActiveEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.venv {
+            Some(venv) => writeln!(f, "Python {} using virtualenv {} (found via {})", self.python_version, venv.root.display(), venv.origin)?,
+            None => writeln!(f, "Python {} using the system interpreter at {} (no virtualenv found)", self.python_version, self.prefix)?,
+        }
+        write!(f, "site-packages:")?;
+        for dir in &self.site_packages {
+            write!(f, "\n    {dir}")?;
+        }
+        Ok(())
+    }
+}
+
+// Activate the venv found by `locator` in the embedded interpreter, once per process
+// later calls return the environment picked by the first one
+// call this before importing anything from the venv (emoji, boto3, ...)
+// it also sends Python's logging to the Rust `log` facade (see logging::install)
+pub fn activate(py: Python<'_>, locator: &VenvLocator) -> Result<&'static ActiveEnvironment, Error> {
+    if let Some(active) = ACTIVE_ENVIRONMENT.get() {
+        return Ok(active);
+    }
+    let _activating = loop {
+        match ACTIVATING.try_lock() {
+            Ok(guard) => break guard,
+            Err(TryLockError::Poisoned(poisoned)) => break poisoned.into_inner(),
+            // wait without the GIL, the thread activating may need it to finish (see ModuleLoader::with_global)
+            Err(TryLockError::WouldBlock) => py.allow_threads(|| drop(ACTIVATING.lock())),
+        }
+    };
+    // another thread activated while this one waited
+    if let Some(active) = ACTIVE_ENVIRONMENT.get() {
+        return Ok(active);
+    }
+    crate::logging::install(py)?;
+    let venv = locator.locate(py)?;
+    if let Some(venv) = &venv {
+        venv.activate_in(py)?;
+    }
+    let active = ActiveEnvironment::current(py, venv)?;
+    Ok(ACTIVE_ENVIRONMENT.get_or_init(|| active))
+}
+
+// the environment set up by activate(), if it was called
+pub fn active_environment() -> Option<&'static ActiveEnvironment> {
+    ACTIVE_ENVIRONMENT.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "3.11" for the embedded interpreter
+    fn interpreter() -> String {
+        Python::with_gil(|py| {
+            let version_info = py.import("sys").unwrap().getattr("version_info").unwrap();
+            let major: u8 = version_info.getattr("major").unwrap().extract().unwrap();
+            let minor: u8 = version_info.getattr("minor").unwrap().extract().unwrap();
+            format!("{major}.{minor}")
+        })
+    }
+
+    // a venv directory for the running interpreter's version, with `config` as pyvenv.cfg
+    // "{interpreter}" in `config` is replaced by the interpreter's version
+    fn venv(test: &str, config: Option<&str>) -> PathBuf {
+        let root = env::temp_dir().join(format!("examples-venv-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let interpreter = interpreter();
+        let site_packages = if cfg!(windows) { root.join("Lib/site-packages") } else { root.join(format!("lib/python{interpreter}/site-packages")) };
+        fs::create_dir_all(site_packages).unwrap();
+        if let Some(config) = config {
+            fs::write(root.join("pyvenv.cfg"), config.replace("{interpreter}", &interpreter)).unwrap();
+        }
+        root
+    }
+
+    fn open(root: &Path) -> Result<VirtualEnv, Error> {
+        Python::with_gil(|py| VirtualEnv::open(py, root, VenvOrigin::Configured))
+    }
+
+    #[test]
+    fn open_reads_pyvenv_cfg() {
+        let root = venv("valid", Some("home = /usr/bin\ninclude-system-site-packages = true\nversion = {interpreter}.7\n"));
+        let venv = open(&root).unwrap();
+        assert!(venv.include_system_site_packages);
+        assert_eq!(venv.version, Some(format!("{}.7", interpreter())));
+        assert!(venv.site_packages.ends_with("site-packages"));
+
+        // virtualenv writes version_info instead of version
+        fs::write(root.join("pyvenv.cfg"), format!("version_info = {}.0.final.0\n", interpreter())).unwrap();
+        let venv = open(&root).unwrap();
+        assert!(!venv.include_system_site_packages);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn open_rejects_unusable_venvs() {
+        let cases = [
+            ("missing", None, "could not read"),
+            ("mismatch", Some("version = 2.7.18\n"), "created with python 2.7.18"),
+            // 3.1 must not match 3.11
+            ("prefix", Some("version = {interpreter}1\n"), "created with python"),
+        ];
+        for (test, config, reason_start) in cases {
+            let root = venv(test, config);
+            match open(&root) {
+                Err(Error::InvalidVenv { path, reason }) => {
+                    assert_eq!(path, root, "{test}");
+                    assert!(reason.starts_with(reason_start), "{test}: {reason}");
+                }
+                other => panic!("{test}: expected Error::InvalidVenv, got {other:?}"),
+            }
+            let _ = fs::remove_dir_all(&root);
+        }
+
+        // pyvenv.cfg is fine but site-packages is missing
+        let root = venv("no-site-packages", Some("version = {interpreter}.0\n"));
+        fs::remove_dir_all(root.join(if cfg!(windows) { "Lib" } else { "lib" })).unwrap();
+        assert!(matches!(open(&root), Err(Error::InvalidVenv { reason, .. }) if reason.ends_with("does not exist")));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,92 @@
+# Child process side of WorkerPool (see worker.rs), run with `python -c`
+# the directories to import from are passed as arguments
+# reads one JSON call per line from stdin and writes one JSON reply per line to the original stdout;
+# print() output from the called functions goes to stderr so it can't corrupt the replies
+import importlib
+import json
+import os
+import sys
+import traceback
+
+MAX_CHAIN = 32
+
+
+def kind_of(error):
+    # the same split as Error::from_pyerr
+    if isinstance(error, SyntaxError):
+        return "syntax"
+    if isinstance(error, ModuleNotFoundError):
+        return "module_not_found"
+    if isinstance(error, TypeError):
+        return "type"
+    if isinstance(error, ValueError):
+        return "value"
+    return "python"
+
+
+def exception_chain(error):
+    # root cause first, like traceback.rs; frames of this script are left out
+    chain = []
+    seen = set()
+    current, leads_to = error, None
+    while current is not None and id(current) not in seen and len(chain) < MAX_CHAIN:
+        seen.add(id(current))
+        frames = [
+            {"file": frame.filename, "line": frame.lineno or 0, "function": frame.name, "source_line": frame.line or None}
+            for frame in traceback.extract_tb(current.__traceback__)
+            if frame.filename != "<string>"
+        ]
+        chain.append({"type_name": type(current).__name__, "message": str(current), "frames": frames, "leads_to": leads_to})
+        if current.__cause__ is not None:
+            current, leads_to = current.__cause__, "cause"
+        elif current.__context__ is not None and not current.__suppress_context__:
+            current, leads_to = current.__context__, "context"
+        else:
+            current = None
+    chain.reverse()
+    return chain
+
+
+def positional(args):
+    # serde sends () as null and a single non-tuple argument as itself
+    if args is None:
+        return []
+    if isinstance(args, list):
+        return args
+    return [args]
+
+
+def handle(request):
+    try:
+        module = importlib.import_module(request["module"])
+        function = getattr(module, request["function"], None)
+        if not callable(function):
+            return {"error": {"kind": "missing_function"}}
+        value = function(*positional(request["args"]))
+    except Exception as error:
+        return {"error": {"kind": kind_of(error), "chain": exception_chain(error)}}
+    try:
+        json.dumps(value)
+    except (TypeError, ValueError) as error:
+        return {"error": {"kind": "return_type", "message": str(error)}}
+    return {"value": value}
+
+
+def main():
+    replies = os.fdopen(os.dup(1), "w", encoding="utf-8")
+    os.dup2(2, 1)
+    sys.stdout = sys.stderr
+    for path in reversed(sys.argv[1:]):
+        sys.path.insert(0, path)
+
+    replies.write(json.dumps({"ready": os.getpid()}) + "\n")
+    replies.flush()
+    for line in sys.stdin:
+        request = json.loads(line)
+        reply = handle(request)
+        reply["id"] = request["id"]
+        replies.write(json.dumps(reply) + "\n")
+        replies.flush()
+
+
+main()
@@ -0,0 +1,503 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, PyException};
+use crate::search_path::{SearchPath, PY_PATH_ENV_VAR};
+use crate::timeout::default_timeout;
+use crate::traceback::{ChainRelation, ExceptionReport, Frame, Traceback};
+use crate::venv::{self, VENV_DIR_NAME};
+
+const WORKER_SCRIPT: &str = include_str!("worker.py");
+
+// How to start the worker processes of a WorkerPool
+#[derive(Debug, Clone)]
+pub struct WorkerOptions {
+    // interpreter started for each worker, usually the venv's bin/python so its packages can be imported
+    pub python: PathBuf,
+    pub workers: usize,
+    // directories the workers import modules from (functions_venv.py, ...)
+    pub dirs: Vec<PathBuf>,
+    // how long a call may take before its worker is killed and replaced; None uses timeout::default_timeout()
+    pub timeout: Option<Duration>,
+}
+
+// Runs Python functions in child processes instead of the embedded interpreter
+// every worker is a separate `python` with its own GIL, so calls on different workers run in parallel and a
+// crash (segfault, os._exit, out of memory) only takes down that worker
+// arguments and return values cross the pipe as JSON, so they must be serde types that map onto
+// JSON-compatible Python values (numbers, strings, bools, None, lists, dicts)
+//     let pool = WorkerPool::new(WorkerOptions::default())?;
+//     let random_number = pool.function::<(i64, i64), i64>("functions_venv", "random_number");
+//     let n = random_number.call((1, 10))?;
+// a call that runs past its timeout returns Error::Timeout; the worker can't be interrupted the way the
+// embedded interpreter is, so it is killed and a new one takes its place
+pub struct WorkerPool {
+    options: WorkerOptions,
+    // None when the worker died and couldn't be restarted yet; the next call on the slot tries again
+    slots: Vec<Mutex<Option<Worker>>>,
+    next_slot: AtomicUsize,
+    next_id: AtomicU64,
+    restarts: AtomicUsize,
+}
+
+// A function in a worker module, called like a PyFunction
+// a clone shares the pool
+pub struct WorkerFunction<Args, Ret> {
+    pool: Arc<WorkerPool>,
+    module: String,
+    name: String,
+    // None uses the pool's timeout
+    timeout: Option<Duration>,
+    signature: PhantomData<fn(Args) -> Ret>,
+}
+
+// one child process and the pipes to it
+// replies are read on a thread of their own and sent here line by line, so waiting for one can time out
+// the channel closes when the worker closes its stdout
+struct Worker {
+    child: Child,
+    requests: ChildStdin,
+    replies: Receiver<io::Result<String>>,
+}
+
+#[derive(Serialize)]
+struct Request<'a, Args> {
+    id: u64,
+    module: &'a str,
+    function: &'a str,
+    args: Args,
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(default)]
+    ready: Option<u32>,
+    // null for None, which is also what a missing field means
+    #[serde(default)]
+    value: serde_json::Value,
+    #[serde(default)]
+    error: Option<ReplyError>,
+}
+
+#[derive(Deserialize)]
+struct ReplyError {
+    // syntax, module_not_found, type, value, python, missing_function or return_type
+    kind: String,
+    #[serde(default)]
+    message: String,
+    // root cause first, see exception_chain() in worker.py
+    #[serde(default)]
+    chain: Vec<ReplyException>,
+}
+
+#[derive(Deserialize)]
+struct ReplyException {
+    type_name: String,
+    message: String,
+    frames: Vec<ReplyFrame>,
+    leads_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReplyFrame {
+    file: String,
+    line: usize,
+    function: String,
+    source_line: Option<String>,
+}
+
+impl WorkerOptions {
+    // the crate's venv (when there is one) and py/ directories; pass env!("CARGO_MANIFEST_DIR") from that crate
+    pub fn for_crate(manifest_dir: impl AsRef<Path>) -> WorkerOptions {
+        let manifest_dir = manifest_dir.as_ref();
+        WorkerOptions {
+            python: venv_python(&manifest_dir.join(VENV_DIR_NAME)).unwrap_or_else(|| PathBuf::from("python3")),
+            workers: 2,
+            dirs: SearchPath::for_crate(PY_PATH_ENV_VAR, manifest_dir).dirs(),
+            timeout: None,
+        }
+    }
+
+    pub fn python(mut self, python: impl Into<PathBuf>) -> WorkerOptions {
+        self.python = python.into();
+        self
+    }
+
+    pub fn workers(mut self, workers: usize) -> WorkerOptions {
+        self.workers = workers;
+        self
+    }
+
+    pub fn dirs(mut self, dirs: Vec<PathBuf>) -> WorkerOptions {
+        self.dirs = dirs;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> WorkerOptions {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl Default for WorkerOptions {
+    // same as for_crate() for this crate, except that a venv already activated in the embedded
+    // interpreter (venv::activate) is preferred, so both backends import the same packages
+    fn default() -> WorkerOptions {
+        let options = WorkerOptions::for_crate(env!("CARGO_MANIFEST_DIR"));
+        match venv::active_environment().and_then(|active| active.venv.as_ref()).and_then(|venv| venv_python(&venv.root)) {
+            Some(python) => options.python(python),
+            None => options,
+        }
+    }
+}
+
+fn venv_python(root: &Path) -> Option<PathBuf> {
+    let python = root.join(if cfg!(windows) { "Scripts/python.exe" } else { "bin/python" });
+    python.is_file().then_some(python)
+}
+
+impl WorkerPool {
+    // Start every worker; fails if one of them doesn't come up (bad interpreter path, ...)
+    pub fn new(options: WorkerOptions) -> Result<Arc<WorkerPool>, Error> {
+        let mut slots = Vec::with_capacity(options.workers.max(1));
+        for _ in 0..options.workers.max(1) {
+            slots.push(Mutex::new(Some(Worker::spawn(&options)?)));
+        }
+        Ok(Arc::new(WorkerPool {
+            options,
+            slots,
+            next_slot: AtomicUsize::new(0),
+            next_id: AtomicU64::new(0),
+            restarts: AtomicUsize::new(0),
+        }))
+    }
+
+    pub fn function<Args: Serialize, Ret: DeserializeOwned>(self: &Arc<Self>, module: &str, name: &str) -> WorkerFunction<Args, Ret> {
+        WorkerFunction { pool: Arc::clone(self), module: module.to_owned(), name: name.to_owned(), timeout: None, signature: PhantomData }
+    }
+
+    // Call module.function(*args) on the next free worker
+    // if the worker dies during the call the result is Error::WorkerCrashed and a new worker takes its place;
+    // the call is not retried, since it may be what crashed the worker
+    pub fn call<Args: Serialize, Ret: DeserializeOwned>(&self, module: &str, function: &str, args: Args) -> Result<Ret, Error> {
+        self.call_with_timeout(module, function, args, self.options.timeout.or_else(default_timeout))
+    }
+
+    // call() with its own timeout, None for no limit
+    // when it expires the worker is killed and replaced, and the result is Error::Timeout
+    pub fn call_with_timeout<Args: Serialize, Ret: DeserializeOwned>(
+        &self,
+        module: &str,
+        function: &str,
+        args: Args,
+        timeout: Option<Duration>,
+    ) -> Result<Ret, Error> {
+        let qualified_name = format!("{module}.{function}");
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = Request { id, module, function, args };
+        let request = serde_json::to_string(&request)
+            .map_err(|e| Error::Conversion { target: "JSON call arguments", message: e.to_string() })?;
+
+        let mut slot = self.free_slot();
+        let worker = match slot.as_mut() {
+            Some(worker) => worker,
+            None => slot.insert(Worker::spawn(&self.options)?),
+        };
+        let reply = match worker.call(id, &request, timeout) {
+            Ok(reply) => reply,
+            Err(e) => {
+                let crashed = slot.take().map(Worker::reap);
+                // if it can't be restarted now, the next call on this slot tries again
+                *slot = Worker::spawn(&self.options).ok();
+                if slot.is_some() {
+                    self.restarts.fetch_add(1, Ordering::Relaxed);
+                }
+                if let (ErrorKind::TimedOut, Some(after)) = (e.kind(), timeout) {
+                    return Err(Error::Timeout { function: qualified_name, after });
+                }
+                let (pid, status) = crashed.unwrap_or_default();
+                return Err(Error::WorkerCrashed { function: qualified_name, pid, status, message: e.to_string() });
+            }
+        };
+        drop(slot);
+
+        match reply.error {
+            Some(error) => Err(error.into_error(module, function)),
+            None => serde_json::from_value(reply.value).map_err(|e| Error::ReturnType {
+                function: qualified_name,
+                target: std::any::type_name::<Ret>(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.slots.len()
+    }
+
+    // how many workers were replaced after crashing or timing out
+    pub fn restarts(&self) -> usize {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    // process ids of the running workers, waits for calls in progress
+    pub fn pids(&self) -> Vec<u32> {
+        self.slots.iter().filter_map(|slot| lock(slot).as_ref().map(|worker| worker.child.id())).collect()
+    }
+
+    // the first idle worker starting from the round-robin position, or wait for the one there
+    fn free_slot(&self) -> MutexGuard<'_, Option<Worker>> {
+        let start = self.next_slot.fetch_add(1, Ordering::Relaxed);
+        let n = self.slots.len();
+        (0..n)
+            .find_map(|i| self.slots[(start + i) % n].try_lock().ok())
+            .unwrap_or_else(|| lock(&self.slots[start % n]))
+    }
+}
+
+// a worker that panicked mid-call is reaped and replaced by the next call, so the lock is still usable
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Worker {
+    fn spawn(options: &WorkerOptions) -> Result<Worker, Error> {
+        let command = format!("{} -c <worker.py>", options.python.display());
+        let mut child = Command::new(&options.python)
+            .arg("-u")
+            .arg("-c")
+            .arg(WORKER_SCRIPT)
+            .args(&options.dirs)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            // Python's print() output and tracebacks of a crashing worker show up like the in-process ones
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| Error::Command { command: command.clone(), status: None, stderr: e.to_string() })?;
+        let requests = child.stdin.take().expect("stdin is piped");
+        let replies = read_lines(child.stdout.take().expect("stdout is piped"));
+        let mut worker = Worker { child, requests, replies };
+        match worker.read_reply(None) {
+            Ok(Reply { ready: Some(_), .. }) => Ok(worker),
+            Ok(_) => Err(Error::Command { command, status: None, stderr: "the worker didn't start with a ready message".to_owned() }),
+            Err(e) => {
+                let (_, status) = worker.reap();
+                Err(Error::Command { command, status: None, stderr: format!("{e} ({status})") })
+            }
+        }
+    }
+
+    // send one request line and wait for its reply; any io error means the worker is gone or unusable
+    // ErrorKind::TimedOut when no reply came within `timeout`
+    fn call(&mut self, id: u64, request: &str, timeout: Option<Duration>) -> io::Result<Reply> {
+        writeln!(self.requests, "{request}")?;
+        self.requests.flush()?;
+        let reply = self.read_reply(timeout)?;
+        if reply.id != Some(id) {
+            return Err(io::Error::new(ErrorKind::InvalidData, format!("the worker answered request {:?} instead of {id}", reply.id)));
+        }
+        Ok(reply)
+    }
+
+    fn read_reply(&mut self, timeout: Option<Duration>) -> io::Result<Reply> {
+        let closed = || io::Error::new(ErrorKind::UnexpectedEof, "the worker closed its end of the pipe");
+        let line = match timeout {
+            Some(timeout) => self.replies.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => io::Error::new(ErrorKind::TimedOut, format!("no reply from the worker within {timeout:?}")),
+                RecvTimeoutError::Disconnected => closed(),
+            })?,
+            None => self.replies.recv().map_err(|_| closed())?,
+        }?;
+        serde_json::from_str(&line).map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("unreadable reply from the worker: {e}")))
+    }
+
+    // make sure the process is gone and describe how it ended
+    fn reap(mut self) -> (u32, String) {
+        let pid = self.child.id();
+        // it may have died with the reply half written or still be running after a broken reply
+        let _ = self.child.kill();
+        let status = match self.child.wait() {
+            Ok(status) => status.to_string(),
+            Err(e) => format!("unknown ({e})"),
+        };
+        (pid, status)
+    }
+}
+
+// every line the worker writes to stdout, until it closes it or a read fails
+// the thread ends with the worker, or when the Worker (and its receiver) is dropped and the next line comes
+fn read_lines(stdout: ChildStdout) -> Receiver<io::Result<String>> {
+    let (lines, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdout = BufReader::new(stdout);
+        loop {
+            let mut line = String::new();
+            let line = match stdout.read_line(&mut line) {
+                Ok(0) => return,
+                Ok(_) => Ok(line),
+                Err(e) => Err(e),
+            };
+            let failed = line.is_err();
+            if lines.send(line).is_err() || failed {
+                return;
+            }
+        }
+    });
+    receiver
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // workers are idle between calls, there is nothing to finish
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl ReplyError {
+    // the same variants an in-process call returns
+    fn into_error(self, module: &str, function: &str) -> Error {
+        let exception = || {
+            let exceptions: Vec<ExceptionReport> = self.chain.into_iter().map(ReplyException::into_report).collect();
+            let raised = exceptions.last();
+            PyException {
+                type_name: raised.map(|e| e.type_name.clone()).unwrap_or_else(|| "<unknown>".to_owned()),
+                message: raised.map(|e| e.message.clone()).unwrap_or_default(),
+                traceback: Box::new(Traceback { exceptions, failing_source: None }),
+            }
+        };
+        match self.kind.as_str() {
+            "missing_function" => Error::MissingFunction { module: module.to_owned(), function: function.to_owned() },
+            "return_type" => Error::ReturnType { function: format!("{module}.{function}"), target: "JSON", message: self.message },
+            "syntax" => Error::Syntax(exception()),
+            "module_not_found" => Error::ModuleNotFound(exception()),
+            "type" => Error::Type(exception()),
+            "value" => Error::Value(exception()),
+            _ => Error::Python(exception()),
+        }
+    }
+}
+
+impl ReplyException {
+    fn into_report(self) -> ExceptionReport {
+        ExceptionReport {
+            type_name: self.type_name,
+            message: self.message,
+            frames: self
+                .frames
+                .into_iter()
+                .map(|frame| Frame { file: frame.file, line: frame.line, column: None, function: frame.function, source_line: frame.source_line })
+                .collect(),
+            leads_to: match self.leads_to.as_deref() {
+                Some("cause") => Some(ChainRelation::Cause),
+                Some("context") => Some(ChainRelation::Context),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl<Args: Serialize, Ret: DeserializeOwned> WorkerFunction<Args, Ret> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Kill the worker and return Error::Timeout when a call takes longer than this, overriding the pool's timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> WorkerFunction<Args, Ret> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn call(&self, args: Args) -> Result<Ret, Error> {
+        let timeout = self.timeout.or(self.pool.options.timeout).or_else(default_timeout);
+        self.pool.call_with_timeout(&self.module, &self.name, args, timeout)
+    }
+}
+
+impl<Args, Ret> Clone for WorkerFunction<Args, Ret> {
+    fn clone(&self) -> Self {
+        WorkerFunction {
+            pool: Arc::clone(&self.pool),
+            module: self.module.clone(),
+            name: self.name.clone(),
+            timeout: self.timeout,
+            signature: PhantomData,
+        }
+    }
+}
+
+impl fmt::Debug for WorkerPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerPool").field("options", &self.options).field("restarts", &self.restarts()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+
+    // a one-worker pool importing from a scratch directory holding `code` as worker_test.py
+    fn pool(test: &str, code: &str) -> (PathBuf, Arc<WorkerPool>) {
+        let dir = env::temp_dir().join(format!("examples-worker-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("worker_test.py"), code).unwrap();
+        let options = WorkerOptions::for_crate(env!("CARGO_MANIFEST_DIR")).python("python3").workers(1).dirs(vec![dir.clone()]);
+        (dir, WorkerPool::new(options).unwrap())
+    }
+
+    #[test]
+    fn slow_calls_time_out_and_the_worker_is_replaced() {
+        let (dir, pool) = pool("timeout", "import time\n\ndef nap(seconds):\n    time.sleep(seconds)\n    return seconds\n");
+        let nap = pool.function::<(f64,), f64>("worker_test", "nap").with_timeout(Duration::from_millis(200));
+        let pid = pool.pids();
+
+        match nap.call((5.0,)) {
+            Err(Error::Timeout { function, after }) => {
+                assert_eq!(function, "worker_test.nap");
+                assert_eq!(after, Duration::from_millis(200));
+            }
+            other => panic!("expected Error::Timeout, got {other:?}"),
+        }
+        assert_eq!(pool.restarts(), 1);
+        assert_ne!(pool.pids(), pid);
+        // the new worker answers the next call, not the reply to the one that timed out
+        assert_eq!(nap.call((0.0,)).unwrap(), 0.0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn errors_name_the_module_and_function() {
+        let (dir, pool) = pool("errors", "def text():\n    return 'not a number'\n\ndef unserializable():\n    return object()\n");
+        match pool.call::<(), i64>("worker_test", "text", ()) {
+            Err(Error::ReturnType { function, .. }) => assert_eq!(function, "worker_test.text"),
+            other => panic!("expected Error::ReturnType, got {other:?}"),
+        }
+        match pool.call::<(), i64>("worker_test", "unserializable", ()) {
+            Err(Error::ReturnType { function, target, .. }) => assert_eq!((function.as_str(), target), ("worker_test.unserializable", "JSON")),
+            other => panic!("expected Error::ReturnType, got {other:?}"),
+        }
+        match pool.call::<(), i64>("worker_test", "missing", ()) {
+            Err(Error::MissingFunction { module, function }) => assert_eq!((module.as_str(), function.as_str()), ("worker_test", "missing")),
+            other => panic!("expected Error::MissingFunction, got {other:?}"),
+        }
+        assert_eq!(pool.restarts(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,71 +1,142 @@
-use examples;
+use examples::{BootstrapOptions, CodegenOptions, OutputFormat, RequirementsPolicy};
+
 fn main() {
-    // for troubleshooting--displays local python packages
-    examples::display_package_info();
+    // Python's logging ends up here too; RUST_LOG=debug (or RUST_LOG=functions_venv=debug) shows more
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    // `cargo run -- bootstrap` creates env/ and installs requirements.txt (replaces setup.sh)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bootstrap") {
+        let options = BootstrapOptions::for_crate(env!("CARGO_MANIFEST_DIR")).parse_args(args.into_iter().skip(1));
+        match options.and_then(|options| examples::bootstrap::bootstrap(&options)) {
+            Ok(report) => println!("\n{report}"),
+            Err(e) => {
+                println!("\nBootstrap failed because {}", e.report());
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // `cargo run -- codegen` rewrites src/lib/py_functions.rs from the type hints in py/*.py
+    // (`--check` only reports whether it is out of date)
+    if args.first().map(String::as_str) == Some("codegen") {
+        let options = CodegenOptions::for_crate(env!("CARGO_MANIFEST_DIR")).parse_args(args.into_iter().skip(1));
+        match options.and_then(|options| examples::codegen::codegen(&options)) {
+            Ok(report) => println!("{report}"),
+            Err(e) => {
+                println!("Codegen failed because {}", e.report());
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // `cargo run -- packages [--json]` only lists the packages the embedded interpreter can import
+    let list_only = args.first().map(String::as_str) == Some("packages");
+    let format = if args.iter().any(|arg| arg == "--json") { OutputFormat::Json } else { OutputFormat::Text };
+
+    // pick up env/ (or $VIRTUAL_ENV) so run.sh isn't needed to make the venv packages importable
+    match examples::activate_venv() {
+        // keep stdout clean for the JSON output
+        Ok(_) if format == OutputFormat::Json => {}
+        Ok(environment) => println!("{environment}"),
+        Err(e) => eprintln!("Could not activate the virtualenv because {e}"),
+    }
+    // for troubleshooting--displays the python packages available to the examples
+    if let Err(e) = examples::display_package_info(format) {
+        println!("Could not display package info because {e}");
+    }
+    if list_only {
+        return;
+    }
+
+    // `cargo run -- --strict` refuses to run the examples when requirements.txt isn't met
+    let policy = if args.iter().any(|arg| arg == "--strict") { RequirementsPolicy::Enforce } else { RequirementsPolicy::Warn };
+    match examples::verify_requirements(policy) {
+        Ok(report) => println!("\n{report}"),
+        Err(e) => {
+            println!("\nNot running the examples: {e}");
+            std::process::exit(1);
+        }
+    }
+    // compare the generated bindings with the functions in py/*.py before any of them is called
+    if let Err(e) = examples::py_functions::validate_all() {
+        println!("\nThe bindings in py_functions.rs don't match py/*.py, run `cargo run -- codegen`: {}", e.report());
+    }
     println!("\n----------Begin PYO3 example functions -------------------------------------\n");
     // each of the following examples shows a different way to call Python from Rust
     /*
     println!("\nExample 1: simple inline code");
-    let _r1 = match examples::simple_inline_python_code() {
+    match examples::simple_inline_python_code() {
         Ok(_) =>     println!("\nPy Function 1 success!!!\n"),
         Err(_) =>     println!("\nPy Function 1 failed...\n"),
     };
     println!("\nEnd\n--------------------------------------------------\n");
 
     println!("\nExample 2: inline code + library import");
-    let _r2 = match examples::print_python_version() {
+    match examples::print_python_version() {
         Ok(_) =>     println!("\nPy Function 2 success!!!\n"),
         Err(_) =>     println!("\nPy Function 2 failed...\n"),
     };
     println!("\nEnd\n--------------------------------------------------\n");
 
     println!("\nExample 3:  No args, vs PyTuple args, vs rust tuple args");
-    let _r3 = match examples::python_function_with_args() {
+    match examples::python_function_with_args() {
         Ok(_) =>     println!("\nPy Function 3 success!!!\n"),
         Err(_) =>     println!("\nPy Function 3 failed...\n"),
     };
     println!("\nEnd\n--------------------------------------------------\n");
     
     println!("\nExample 4: kwargs as PyDict, Vec, or Hashmap");
-    let _r4 = match examples::python_function_with_kwargs() {
+    match examples::python_function_with_kwargs() {
         Ok(_) =>     println!("\nPy Function 4 success!!!\n"),
         Err(_) =>     println!("\nPy Function 4 failed...\n"),
     };
     println!("\nEnd\n--------------------------------------------------\n");
 
     println!("\nExample 5: call from local .py file");
-    let _r5 = match examples::python_function_from_file() {
+    match examples::python_function_from_file() {
         Ok(n) =>     println!("\nPy Function 5 success!! \nThe result was {n:?} \n"),
-        Err(e) =>     println!("\nPy Function 5 failed because {e}...\n"),
+        Err(e) =>     println!("\nPy Function 5 failed because {}\n", e.report()),
     };
     println!("\nEnd\n--------------------------------------------------\n");
 
     println!("\nExample 6: error handling");
-    let _r6 = match examples::python_function_err_handling() {
+    match examples::python_function_err_handling() {
         Ok(n) =>     println!("\nPy Function 6 success!! \nThe result was {n:?} \n"),
-        Err(e) =>     println!("\nPy Function 6 failed because {e}...\n"),
+        Err(e) =>     println!("\nPy Function 6 failed because {}\n", e.report()),
     };
     println!("\nEnd\n--------------------------------------------------\n");
      */
 
-    println!("\nExample 7a: functions requiring packages installed on venv -- returns PyResult<i32, PyErr>");
-    let _r7a = match examples::python_function_venv_a() {
+    println!("\nExample 7a: functions requiring packages installed on venv -- returns Result<i64, Error>");
+    match examples::python_function_venv_a() {
         Ok(n) =>     println!("\nPy Function 7a success!! \nThe result was {n:?} \n"),
-        Err(pyerr) =>     println!("\nPy Function 7a failed because {pyerr} ...\n"),
+        Err(e) =>     println!("\nPy Function 7a failed because {}\n", e.report()),
     };
     println!("\n\n---------------------------------------------------------------------------\n---------------------------------------------------------------------------\n");
 
-    println!("\nExample 7b: functions requiring packages installed on venv -- returns Result<i32, Error>");
-    let _r7b = match examples::python_function_venv_b() {
+    println!("\nExample 7b: functions requiring packages installed on venv -- returns Result<i64, Error>");
+    match examples::python_function_venv_b() {
         Ok(n) =>     println!("\nPy Function 7b success!! \nThe result was {n:?} \n"),
-        Err(e) =>     println!("\nPy Function 7b failed because {e}...\n"),
+        Err(e) =>     println!("\nPy Function 7b failed because {}\n", e.report()),
+    };
+    println!("\n\n---------------------------------------------------------------------------\n---------------------------------------------------------------------------\n");
+
+    println!("\nExample 7c: functions requiring packages installed on venv -- returns Result<Option<Emoji>, Error>");
+    match examples::python_function_venv_c("red") {
+        Ok(Some(emoji)) =>     println!("\nPy Function 7c success!! \nThe result was {emoji} ({} code point(s))\n", emoji.code_points()),
+        Ok(None) =>     println!("\nPy Function 7c success!! \nThe result was None (no emoji with that name)\n"),
+        Err(e) =>     println!("\nPy Function 7c failed because {}\n", e.report()),
     };
     println!("\n\n---------------------------------------------------------------------------\n---------------------------------------------------------------------------\n");
 
-    println!("\nExample 7c: functions requiring packages installed on venv -- returns Result<Option<char>, Error>");
-    let _r7c = match examples::python_function_venv_c("red") {
-        Ok(n) =>     println!("\nPy Function 7c success!! \nThe result was Ok({n:?}) \n"),
-        Err(e) =>     println!("\nPy Function 7c failed because {e}...\n"),
+    println!("\nExample 7e: any emoji by name or alias -- returns Result<Option<Emoji>, Error>");
+    match examples::python_function_venv_e("thumbsup") {
+        Ok(Some(emoji)) =>     println!("\nPy Function 7e success!! \nThe result was {emoji} ({} code point(s))\n", emoji.code_points()),
+        Ok(None) =>     println!("\nPy Function 7e success!! \nThe result was None (no emoji with that name)\n"),
+        Err(e) =>     println!("\nPy Function 7e failed because {}\n", e.report()),
     };
 
     println!("\nEnd\n--------------------------------------------------\n");
@@ -2,6 +2,7 @@
 # use $ aws configure to enter credentials (Access Key, Secret Key, and Session Token) 
 
 import boto3
+from botocore.config import Config
 from botocore.exceptions import BotoCoreError, ClientError
 import os
 import sys
@@ -9,18 +10,32 @@ from contextlib import closing
 import pathlib
 import subprocess
 
-def polly_demo(text:str):
+# timeout: seconds the whole request may take, retries included (None keeps botocore's defaults)
+def polly_demo(text:str, timeout:float=None):
     print("Creating Speech from text using Polly")
-    p = AmazonPolly()
+    p = AmazonPolly(client_config(timeout))
     p.set_dialog(text)
     p.generate_audio()
     p.play_audio()
     return p.output_audio_file_path
 
+# botocore timeouts that fit in `timeout`: the Rust side can't interrupt a socket read blocked in C,
+# so the connection has to give up by itself before the deadline
+def client_config(timeout, attempts=2):
+    if timeout is None:
+        return None
+    per_attempt = timeout / attempts
+    connect_timeout = min(10.0, per_attempt / 4)
+    return Config(
+        connect_timeout=connect_timeout,
+        read_timeout=per_attempt - connect_timeout,
+        retries={"mode": "standard", "total_max_attempts": attempts},
+    )
+
 class AmazonPolly():
     
-    def __init__(self):
-        self.polly = boto3.client('polly')
+    def __init__(self, config=None):
+        self.polly = boto3.client('polly', config=config)
         self.VOICE_ID = 'Joanna'
         self.text = None
         self.output_audio_file_path = os.path.join(pathlib.Path(__file__).resolve().parent, "output.mp3")
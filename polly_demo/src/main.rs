@@ -1,23 +1,72 @@
-use std::io::prelude::*;
-use std::io::Error;
-use std::io::ErrorKind;
 use std::path::Path;
-use std::fs::File;
+use std::time::Duration;
 
+use log::LevelFilter;
 use pyo3::prelude::*;
 use pyo3::types::PyModule;
-use pyo3::types::PyTuple;
-use pyo3::exceptions::PySyntaxError;
+
+use examples::bootstrap::REQUIREMENTS_FILE;
+use examples::timeout::run_with_timeout;
+use examples::{capture, module_from_code, BootstrapOptions, Capture, EmbeddedSource, Error, RequirementsPolicy, SearchPath, SourceSet, VenvLocator};
 
 // Input text
 const INPUT_TEXT: &str = "Welcome to Polly";
-const PY_FILE: &str = "py/polly.py";
+// looked up in $POLLY_PY_PATH, then py/ next to the executable, then polly_demo/py
+// if none of those exist, the copy compiled into the binary is used
+const PY_FILE: &str = "polly.py";
+const PY_PATH_ENV_VAR: &str = "POLLY_PY_PATH";
+const EMBEDDED_SOURCES: &[EmbeddedSource] = &[examples::embed_py!("polly.py")];
+// a hung request to AWS is interrupted after this long (Error::Timeout)
+// polly.py also derives botocore's connect/read timeouts and retries from it, since a blocked socket read
+// can only be stopped by botocore itself
+const POLLY_TIMEOUT: Duration = Duration::from_secs(60);
 
 fn main() {
+    // boto3 and botocore log through Python's logging, which is forwarded here (ex. "Found credentials in ...")
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // `cargo run -- bootstrap` creates env/ and installs requirements.txt (replaces setup.sh)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bootstrap") {
+        let options = BootstrapOptions::for_crate(env!("CARGO_MANIFEST_DIR")).parse_args(args.into_iter().skip(1));
+        match options.and_then(|options| examples::bootstrap::bootstrap(&options)) {
+            Ok(report) => println!("\n{report}"),
+            Err(e) => {
+                println!("\nBootstrap failed because {}", e.report());
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // boto3 lives in polly_demo/env (or $VIRTUAL_ENV), so activate it before importing polly.py
+    let activated = Python::with_gil(|py| examples::venv::activate(py, &VenvLocator::for_crate(env!("CARGO_MANIFEST_DIR"))));
+    match activated {
+        Ok(environment) => println!("{environment}"),
+        Err(e) => println!("Could not activate the virtualenv because {e}"),
+    }
+    // botocore logs every request and response at DEBUG; keep it to INFO even with RUST_LOG=debug
+    if let Err(e) = Python::with_gil(|py| examples::logging::set_levels(py, [("botocore", LevelFilter::Info), ("urllib3", LevelFilter::Warn)])) {
+        println!("Could not set the Python log levels because {e}");
+    }
+
+    // a boto3 that doesn't match requirements.txt fails in odd ways inside AmazonPolly, so check it first
+    // `cargo run -- --strict` stops here instead of only printing the report
+    let strict = std::env::args().any(|arg| arg == "--strict");
+    let policy = if strict { RequirementsPolicy::Enforce } else { RequirementsPolicy::Warn };
+    let requirements = Path::new(env!("CARGO_MANIFEST_DIR")).join(REQUIREMENTS_FILE);
+    match Python::with_gil(|py| examples::requirements::check_requirements(py, &requirements, policy)) {
+        Ok(report) => println!("\n{report}"),
+        Err(e) => {
+            println!("\nNot calling Polly: {e}");
+            std::process::exit(1);
+        }
+    }
+
     let s = String::from(INPUT_TEXT);
     match call_polly(s) {
         Ok(n) => println!("\nPy Function was successful!! \nThe audio file was saved at: {n:?} \n"),
-        Err(e) => println!("\nPy Function failed because {e}...\n"),
+        Err(e) => println!("\nPy Function failed because {}\n", e.report()),
     };
 }
 
@@ -26,28 +75,36 @@ fn call_polly(text: String) -> Result<String, Error> {
     println!("\nInitializing py interpreter...");
     Python::with_gil(|py| {
         // first we need to grab the python code from a local file
-        let code = get_py_file_contents(PY_FILE)?;
+        // the file is found through the search path, so the binary doesn't have to be run from polly_demo/
+        let search_path = SearchPath::for_crate(PY_PATH_ENV_VAR, env!("CARGO_MANIFEST_DIR"));
+        let source = SourceSet::new(EMBEDDED_SOURCES, search_path).load(PY_FILE)?;
+        println!("Using {}", source.info);
+        let file_name = source.file_name();
+        let code = source.code;
+        // let linecache know about the code so tracebacks can show the failing line
+        examples::traceback::register_source(py, &file_name, &code)?;
         println!("\nPython code to evaluate:\n-----start of py code-----\n\n{code}\n\n-----end of py code-----");
 
         // attempt create PyModule from contents of file
-        let functions_pymodule: Result<&PyModule, PyErr> = PyModule::from_code(py, &code, "functions.py", "functions");
-        let args = PyTuple::new(py, &[text]);
+        // a missing boto3 shows up here as Error::ModuleNotFound
+        // registered in sys.modules as "_examples.polly_<n>", so it can't clash with a module called "functions" or "polly"
+        let functions_pymodule: Result<&PyModule, Error> = module_from_code(py, &code, &file_name, "polly");
+        let args = (text, POLLY_TIMEOUT.as_secs_f64());
         println!("\nEvaluating python code using args: {args:?}...\n-----start of py output-----\n");
 
-        match functions_pymodule?.getattr("polly_demo")?.call1(args) {
+        // each line polly.py prints is shown indented as soon as it is printed, so it stands apart from the Rust output
+        let polly_demo = functions_pymodule?.getattr("polly_demo")?;
+        let call = || polly_demo.call1(args).map_err(|pyerr| Error::from_pyerr(py, pyerr));
+        let captured = capture(&Capture::lines(|_, line| println!("    {line}")), || run_with_timeout(POLLY_TIMEOUT, "polly_demo", call));
+        match captured.value {
             Ok(p) => { // python function was completed successfully
                 println!("\n-----end of py output-----\npolly_demo() function call succeeded");
-                match p.extract::<String>() {
-                    Ok(p) => {return Ok(is_str_valid_filepath(&p)?.to_owned());},
-                    Err(e) => {return Err(Error::new(ErrorKind::Other, e));},
-                };
-            }
-            Err(pyerr) if pyerr.is_instance_of::<PySyntaxError>(py) => {
-                println!("\nResult: ERR (InvalidInput) \nPython module could not be created due to syntax error");
-                return Err(Error::new(ErrorKind::InvalidInput, pyerr));
+                let p: String = examples::extract(p)?;
+                Ok(is_str_valid_filepath(&p)?.to_owned())
             }
-            Err(e) => {return Err(Error::new(ErrorKind::Other, e));}
-        };
+            // boto3/botocore failures keep their exception type name (ex. "NoCredentialsError") inside Error::Python
+            Err(e) => Err(e),
+        }
     })
 }
 
@@ -55,32 +112,7 @@ fn call_polly(text: String) -> Result<String, Error> {
 fn is_str_valid_filepath(s: &str) -> Result<&str, Error> {
     match Path::new(s).try_exists() {
         Ok(true) => Ok(s),
-        Ok(false) => Err(Error::new(ErrorKind::Other,"Could not access a file--check for broken symbolic link")),
-        Err(e) => Err(e),
+        Ok(false) => Err(Error::invalid_file(s, "Could not access a file--check for broken symbolic link")),
+        Err(e) => Err(Error::Io(e)),
     }
 }
-
-fn get_py_file_contents(file_name: &str) -> Result<String, Error> {
-    match File::open(Path::new(is_str_valid_filepath(file_name)?)) {
-        Ok(mut file) => {
-            // create a new String and read the file contents into it
-            let mut s = String::new();
-            file.read_to_string(&mut s)?;
-
-            if s.is_empty() { // make sure string is not empty
-                Err(Error::new(ErrorKind::UnexpectedEof,String::from("Py file was empty...")))
-            } else {
-                Ok(s)}
-        },
-        Err(e) => {
-            println!("Failed to open file");
-            // can potentially use match to create different behavior depending on what kind of error occcured
-            match e.kind() {
-                ErrorKind::NotFound => println!("File not Found"),
-                ErrorKind::PermissionDenied => println!("Permission Denied"),
-                _ => println!("Unknown Error Occured: {}", e),
-            }
-            Err(e)
-        }
-    }
-}
\ No newline at end of file